@@ -1,6 +1,8 @@
 extern crate name_match;
-use criterion::{criterion_group, criterion_main, Criterion};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
 use name_match::jaro::compare::JaroWinklerMatcher;
+use name_match::jaro::compute as jaro_compute;
+use name_match::levenshtein::compute as levenshtein_compute;
 use name_match::soundex::compare::SoundexJaccardMatcher;
 use name_match::prelude::*;
 
@@ -23,5 +25,77 @@ fn criterion_benchmark(c: &mut Criterion) {
     c.bench_function("soundex_jaccard", |b| b.iter(|| soundex_jaccard(name1, name2)));
 }
 
-criterion_group!(benches, criterion_benchmark);
-criterion_main!(benches);
\ No newline at end of file
+// Bit-parallel kernels against the scalar implementations they replace,
+// on a short name pair and on a pair longer than 64 characters.
+fn kernel_benchmark(c: &mut Criterion) {
+    let short = ("JOHN DOE CHRISTOPHER", "JON DOE CHRISTOPHERS");
+    let long = (
+        "MARIA DEL CARMEN DE LOS ANGELES GUADALUPE FERNANDEZ DE LA TORRE Y ALVAREZ",
+        "MARIA DEL CARMEN DE LOS ANGELES GUADALUPE HERNANDEZ DE LA TORRE ALVARES",
+    );
+
+    for (label, (s1, s2)) in [("short", short), ("long", long)] {
+        c.bench_function(&format!("jaro/scalar/{}", label), |b| {
+            b.iter(|| jaro_compute::jaro_score_scalar(black_box(s1), black_box(s2)))
+        });
+        c.bench_function(&format!("jaro/bit_parallel/{}", label), |b| {
+            b.iter(|| jaro_compute::jaro_score(black_box(s1), black_box(s2)))
+        });
+
+        let chars_1: Vec<char> = s1.chars().collect();
+        let chars_2: Vec<char> = s2.chars().collect();
+        c.bench_function(&format!("levenshtein/scalar/{}", label), |b| {
+            b.iter(|| {
+                levenshtein_compute::levenshtein_distance_scalar(
+                    black_box(&chars_1),
+                    black_box(&chars_2),
+                )
+            })
+        });
+        c.bench_function(&format!("levenshtein/bit_parallel/{}", label), |b| {
+            b.iter(|| {
+                levenshtein_compute::levenshtein_distance_chars(
+                    black_box(&chars_1),
+                    black_box(&chars_2),
+                )
+            })
+        });
+    }
+}
+
+// One query against many candidates, scoring name pairs against reusing
+// the prepared query.
+fn query_benchmark(c: &mut Criterion) {
+    let matcher = JaroWinklerMatcher::default();
+    let query = "JOHN DOE CHRISTOPHER";
+    let candidates: Vec<String> = (0..1000)
+        .map(|i| format!("JON DOE CHRISTOPHER {}", i))
+        .collect();
+    let prepared: Vec<PreparedName> = candidates
+        .iter()
+        .map(|name| PreparedName::new(name))
+        .collect();
+
+    c.bench_function("query/get_score", |b| {
+        b.iter(|| {
+            candidates
+                .iter()
+                .map(|name| matcher.get_score(query, name))
+                .sum::<f64>()
+        })
+    });
+    c.bench_function("query/prepare_query", |b| {
+        b.iter(|| {
+            let scorer = matcher.prepare_query(query);
+            prepared.iter().map(|name| scorer.score(name)).sum::<f64>()
+        })
+    });
+}
+
+criterion_group!(
+    benches,
+    criterion_benchmark,
+    kernel_benchmark,
+    query_benchmark
+);
+criterion_main!(benches);
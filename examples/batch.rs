@@ -0,0 +1,36 @@
+extern crate name_match;
+
+use name_match::batch::{BatchMatcher, Column};
+use name_match::ensemble::Ensemble;
+use name_match::jaro::compare::JaroWinklerMatcher;
+use name_match::prelude::*;
+use name_match::soundex::compare::SoundexMatcher;
+use std::io;
+
+fn main() {
+    let matchers: Vec<Box<dyn Matcher>> = vec![
+        Box::new(JaroWinklerMatcher::default()),
+        Box::new(SoundexMatcher::default()),
+    ];
+    let mut ensemble = Ensemble::new(matchers);
+    ensemble.set_equal_weight();
+
+    // Files work the same way, eg File::open("queries.csv").
+    let queries = "id,name\n1,Jon Doe\n2,Mary Smyth\n";
+    let references = "ref,first_name,last_name\nA,John,Doe\nB,Mary,Smith\nC,Peter,Parker\n";
+
+    let mut batch = BatchMatcher::new();
+    batch.min_score = Some(0.8);
+    let name = vec![Column::from("name")];
+    let full_name = vec![Column::from("first_name"), Column::from("last_name")];
+    batch
+        .match_references(
+            &ensemble,
+            queries.as_bytes(),
+            &name,
+            references.as_bytes(),
+            &full_name,
+            io::stdout(),
+        )
+        .expect("batch failed");
+}
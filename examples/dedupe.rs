@@ -0,0 +1,27 @@
+extern crate name_match;
+
+use name_match::dedupe::{Clustering, Deduplicator};
+use name_match::ensemble::Ensemble;
+use name_match::jaro::compare::JaroWinklerMatcher;
+use name_match::prelude::*;
+
+fn main() {
+    let matchers: Vec<Box<dyn Matcher>> = vec![Box::new(JaroWinklerMatcher::default())];
+    let ensemble = Ensemble::new(matchers);
+    let names = vec![
+        "John Doe",
+        "Mary Smith",
+        "Jon Doe",
+        "Mary Smyth",
+        "Doe John",
+        "Peter Parker",
+    ];
+
+    // Center clustering avoids chaining loosely related names together.
+    let mut deduplicator = Deduplicator::new(0.9);
+    deduplicator.clustering = Clustering::Center;
+    for cluster in deduplicator.cluster(&ensemble, &names) {
+        let members: Vec<&str> = cluster.ids.iter().map(|id| names[*id]).collect();
+        println!("{} <- {:?}", names[cluster.representative], members);
+    }
+}
@@ -28,6 +28,10 @@ fn main(){
     println!("Score = {}", score);
 
     let name_list = vec!["James Hardy", "David James"];
-    let result_arr = ensemble.get_ensemble_result_arr(name_1, name_list);
+    let result_arr = ensemble.get_ensemble_result_arr(name_1, name_list.clone());
     println!("EnsembleResult: {:?}", result_arr);
+
+    // Best match only, sorted by score.
+    let top_k = ensemble.get_top_k(name_1, name_list, 1);
+    println!("Top 1: {:?}", top_k);
 }
\ No newline at end of file
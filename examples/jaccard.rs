@@ -12,4 +12,10 @@ fn main(){
         compare::JaccardMatcher::default();
     let score = name_matcher.get_score(name_1, name_2);
     println!("Jaccard Similarity = {}", score);
+
+    // Segmented tokenization merges and splits tokens against the other name.
+    let mut name_matcher = compare::JaccardMatcher::default();
+    name_matcher.set_tokenization(Tokenization::Segmented);
+    let score = name_matcher.get_score("Mary Ann Doe", "Maryann Doe");
+    println!("Jaccard Similarity (Segmented) = {}", score);
 }
\ No newline at end of file
@@ -2,6 +2,7 @@ extern crate name_match;
 
 use name_match::jaro::compare;
 use name_match::jaro::config;
+use name_match::jaro::similar;
 use name_match::prelude::*;
 
 fn main() {
@@ -21,5 +22,21 @@ fn main() {
     let jw_config = config::JaroWinklerConfigOptions::new(0.6, 4, 0.1);
     let name_matcher = compare::JaroWinklerMatcher::new(Some(jw_config), None::<f64>);
     let score = name_matcher.get_score(name_1, name_2);
+    println!("Jaro-Winkler Similarity = {}", score);
+
+    // Name-order search
+    // Family name may come first or last depending on the source.
+    let mut jw_config = config::JaroWinklerConfigOptions::default();
+    jw_config.max_permutation_tokens = Some(3);
+    let name_matcher = compare::JaroWinklerMatcher::new(Some(jw_config), None::<f64>);
+    let result = name_matcher.get_match_result("Nagy Istvan", "Istvan Nagy");
+    println!("Jaro-Winkler Similarity = {} ({:?})", result.absolute_score, result.ordering);
+
+    // Similar characters
+    // Keying and OCR errors earn partial credit.
+    let mut jw_config = config::JaroWinklerConfigOptions::default();
+    jw_config.similar_chars = Some(similar::SimilarCharTable::census());
+    let name_matcher = compare::JaroWinklerMatcher::new(Some(jw_config), None::<f64>);
+    let score = name_matcher.get_score("Snith", "Smith");
     println!("Jaro-Winkler Similarity = {}", score)
 }
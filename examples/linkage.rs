@@ -0,0 +1,27 @@
+extern crate name_match;
+
+use name_match::ensemble::Ensemble;
+use name_match::jaro::compare::JaroWinklerMatcher;
+use name_match::linkage::{Assignment, Linker};
+use name_match::prelude::*;
+
+fn main() {
+    let matchers: Vec<Box<dyn Matcher>> = vec![Box::new(JaroWinklerMatcher::default())];
+    let ensemble = Ensemble::new(matchers);
+    let vendor = vec!["John Doe", "Mary Smith", "Bruce Wayne"];
+    let master = vec!["Peter Parker", "Jon Doe", "Mary Smyth", "Marie Smith"];
+
+    // Optimal assignment maximises the total score of links.
+    let mut linker = Linker::new(0.85);
+    linker.assignment = Assignment::Optimal;
+    let linkage = linker.link(&ensemble, &vendor, &master);
+    for link in linkage.links.iter() {
+        println!(
+            "{} -> {} = {}",
+            vendor[link.left], master[link.right], link.score
+        );
+    }
+    println!("Unmatched vendor records: {:?}", linkage.unmatched_left);
+    println!("Unmatched master records: {:?}", linkage.unmatched_right);
+    println!("Ties: {:?}", linkage.ties);
+}
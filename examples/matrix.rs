@@ -0,0 +1,28 @@
+extern crate name_match;
+
+use name_match::jaro::compare::JaroWinklerMatcher;
+use name_match::matrix::SimilarityMatrix;
+
+fn main() {
+    let matcher = JaroWinklerMatcher::default();
+    let rows = vec!["John Doe", "Mary Smith", "Peter Parker"];
+    let cols = vec!["Jon Doe", "Mary Smyth", "Doe John", "Pete Parker"];
+    let matrix = SimilarityMatrix::new();
+
+    let dense = matrix.dense(&matcher, &rows, &cols);
+    println!("{:?}", dense.row(0));
+
+    // Rows are streamed in order, without holding the whole matrix.
+    matrix.for_each_row(&matcher, &rows, &cols, |row, scores| {
+        println!("{}: {:?}", rows[row], scores);
+    });
+
+    // Only scores of at least 0.9 are kept.
+    let sparse = matrix.sparse(&matcher, &rows, &cols, 0.9);
+    for (row, name) in rows.iter().enumerate() {
+        let (col_ids, scores) = sparse.row(row);
+        for (col, score) in col_ids.iter().zip(scores.iter()) {
+            println!("{} ~ {}: {}", name, cols[*col], score);
+        }
+    }
+}
@@ -0,0 +1,22 @@
+extern crate name_match;
+
+use name_match::index::bktree::BKTree;
+use name_match::index::metric::{DamerauLevenshtein, NormalizedLevenshtein};
+use name_match::index::vptree::VPTree;
+
+fn main() {
+    let names = vec!["John Doe", "Jon Doe", "Jhon Doe", "Jane Doe", "Mary Smith"];
+
+    // BK-tree: every name within 1 edit of the query.
+    let mut bktree = BKTree::build(DamerauLevenshtein, names.clone());
+    bktree.insert("John Dow");
+    for neighbour in bktree.find("John Doe", 1) {
+        println!("BK-tree: {} ({})", neighbour.name, neighbour.distance);
+    }
+
+    // VP-tree: 2 names closest to the query using normalized Levenshtein.
+    let vptree = VPTree::build(NormalizedLevenshtein, names);
+    for neighbour in vptree.nearest("Jane Do", 2) {
+        println!("VP-tree: {} ({})", neighbour.name, neighbour.distance);
+    }
+}
@@ -0,0 +1,20 @@
+extern crate name_match;
+
+use name_match::index::minhash::{self, LshIndex, MinHasher, TokenSource};
+
+fn main() {
+    // MinHash signatures approximate the Jaccard Index of 2-gram sets.
+    let hasher = MinHasher::new(128, 42);
+    let source = TokenSource::QGrams(2);
+    let signature_1 = hasher.signature(&source.token_set("Christopher Columbus"));
+    let signature_2 = hasher.signature(&source.token_set("Kristopher Colombus"));
+    let estimate = minhash::estimate_jaccard(&signature_1, &signature_2);
+    println!("Estimated Jaccard = {}", estimate);
+
+    // LSH buckets tuned for a Jaccard threshold of 0.5.
+    let mut index = LshIndex::with_threshold(source, 0.5, 128, 42);
+    index.extend(vec!["Christopher Columbus", "Mary Smith", "Peter Parker"]);
+    for (id, estimate) in index.candidates("Kristopher Colombus") {
+        println!("{} = {}", index.get(id).unwrap(), estimate);
+    }
+}
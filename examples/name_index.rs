@@ -0,0 +1,37 @@
+extern crate name_match;
+
+use name_match::blocking::keys::BlockingKey;
+use name_match::ensemble::Ensemble;
+use name_match::index::name_index::NameIndex;
+use name_match::jaro::compare::JaroWinklerMatcher;
+use name_match::prelude::*;
+use name_match::soundex::compare::SoundexJaccardMatcher;
+
+fn main() {
+    let matchers: Vec<Box<dyn Matcher>> = vec![
+        Box::new(JaroWinklerMatcher::default()),
+        Box::new(SoundexJaccardMatcher::default()),
+    ];
+    let mut ensemble = Ensemble::new(matchers);
+    ensemble.set_equal_weight();
+
+    // Ingest the corpus once.
+    // Names are grouped by Soundex code, 3-grams and sorted token prefix.
+    let corpus = vec!["James Bond", "Jim Bond", "Bond, James", "David James", "Mary Smith"];
+    let mut index = NameIndex::build(
+        vec![
+            BlockingKey::Soundex,
+            BlockingKey::QGram(3),
+            BlockingKey::SortedTokenPrefix(4),
+        ],
+        corpus,
+    );
+    // Candidates must share at least 2 blocking keys with the query.
+    index.min_shared_keys = 2;
+
+    // Only candidates are scored by the ensemble.
+    let results = index.search(&ensemble, "Jmes Bond", 3, 0.5);
+    for result in results.iter() {
+        println!("{} = {}", result.name2, result.score);
+    }
+}
@@ -0,0 +1,40 @@
+extern crate name_match;
+
+use name_match::ensemble::Ensemble;
+use name_match::index::bktree::BKTree;
+use name_match::index::mapped::MappedNameIndex;
+use name_match::index::metric::Levenshtein;
+use name_match::index::name_index::NameIndex;
+use name_match::jaro::compare::JaroWinklerMatcher;
+use name_match::prelude::*;
+
+fn main() {
+    let names = vec![
+        "John Doe",
+        "Jon Doe",
+        "Mary Smith",
+        "Doe, John",
+        "Peter Parker",
+    ];
+    let directory = std::env::temp_dir();
+
+    // Build once, then reopen the memory mapped file on every start.
+    let path = directory.join("customers.idx");
+    let mut built = NameIndex::default();
+    built.extend(names.clone());
+    built.save(&path).unwrap();
+    let index = MappedNameIndex::open(&path).unwrap();
+    println!("{} -> {:?}", index.get(3).unwrap(), index.soundex(3));
+
+    let matchers: Vec<Box<dyn Matcher>> = vec![Box::new(JaroWinklerMatcher::default())];
+    let ensemble = Ensemble::new(matchers);
+    for result in index.search(&ensemble, "Jhon Doe", 3, 0.8) {
+        println!("{} = {}", result.name2, result.score);
+    }
+
+    // Other indices are snapshots, decoded whole onto the heap by load.
+    let tree_path = directory.join("customers.bktree");
+    BKTree::build(Levenshtein, names).save(&tree_path).unwrap();
+    let tree: BKTree<Levenshtein> = BKTree::load(&tree_path).unwrap();
+    println!("{:?}", tree.find("Jon Do", 1));
+}
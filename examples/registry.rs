@@ -0,0 +1,84 @@
+extern crate name_match;
+
+use name_match::prelude::*;
+use name_match::registry::MatcherRegistry;
+use name_match::spec::Params;
+
+// InitialsMatcher scores 1.0 when both names have the same initials.
+struct InitialsMatcher {
+    weight: f64,
+    min_tokens: usize,
+}
+
+impl InitialsMatcher {
+    fn initials(&self, name: &str) -> String {
+        self.clean(name)
+            .split_whitespace()
+            .filter_map(|token| token.chars().next())
+            .collect()
+    }
+}
+
+impl Named for InitialsMatcher {
+    fn get_name(&self) -> &str {
+        "Initials"
+    }
+}
+
+impl Clean for InitialsMatcher {}
+
+impl Weighted for InitialsMatcher {
+    fn get_weight(&self) -> f64 {
+        self.weight
+    }
+
+    fn set_weight(&mut self, weight: f64) {
+        self.weight = weight;
+    }
+}
+
+impl Matcher for InitialsMatcher {
+    fn get_score(&self, s1: &str, s2: &str) -> f64 {
+        let (initials1, initials2) = (self.initials(s1), self.initials(s2));
+        if initials1.len() >= self.min_tokens && initials1 == initials2 {
+            1.0
+        } else {
+            0.0
+        }
+    }
+}
+
+fn main() {
+    let mut registry = MatcherRegistry::new();
+    registry.register("Initials", |spec| {
+        spec.check_params(&["min_tokens"])?;
+        Ok(Box::new(InitialsMatcher {
+            weight: 1.0,
+            min_tokens: spec.usize_param("min_tokens")?.unwrap_or(2),
+        }))
+    });
+    println!("{:?}", registry.names());
+
+    // Names are looked up like get_name returns them, case and punctuation
+    // aside.
+    let names = ["jaro-winkler", "Token-Sort(Soundex-Jaccard)", "initials"];
+    for name in names.iter() {
+        let matcher = registry.get(name, &Params::new()).unwrap();
+        println!(
+            "{}: {}",
+            matcher.get_name(),
+            matcher.get_score("John Ronald Doe", "Jon R Doe")
+        );
+    }
+
+    let mut params = Params::new();
+    params.insert("min_tokens".to_owned(), ParamValue::Text("two".to_owned()));
+    match registry.get("Initials", &params) {
+        Ok(_) => println!("built"),
+        Err(err) => println!("error: {}", err),
+    }
+    match registry.get("Metaphone", &Params::new()) {
+        Ok(_) => println!("built"),
+        Err(err) => println!("error: {}", err),
+    }
+}
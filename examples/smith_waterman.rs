@@ -0,0 +1,25 @@
+extern crate name_match;
+
+use name_match::prelude::*;
+use name_match::smith_waterman::compare;
+use name_match::smith_waterman::config;
+
+fn main() {
+    let name_1 = "PAYMENT TO JOHN A DOE REF 123";
+    let name_2 = "John Doe";
+
+    // Default implementation using
+    // Match Score = 2.0
+    // Mismatch Score = -1.0
+    // Gap Open = -1.5
+    // Gap Extend = -0.5
+    let name_matcher = compare::SmithWatermanMatcher::default();
+    let score = name_matcher.get_score(name_1, name_2);
+    println!("Smith-Waterman Similarity = {}", score);
+
+    // Custom config
+    let sw_config = config::SmithWatermanConfigOptions::new(1.0, -1.0, -2.0, -1.0);
+    let name_matcher = compare::SmithWatermanMatcher::new(Some(sw_config), None::<f64>);
+    let score = name_matcher.get_score(name_1, name_2);
+    println!("Smith-Waterman Similarity = {}", score)
+}
@@ -0,0 +1,47 @@
+extern crate name_match;
+
+use name_match::blocking::sorted_neighbourhood::{SortKey, SortedNeighbourhood};
+use name_match::dedupe::Deduplicator;
+use name_match::ensemble::Ensemble;
+use name_match::jaro::compare::JaroWinklerMatcher;
+use name_match::linkage::Linker;
+use name_match::prelude::*;
+
+fn main() {
+    let matchers: Vec<Box<dyn Matcher>> = vec![Box::new(JaroWinklerMatcher::default())];
+    let ensemble = Ensemble::new(matchers);
+
+    // Two passes: surname Soundex + first initial, then the reversed name.
+    let method = SortedNeighbourhood::new(
+        3,
+        vec![
+            vec![SortKey::SurnameSoundex, SortKey::FirstInitial],
+            vec![SortKey::ReversedTokens],
+        ],
+    );
+
+    let names = vec![
+        "John Doe",
+        "Mary Smith",
+        "Jon Doe",
+        "Mary Smyth",
+        "Peter Parker",
+    ];
+    let pairs = method.pairs(&names);
+    let clusters = Deduplicator::new(0.9).cluster_candidates(&ensemble, &names, &pairs);
+    for cluster in clusters {
+        let members: Vec<&str> = cluster.ids.iter().map(|id| names[*id]).collect();
+        println!("{:?}", members);
+    }
+
+    let left = vec!["John Doe", "Mary Smith"];
+    let right = vec!["Marie Smith", "Jon Doe"];
+    let pairs = method.linkage_pairs(&left, &right);
+    let linkage = Linker::new(0.85).link_candidates(&ensemble, &left, &right, &pairs);
+    for link in linkage.links {
+        println!(
+            "{} -> {} = {}",
+            left[link.left], right[link.right], link.score
+        );
+    }
+}
@@ -0,0 +1,31 @@
+extern crate name_match;
+
+use name_match::ensemble::Ensemble;
+use name_match::spec::EnsembleSpec;
+
+fn main() {
+    // Files work the same way, eg EnsembleSpec::load("ensemble.toml").
+    let spec = EnsembleSpec::from_toml(
+        r#"
+        aggregation = "WeightedMean"
+
+        [[matchers]]
+        type = "Jaro-Winkler"
+        weight = 0.6
+        params = { scaling_factor = 0.15, similar_chars = "census" }
+
+        [[matchers]]
+        type = "Token-Sort"
+        weight = 0.4
+        matcher = { type = "Soundex-Jaccard", params = { tokenization = "Segmented" } }
+        "#,
+    )
+    .expect("invalid spec");
+    let ensemble = Ensemble::from_spec(&spec).expect("invalid matcher");
+
+    let result = ensemble.get_ensemble_result("Mary Ann Smith", "Smith Maryann");
+    println!("{:?}", result);
+
+    // The spec of an ensemble lists every parameter, defaults included.
+    println!("{}", ensemble.to_spec().to_json().unwrap());
+}
@@ -0,0 +1,21 @@
+extern crate name_match;
+
+use name_match::ensemble::Ensemble;
+use name_match::jaro::compare::JaroWinklerMatcher;
+use name_match::prelude::*;
+use name_match::stream::Streamer;
+use std::io::Cursor;
+
+fn main() {
+    let matchers: Vec<Box<dyn Matcher>> = vec![Box::new(JaroWinklerMatcher::default())];
+    let ensemble = Ensemble::new(matchers);
+
+    // Any BufRead works, eg a BufReader over a file or stdin.
+    let reader = Cursor::new("Jon Doe\nMary Smith\nJohn Doe\nPeter Parker\n");
+    let mut streamer = Streamer::new();
+    streamer.min_score = Some(0.9);
+    for result in streamer.lines(&ensemble, "John Doe", reader) {
+        let (line, result) = result.expect("failed to read names");
+        println!("{}: {} {}", line, result.name2, result.score);
+    }
+}
@@ -0,0 +1,27 @@
+extern crate name_match;
+
+use name_match::ensemble::Ensemble;
+use name_match::index::symspell::SymSpellNameIndex;
+use name_match::jaro::compare::JaroWinklerMatcher;
+use name_match::prelude::*;
+
+fn main() {
+    // Tokens within 2 edits, deletes generated from the first 7 characters.
+    let index = SymSpellNameIndex::build(
+        2,
+        7,
+        vec!["Christopher Columbus", "Kristofer Colombo", "Christine Smith"],
+    );
+
+    // Typo-tolerant token lookup.
+    for suggestion in index.dictionary().lookup("CRISTOPHER", 2) {
+        println!("{} ({})", suggestion.token, suggestion.distance);
+    }
+
+    // Whole-name search, candidates re-scored with an ensemble.
+    let matchers: Vec<Box<dyn Matcher>> = vec![Box::new(JaroWinklerMatcher::default())];
+    let ensemble = Ensemble::new(matchers);
+    for result in index.search(&ensemble, "Cristopher Columbos", 2, 5, 0.8) {
+        println!("{} = {}", result.name2, result.score);
+    }
+}
@@ -0,0 +1,28 @@
+extern crate name_match;
+
+use name_match::jaro::compare::JaroWinklerMatcher;
+use name_match::prelude::*;
+use name_match::token::compare;
+
+fn main() {
+    let name_1 = "Doe, John Adam";
+    let name_2 = "John Doe";
+
+    // Token-Sort sorts the tokens of both names before comparison.
+    let token_sort_matcher =
+        compare::TokenSortMatcher::new(Box::new(JaroWinklerMatcher::default()), None);
+    let score = token_sort_matcher.get_score(name_1, name_2);
+    println!("Token-Sort(Jaro-Winkler) Similarity = {}", score);
+
+    // Token-Set compares the common tokens against the remaining tokens.
+    let token_set_matcher =
+        compare::TokenSetMatcher::new(Box::new(JaroWinklerMatcher::default()), None);
+    let score = token_set_matcher.get_score(name_1, name_2);
+    println!("Token-Set(Jaro-Winkler) Similarity = {}", score);
+
+    // Partial compares the shorter name against the best window of the longer name.
+    let partial_matcher =
+        compare::PartialMatcher::new(Box::new(JaroWinklerMatcher::default()), None);
+    let score = partial_matcher.get_score("PAYMENT TO JOHN DOE REF", name_2);
+    println!("Partial(Jaro-Winkler) Similarity = {}", score);
+}
@@ -0,0 +1,488 @@
+use super::ensemble::{self, Ensemble, EnsembleResult};
+use super::prelude::*;
+use rayon::prelude::*;
+use std::fmt;
+use std::io;
+
+// Column refers to a column of a CSV file, by header or by position.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Column {
+    Header(String),
+    Index(usize),
+}
+
+impl From<&str> for Column {
+    fn from(header: &str) -> Column {
+        Column::Header(header.to_owned())
+    }
+}
+
+impl From<usize> for Column {
+    fn from(index: usize) -> Column {
+        Column::Index(index)
+    }
+}
+
+// BatchError is returned when a batch can not be read, matched or written.
+#[derive(Debug)]
+pub enum BatchError {
+    Io(io::Error),
+    Csv(csv::Error),
+    MissingColumn(String),
+}
+
+impl fmt::Display for BatchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BatchError::Io(err) => write!(f, "io error: {}", err),
+            BatchError::Csv(err) => write!(f, "csv error: {}", err),
+            BatchError::MissingColumn(column) => write!(f, "missing column {}", column),
+        }
+    }
+}
+
+impl std::error::Error for BatchError {}
+
+impl From<io::Error> for BatchError {
+    fn from(err: io::Error) -> BatchError {
+        BatchError::Io(err)
+    }
+}
+
+impl From<csv::Error> for BatchError {
+    fn from(err: csv::Error) -> BatchError {
+        BatchError::Csv(err)
+    }
+}
+
+// BatchMatcher runs an Ensemble over CSV or TSV files.
+// A name is built from one or more columns of a row, joined by whitespace,
+// eg first name and last name columns.
+// Every output row holds the input fields followed by the aggregated score,
+// and the absolute and weighted score of every matcher.
+//   - delimiter: Field delimiter of input and output files, b'\t' for TSV.
+//   - has_headers: Input files start with a header row. Output files then
+//     start with a header row too.
+//   - chunk_size: Rows matched in parallel at a time, bounding memory.
+//   - k: Matches written per query, best first.
+//   - min_score: Matches of queries scoring below it are not written.
+pub struct BatchMatcher {
+    pub delimiter: u8,
+    pub has_headers: bool,
+    pub chunk_size: usize,
+    pub k: usize,
+    pub min_score: Option<f64>,
+}
+
+impl Default for BatchMatcher {
+    fn default() -> BatchMatcher {
+        BatchMatcher::new()
+    }
+}
+
+impl BatchMatcher {
+    pub fn new() -> BatchMatcher {
+        BatchMatcher {
+            delimiter: b',',
+            has_headers: true,
+            chunk_size: 4096,
+            k: 1,
+            min_score: None,
+        }
+    }
+
+    pub fn tsv() -> BatchMatcher {
+        BatchMatcher {
+            delimiter: b'\t',
+            ..BatchMatcher::new()
+        }
+    }
+
+    fn reader<R: io::Read>(&self, input: R) -> csv::Reader<R> {
+        csv::ReaderBuilder::new()
+            .delimiter(self.delimiter)
+            .has_headers(self.has_headers)
+            .from_reader(input)
+    }
+
+    fn writer<W: io::Write>(&self, output: W) -> csv::Writer<W> {
+        csv::WriterBuilder::new()
+            .delimiter(self.delimiter)
+            .from_writer(output)
+    }
+
+    // match_pairs scores the name held by left columns against the name held
+    // by right columns, for every row of input.
+    // Returns the number of rows written.
+    pub fn match_pairs<R: io::Read, W: io::Write>(
+        &self,
+        ensemble: &Ensemble,
+        input: R,
+        left: &[Column],
+        right: &[Column],
+        output: W,
+    ) -> Result<usize, BatchError> {
+        let mut reader = self.reader(input);
+        let mut writer = self.writer(output);
+        let headers = self.headers(&mut reader)?;
+        let left = resolve_columns(&headers, left)?;
+        let right = resolve_columns(&headers, right)?;
+        if self.has_headers {
+            let mut header_row: Vec<String> = headers.iter().map(|h| h.to_owned()).collect();
+            header_row.extend(score_headers(ensemble));
+            writer.write_record(&header_row)?;
+        }
+
+        let mut written = 0;
+        let mut records = reader.records();
+        loop {
+            let chunk = next_chunk(&mut records, self.chunk_size)?;
+            if chunk.is_empty() {
+                break;
+            }
+            let results: Vec<EnsembleResult> = chunk
+                .par_iter()
+                .map(|record| {
+                    let name1 = join_columns(record, &left);
+                    let name2 = join_columns(record, &right);
+                    ensemble.get_ensemble_result(&name1[..], &name2[..])
+                })
+                .collect();
+            for (record, result) in chunk.iter().zip(results.iter()) {
+                let mut row: Vec<String> = record.iter().map(|f| f.to_owned()).collect();
+                row.extend(score_fields(result));
+                writer.write_record(&row)?;
+                written += 1;
+            }
+        }
+        writer.flush()?;
+        Ok(written)
+    }
+
+    // match_references finds the k best matches of every query row among
+    // reference rows. Reference rows are held in memory, query rows are
+    // read in chunks.
+    // Output rows hold the query fields, the fields of the matched reference
+    // row, and scores. Queries without a match are written with empty
+    // reference fields and scores.
+    // Returns the number of rows written.
+    pub fn match_references<Q: io::Read, F: io::Read, W: io::Write>(
+        &self,
+        ensemble: &Ensemble,
+        queries: Q,
+        query_columns: &[Column],
+        references: F,
+        reference_columns: &[Column],
+        output: W,
+    ) -> Result<usize, BatchError> {
+        let mut reference_reader = self.reader(references);
+        let reference_headers = self.headers(&mut reference_reader)?;
+        let reference_columns = resolve_columns(&reference_headers, reference_columns)?;
+        let reference_records = reference_reader
+            .records()
+            .collect::<Result<Vec<csv::StringRecord>, csv::Error>>()?;
+        let reference_names: Vec<PreparedName> = reference_records
+            .par_iter()
+            .map(|record| PreparedName::new(&join_columns(record, &reference_columns)))
+            .collect();
+
+        let mut reader = self.reader(queries);
+        let mut writer = self.writer(output);
+        let headers = self.headers(&mut reader)?;
+        let query_columns = resolve_columns(&headers, query_columns)?;
+        if self.has_headers {
+            let mut header_row: Vec<String> = headers.iter().map(|h| h.to_owned()).collect();
+            header_row.extend(reference_headers.iter().map(|h| format!("match_{}", h)));
+            header_row.extend(score_headers(ensemble));
+            writer.write_record(&header_row)?;
+        }
+        let empty_fields = reference_headers.len() + 1 + 2 * ensemble.matchers.len();
+
+        let mut written = 0;
+        let mut records = reader.records();
+        loop {
+            let chunk = next_chunk(&mut records, self.chunk_size)?;
+            if chunk.is_empty() {
+                break;
+            }
+            let matches: Vec<Vec<(usize, EnsembleResult)>> = chunk
+                .par_iter()
+                .map(|record| {
+                    let query = ensemble.prepare_query(&join_columns(record, &query_columns));
+                    let results =
+                        reference_names
+                            .par_iter()
+                            .enumerate()
+                            .filter_map(|(id, name)| {
+                                let result = match self.min_score {
+                                    Some(min_score) => {
+                                        query.get_ensemble_result_above(name, min_score)?
+                                    }
+                                    None => query.get_ensemble_result(name),
+                                };
+                                Some((id, result))
+                            });
+                    // The k best matches, ties broken by reference position.
+                    ensemble::top_k(results, self.k)
+                })
+                .collect();
+
+            for (record, record_matches) in chunk.iter().zip(matches.iter()) {
+                let query_fields: Vec<String> = record.iter().map(|f| f.to_owned()).collect();
+                if record_matches.is_empty() {
+                    let mut row = query_fields.clone();
+                    row.extend(std::iter::repeat_n(String::new(), empty_fields));
+                    writer.write_record(&row)?;
+                    written += 1;
+                }
+                for (id, result) in record_matches.iter() {
+                    let mut row = query_fields.clone();
+                    row.extend(reference_records[*id].iter().map(|f| f.to_owned()));
+                    row.extend(score_fields(result));
+                    writer.write_record(&row)?;
+                    written += 1;
+                }
+            }
+        }
+        writer.flush()?;
+        Ok(written)
+    }
+
+    // headers returns the header row, or positions when the input has no
+    // headers. Positions are only known once the first row is read.
+    fn headers<R: io::Read>(&self, reader: &mut csv::Reader<R>) -> Result<Vec<String>, BatchError> {
+        let headers = reader.headers()?;
+        if self.has_headers {
+            return Ok(headers.iter().map(|h| h.to_owned()).collect());
+        }
+        Ok((0..headers.len()).map(|i| i.to_string()).collect())
+    }
+}
+
+fn next_chunk<R: io::Read>(
+    records: &mut csv::StringRecordsIter<R>,
+    chunk_size: usize,
+) -> Result<Vec<csv::StringRecord>, BatchError> {
+    let mut chunk = Vec::new();
+    for record in records.take(chunk_size.max(1)) {
+        chunk.push(record?);
+    }
+    Ok(chunk)
+}
+
+// resolve_columns returns the position of every column.
+fn resolve_columns(headers: &[String], columns: &[Column]) -> Result<Vec<usize>, BatchError> {
+    columns
+        .iter()
+        .map(|column| match column {
+            Column::Header(header) => headers
+                .iter()
+                .position(|other| other == header)
+                .ok_or_else(|| BatchError::MissingColumn(header.to_owned())),
+            Column::Index(index) if *index < headers.len() => Ok(*index),
+            Column::Index(index) => Err(BatchError::MissingColumn(index.to_string())),
+        })
+        .collect()
+}
+
+fn join_columns(record: &csv::StringRecord, columns: &[usize]) -> String {
+    columns
+        .iter()
+        .filter_map(|column| record.get(*column))
+        .map(|field| field.trim())
+        .filter(|field| !field.is_empty())
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+fn score_headers(ensemble: &Ensemble) -> Vec<String> {
+    let mut headers = vec!["score".to_owned()];
+    for matcher in ensemble.matchers.iter() {
+        headers.push(format!("{}_absolute_score", matcher.get_name()));
+        headers.push(format!("{}_weighted_score", matcher.get_name()));
+    }
+    headers
+}
+
+fn score_fields(result: &EnsembleResult) -> Vec<String> {
+    let mut fields = vec![result.score.to_string()];
+    for match_result in result.results.iter() {
+        fields.push(match_result.absolute_score.to_string());
+        fields.push(match_result.weighted_score.to_string());
+    }
+    fields
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::ensemble::Ensemble;
+    use super::super::jaccard::compare::JaccardMatcher;
+    use super::super::jaro::compare::JaroWinklerMatcher;
+    use super::super::prelude::*;
+    use super::super::soundex::compare::SoundexMatcher;
+    use super::{BatchError, BatchMatcher, Column};
+
+    fn build_ensemble() -> Ensemble {
+        let matchers: Vec<Box<dyn Matcher>> = vec![
+            Box::new(JaroWinklerMatcher::default()),
+            Box::new(SoundexMatcher::default()),
+        ];
+        let mut ensemble = Ensemble::new(matchers);
+        ensemble.set_equal_weight();
+        ensemble
+    }
+
+    fn read_rows(output: Vec<u8>, delimiter: u8) -> Vec<Vec<String>> {
+        csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .has_headers(false)
+            .from_reader(&output[..])
+            .records()
+            .map(|record| record.unwrap().iter().map(|f| f.to_owned()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_match_pairs() {
+        let ensemble = build_ensemble();
+        let input = "id,first,last,other\n1,John,Doe,Jon Doe\n2,Mary,Smith,Peter Parker\n";
+        let mut output = Vec::new();
+        let mut batch = BatchMatcher::new();
+        batch.chunk_size = 1;
+        let left = vec![Column::from("first"), Column::from("last")];
+        let written = batch
+            .match_pairs(
+                &ensemble,
+                input.as_bytes(),
+                &left,
+                &["other".into()],
+                &mut output,
+            )
+            .unwrap();
+        assert_eq!(written, 2);
+
+        let rows = read_rows(output, b',');
+        assert_eq!(
+            rows[0],
+            vec![
+                "id",
+                "first",
+                "last",
+                "other",
+                "score",
+                "Jaro-Winkler_absolute_score",
+                "Jaro-Winkler_weighted_score",
+                "Soundex_absolute_score",
+                "Soundex_weighted_score",
+            ]
+        );
+        let score: f64 = rows[1][4].parse().unwrap();
+        assert_eq!(score, ensemble.get_aggregated_score("John Doe", "Jon Doe"));
+        assert_eq!(rows[1][7], "1");
+        assert_eq!(rows[1][8], "0.5");
+        assert_eq!(rows[2][0], "2");
+    }
+
+    #[test]
+    fn test_match_pairs_tsv_without_headers() {
+        let ensemble = build_ensemble();
+        let input = "John Doe\tJohn Doe\n";
+        let mut output = Vec::new();
+        let mut batch = BatchMatcher::tsv();
+        batch.has_headers = false;
+        batch
+            .match_pairs(
+                &ensemble,
+                input.as_bytes(),
+                &[0.into()],
+                &[1.into()],
+                &mut output,
+            )
+            .unwrap();
+        let rows = read_rows(output, b'\t');
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0][2], "1");
+    }
+
+    #[test]
+    fn test_missing_column() {
+        let ensemble = build_ensemble();
+        let input = "first,last\nJohn,Doe\n";
+        let result = BatchMatcher::new().match_pairs(
+            &ensemble,
+            input.as_bytes(),
+            &["first".into()],
+            &["surname".into()],
+            Vec::new(),
+        );
+        match result {
+            Err(BatchError::MissingColumn(column)) => assert_eq!(column, "surname"),
+            _ => panic!("expected missing column"),
+        }
+    }
+
+    #[test]
+    fn test_match_references() {
+        let ensemble = build_ensemble();
+        let queries = "query_id,name\nq1,Jon Doe\nq2,Zed Zulu\n";
+        let references = "ref_id,first,last\nr1,Mary,Smith\nr2,John,Doe\nr3,Jane,Doe\n";
+        let mut output = Vec::new();
+        let mut batch = BatchMatcher::new();
+        batch.k = 2;
+        batch.min_score = Some(0.8);
+        let written = batch
+            .match_references(
+                &ensemble,
+                queries.as_bytes(),
+                &["name".into()],
+                references.as_bytes(),
+                &["first".into(), "last".into()],
+                &mut output,
+            )
+            .unwrap();
+        assert_eq!(written, 3);
+
+        let rows = read_rows(output, b',');
+        assert_eq!(
+            rows[0][..5],
+            [
+                "query_id",
+                "name",
+                "match_ref_id",
+                "match_first",
+                "match_last"
+            ]
+        );
+        assert_eq!(rows[1][..3], ["q1", "Jon Doe", "r2"]);
+        assert_eq!(rows[2][..3], ["q1", "Jon Doe", "r3"]);
+        assert_eq!(rows[3][0], "q2");
+        assert!(rows[3][2..].iter().all(|field| field.is_empty()));
+        assert_eq!(rows[3].len(), rows[0].len());
+    }
+
+    #[test]
+    fn test_match_references_nan_last() {
+        // Jaccard scores two empty names as NaN, other references score 0.
+        let matchers: Vec<Box<dyn Matcher>> = vec![Box::new(JaccardMatcher::default())];
+        let ensemble = Ensemble::new(matchers);
+        let queries = "query_id,name\nq1,\n";
+        let references = "ref_id,name\nr1,\nr2,John\nr3,Mary\n";
+        let mut output = Vec::new();
+        let mut batch = BatchMatcher::new();
+        batch.k = 2;
+        batch
+            .match_references(
+                &ensemble,
+                queries.as_bytes(),
+                &["name".into()],
+                references.as_bytes(),
+                &["name".into()],
+                &mut output,
+            )
+            .unwrap();
+        let rows = read_rows(output, b',');
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[1][2], "r2");
+        assert_eq!(rows[2][2], "r3");
+    }
+}
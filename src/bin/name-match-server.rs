@@ -0,0 +1,73 @@
+extern crate name_match;
+
+use clap::Parser;
+use name_match::input;
+use name_match::server::{MatchService, Server, DEFAULT_MAX_BODY_BYTES};
+use std::process;
+use std::sync::Arc;
+
+// Serve name matching over HTTP, see name_match::server for the endpoints.
+// The ensemble is described by an EnsembleSpec file in JSON, TOML or YAML,
+// read again when the process receives SIGHUP.
+#[derive(Parser)]
+#[command(
+    name = "name-match-server",
+    version,
+    about = "Serve name matching over HTTP/JSON"
+)]
+struct Cli {
+    /// JSON, TOML or YAML file describing the ensemble, reloaded on SIGHUP
+    #[arg(short, long, value_name = "FILE")]
+    config: String,
+
+    /// Address to listen on
+    #[arg(short, long, default_value = "127.0.0.1:8080")]
+    address: String,
+
+    /// Names searched by /search requests without names, one per line
+    #[arg(short, long, value_name = "FILE")]
+    names: Option<String>,
+
+    /// Number of threads answering requests
+    #[arg(short, long, default_value_t = 4)]
+    workers: usize,
+
+    /// Largest request body in bytes, larger requests are answered with 413
+    #[arg(long, value_name = "BYTES", default_value_t = DEFAULT_MAX_BODY_BYTES)]
+    max_body: usize,
+}
+
+fn main() {
+    let cli = Cli::parse();
+    if let Err(err) = run(cli) {
+        eprintln!("error: {}", err);
+        process::exit(1);
+    }
+}
+
+fn run(cli: Cli) -> Result<(), String> {
+    let mut service =
+        MatchService::load(&cli.config).map_err(|err| format!("{}: {}", cli.config, err))?;
+    if let Some(path) = &cli.names {
+        service = service.with_names(input::read_names(path).map_err(|err| err.to_string())?);
+    }
+    let service = Arc::new(service);
+
+    #[cfg(unix)]
+    {
+        let path = cli.config.clone();
+        name_match::server::reload_on_sighup(Arc::clone(&service), move |err| {
+            eprintln!("error: reloading {}: {}", path, err)
+        })
+        .map_err(|err| err.to_string())?;
+    }
+
+    let mut server = Server::bind(&cli.address, service).map_err(|err| err.to_string())?;
+    server.max_body_bytes = cli.max_body;
+    match server.local_addr() {
+        Some(addr) => eprintln!("listening on http://{}", addr),
+        None => eprintln!("listening on {}", cli.address),
+    }
+    server.run(cli.workers);
+    Ok(())
+}
@@ -0,0 +1,335 @@
+extern crate name_match;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use name_match::blocking::keys::BlockingKey;
+use name_match::dedupe::{Clustering, Deduplicator};
+use name_match::ensemble::{Ensemble, EnsembleResult};
+use name_match::input;
+use name_match::prelude::*;
+use name_match::soundex::compare::SoundexMatcher;
+use name_match::soundex::encode;
+use name_match::spec::EnsembleSpec;
+use serde::Serialize;
+use std::process;
+
+// Score, search and deduplicate names from the command line.
+// Matchers are selected with --matcher, eg --matcher jaro-winkler=0.6, or
+// with an EnsembleSpec config file in JSON, TOML or YAML, eg:
+//   {"matchers": [{"type": "Jaro-Winkler", "weight": 0.6}, {"type": "Soundex"}]}
+// Every matcher is used with equal weights when none is selected.
+#[derive(Parser)]
+#[command(
+    name = "name-match",
+    version,
+    about = "Score, search and deduplicate names"
+)]
+struct Cli {
+    /// Matcher as NAME or NAME=WEIGHT, repeatable. Wrappers take the wrapped
+    /// matcher in parentheses, eg token-sort(jaro-winkler)
+    #[arg(short, long = "matcher", global = true, value_name = "NAME[=WEIGHT]")]
+    matchers: Vec<String>,
+
+    /// JSON, TOML or YAML file describing the matchers, their weights and
+    /// parameters
+    #[arg(short, long, global = true, value_name = "FILE")]
+    config: Option<String>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Score two names with every matcher
+    Score {
+        name1: String,
+        name2: String,
+        #[arg(short, long, value_enum, default_value = "table")]
+        format: Format,
+    },
+    /// Search a query among the names of a file, one name per line
+    Search {
+        query: String,
+        #[arg(short, long, value_name = "FILE")]
+        reference: String,
+        #[arg(short, default_value_t = 10)]
+        k: usize,
+        #[arg(short, long, default_value_t = 0.0)]
+        threshold: f64,
+        #[arg(short, long, value_enum, default_value = "table")]
+        format: Format,
+    },
+    /// Cluster duplicated names of a file, one name per line
+    Dedupe {
+        file: String,
+        #[arg(short, long, default_value_t = 0.9)]
+        threshold: f64,
+        #[arg(long, value_enum, default_value = "connected-components")]
+        clustering: ClusteringArg,
+        #[arg(short, long, value_enum, default_value = "table")]
+        format: Format,
+    },
+    /// Print the phonetic codes or blocking keys of names
+    Encode {
+        names: Vec<String>,
+        #[arg(short, long, value_enum, default_value = "soundex")]
+        encoding: Encoding,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Table,
+    Json,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ClusteringArg {
+    ConnectedComponents,
+    Center,
+    Correlation,
+}
+
+// Encoding selects what encode prints for every name.
+//   - Soundex: Soundex code of the whole name.
+//   - SoundexTokens: Soundex code of every token.
+//   - Keys: Blocking keys used by indexes and deduplication.
+#[derive(Clone, Copy, ValueEnum)]
+enum Encoding {
+    Soundex,
+    SoundexTokens,
+    Keys,
+}
+
+fn main() {
+    let cli = Cli::parse();
+    if let Err(err) = run(cli) {
+        eprintln!("error: {}", err);
+        process::exit(1);
+    }
+}
+
+fn run(cli: Cli) -> Result<(), String> {
+    if let Command::Encode { names, encoding } = &cli.command {
+        encode_names(names, *encoding);
+        return Ok(());
+    }
+    let ensemble = build_ensemble(&cli)?;
+    match cli.command {
+        Command::Score {
+            name1,
+            name2,
+            format,
+        } => {
+            let result = ensemble.get_ensemble_result(&name1[..], &name2[..]);
+            print_score(&result, format);
+        }
+        Command::Search {
+            query,
+            reference,
+            k,
+            threshold,
+            format,
+        } => {
+            let names = input::read_names(&reference).map_err(|err| err.to_string())?;
+            let names: Vec<&str> = names.iter().map(|name| &name[..]).collect();
+            let results = ensemble.get_top_k_above(&query[..], names, k, threshold);
+            print_results(&results, format);
+        }
+        Command::Dedupe {
+            file,
+            threshold,
+            clustering,
+            format,
+        } => {
+            let names = input::read_names(&file).map_err(|err| err.to_string())?;
+            let names: Vec<&str> = names.iter().map(|name| &name[..]).collect();
+            let mut deduplicator = Deduplicator::new(threshold);
+            deduplicator.clustering = match clustering {
+                ClusteringArg::ConnectedComponents => Clustering::ConnectedComponents,
+                ClusteringArg::Center => Clustering::Center,
+                ClusteringArg::Correlation => Clustering::Correlation,
+            };
+            print_clusters(&deduplicator, &ensemble, &names, format);
+        }
+        Command::Encode { .. } => unreachable!(),
+    }
+    Ok(())
+}
+
+// build_ensemble builds the ensemble from the config file and --matcher
+// flags, or from every matcher.
+fn build_ensemble(cli: &Cli) -> Result<Ensemble, String> {
+    let mut spec = match &cli.config {
+        Some(path) => EnsembleSpec::load(path).map_err(|err| format!("{}: {}", path, err))?,
+        None => EnsembleSpec {
+            matchers: Vec::new(),
+            aggregation: Default::default(),
+        },
+    };
+    for flag in cli.matchers.iter() {
+        spec.matchers.push(parse_matcher_flag(flag)?);
+    }
+    if spec.matchers.is_empty() {
+        spec.matchers = DEFAULT_MATCHERS
+            .iter()
+            .map(|kind| MatcherSpec::new(kind))
+            .collect();
+    }
+
+    let equal_weights = spec.matchers.iter().all(|matcher| matcher.weight.is_none());
+    let mut ensemble = Ensemble::from_spec(&spec).map_err(|err| err.to_string())?;
+    if equal_weights {
+        ensemble.set_equal_weight();
+    }
+    Ok(ensemble)
+}
+
+const DEFAULT_MATCHERS: [&str; 6] = [
+    "Jaro-Winkler",
+    "Jaccard",
+    "Soundex",
+    "Soundex-Jaccard",
+    "Smith-Waterman",
+    "Levenshtein",
+];
+
+// parse_matcher_flag parses NAME or NAME=WEIGHT.
+fn parse_matcher_flag(flag: &str) -> Result<MatcherSpec, String> {
+    let (kind, weight) = match flag.rsplit_once('=') {
+        Some((kind, weight)) => {
+            let weight: f64 = weight
+                .trim()
+                .parse()
+                .map_err(|_| format!("invalid weight in {}", flag))?;
+            (kind, Some(weight))
+        }
+        None => (flag, None),
+    };
+    let mut spec = MatcherSpec::new(kind.trim());
+    spec.weight = weight;
+    Ok(spec)
+}
+
+fn print_score(result: &EnsembleResult, format: Format) {
+    match format {
+        Format::Json => println!("{}", serde_json::to_string_pretty(result).unwrap()),
+        Format::Table => {
+            println!(
+                "{:<30} {:>10} {:>10} {:>10}",
+                "matcher", "weight", "score", "weighted"
+            );
+            for match_result in result.results.iter() {
+                println!(
+                    "{:<30} {:>10.4} {:>10.4} {:>10.4}",
+                    match_result.algorithm,
+                    match_result.weight,
+                    match_result.absolute_score,
+                    match_result.weighted_score
+                );
+            }
+            println!("{:<30} {:>32.4}", "total", result.score);
+        }
+    }
+}
+
+fn print_results(results: &[EnsembleResult], format: Format) {
+    match format {
+        Format::Json => println!("{}", serde_json::to_string_pretty(results).unwrap()),
+        Format::Table => {
+            for result in results.iter() {
+                println!("{:>8.4}  {}", result.score, result.name2);
+            }
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ClusterOutput<'a> {
+    representative: &'a str,
+    members: Vec<&'a str>,
+}
+
+fn print_clusters(
+    deduplicator: &Deduplicator,
+    ensemble: &Ensemble,
+    names: &[&str],
+    format: Format,
+) {
+    let clusters = deduplicator.cluster(ensemble, names);
+    let clusters: Vec<ClusterOutput> = clusters
+        .iter()
+        .map(|cluster| ClusterOutput {
+            representative: names[cluster.representative],
+            members: cluster.ids.iter().map(|id| names[*id]).collect(),
+        })
+        .collect();
+    match format {
+        Format::Json => println!("{}", serde_json::to_string_pretty(&clusters).unwrap()),
+        Format::Table => {
+            for cluster in clusters.iter() {
+                println!(
+                    "{}: {}",
+                    cluster.representative,
+                    cluster.members.join(" | ")
+                );
+            }
+        }
+    }
+}
+
+fn encode_names(names: &[String], encoding: Encoding) {
+    let cleaner = SoundexMatcher::default();
+    for name in names.iter() {
+        let cleaned = cleaner.clean(name);
+        let codes: Vec<String> = match encoding {
+            Encoding::Soundex => vec![encode::apply_soundex(&cleaned[..])],
+            Encoding::SoundexTokens => cleaned
+                .split_whitespace()
+                .map(encode::apply_soundex)
+                .collect(),
+            Encoding::Keys => {
+                let kinds = [
+                    BlockingKey::Soundex,
+                    BlockingKey::QGram(3),
+                    BlockingKey::SortedTokenPrefix(4),
+                ];
+                kinds
+                    .iter()
+                    .flat_map(|kind| kind.keys(&cleaned[..]))
+                    .collect()
+            }
+        };
+        println!("{}\t{}", name, codes.join(" "));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_matcher_flag;
+
+    #[test]
+    fn test_parse_matcher_flag() {
+        let spec = parse_matcher_flag("jaro-winkler=0.6").unwrap();
+        assert_eq!(spec.kind, "jaro-winkler");
+        assert_eq!(spec.weight, Some(0.6));
+        assert_eq!(parse_matcher_flag("Soundex").unwrap().weight, None);
+        assert!(parse_matcher_flag("soundex=heavy").is_err());
+    }
+
+    #[test]
+    fn test_build_matcher_from_flag() {
+        let matcher = parse_matcher_flag("jaro-winkler=0.5")
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(matcher.get_name(), "Jaro-Winkler");
+        assert_eq!(matcher.get_weight(), 0.5);
+        let matcher = parse_matcher_flag("token-sort(SoundexJaccard)")
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(matcher.get_name(), "Token-Sort(Soundex-Jaccard)");
+        assert!(parse_matcher_flag("metaphone").unwrap().build().is_err());
+    }
+}
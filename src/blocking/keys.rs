@@ -0,0 +1,86 @@
+use super::super::shared::compute;
+use super::super::soundex::encode;
+use serde::{Deserialize, Serialize};
+
+// BlockingKey describes how blocking keys are derived from a cleaned name.
+// Names sharing a blocking key are candidates for comparison.
+//   - Soundex: Soundex code of every token.
+//     "JOHN DOE" -> ["J500", "D000"]
+//   - QGram(q): Every q-gram of every token.
+//     "JOHN DOE" with q = 3 -> ["JOH", "OHN", "DOE"]
+//   - SortedTokenPrefix(n): First n characters of the name with its tokens
+//     sorted alphabetically and concatenated.
+//     "JOHN DOE" with n = 4 -> ["DOEJ"]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum BlockingKey {
+    Soundex,
+    QGram(usize),
+    SortedTokenPrefix(usize),
+}
+
+impl BlockingKey {
+    // keys returns the blocking keys of a cleaned name. Every key is tagged
+    // with its kind so that keys of different kinds never collide.
+    pub fn keys(&self, cleaned: &str) -> Vec<String> {
+        let mut keys: Vec<String> = match self {
+            BlockingKey::Soundex => cleaned
+                .split_whitespace()
+                .map(|token| format!("S:{}", encode::apply_soundex(token)))
+                .collect(),
+            BlockingKey::QGram(q) => compute::qgrams(cleaned, *q)
+                .into_iter()
+                .map(|gram| format!("Q{}:{}", q, gram))
+                .collect(),
+            BlockingKey::SortedTokenPrefix(n) => {
+                let mut tokens: Vec<&str> = cleaned.split_whitespace().collect();
+                if tokens.is_empty() {
+                    return Vec::new();
+                }
+                tokens.sort_unstable();
+                let prefix: String = tokens.concat().chars().take(*n).collect();
+                vec![format!("P{}:{}", n, prefix)]
+            }
+        };
+        keys.sort_unstable();
+        keys.dedup();
+        keys
+    }
+}
+
+// blocking_keys returns the blocking keys of a cleaned name
+// for every kind of key.
+pub fn blocking_keys(kinds: &[BlockingKey], cleaned: &str) -> Vec<String> {
+    kinds.iter().flat_map(|kind| kind.keys(cleaned)).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::BlockingKey;
+
+    #[test]
+    fn test_soundex_keys() {
+        let keys = BlockingKey::Soundex.keys("JOHN DOE");
+        assert_eq!(keys, vec!["S:D000", "S:J500"]);
+    }
+
+    #[test]
+    fn test_qgram_keys() {
+        let keys = BlockingKey::QGram(3).keys("JOHN JOHN");
+        assert_eq!(keys, vec!["Q3:JOH", "Q3:OHN"]);
+    }
+
+    #[test]
+    fn test_sorted_token_prefix_keys() {
+        let keys = BlockingKey::SortedTokenPrefix(4).keys("JOHN DOE");
+        assert_eq!(keys, vec!["P4:DOEJ"]);
+    }
+
+    #[test]
+    fn test_empty_name_keys() {
+        let keys = super::blocking_keys(
+            &[BlockingKey::Soundex, BlockingKey::SortedTokenPrefix(4)],
+            "",
+        );
+        assert!(keys.is_empty());
+    }
+}
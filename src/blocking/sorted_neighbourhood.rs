@@ -0,0 +1,210 @@
+use super::super::prelude::*;
+use super::super::soundex::encode;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+// SortKey describes a component of the key names are sorted by.
+//   - Cleaned: The cleaned name.
+//     "JOHN DOE" -> "JOHN DOE"
+//   - ReversedTokens: Tokens in reverse order.
+//     "JOHN DOE" -> "DOE JOHN"
+//   - ReversedChars: Characters in reverse order, brings together names
+//     differing in their first characters.
+//     "JOHN DOE" -> "EOD NHOJ"
+//   - SortedTokens: Tokens sorted alphabetically.
+//     "JOHN DOE" -> "DOE JOHN"
+//   - FirstInitial: First character of the name.
+//     "JOHN DOE" -> "J"
+//   - SurnameSoundex: Soundex code of the last token.
+//     "JOHN DOE" -> "D000"
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum SortKey {
+    Cleaned,
+    ReversedTokens,
+    ReversedChars,
+    SortedTokens,
+    FirstInitial,
+    SurnameSoundex,
+}
+
+impl SortKey {
+    // key returns the sort key component of a cleaned name.
+    pub fn key(&self, cleaned: &str) -> String {
+        match self {
+            SortKey::Cleaned => cleaned.split_whitespace().collect::<Vec<&str>>().join(" "),
+            SortKey::ReversedTokens => cleaned
+                .split_whitespace()
+                .rev()
+                .collect::<Vec<&str>>()
+                .join(" "),
+            SortKey::ReversedChars => cleaned.chars().rev().collect(),
+            SortKey::SortedTokens => {
+                let mut tokens: Vec<&str> = cleaned.split_whitespace().collect();
+                tokens.sort_unstable();
+                tokens.join(" ")
+            }
+            SortKey::FirstInitial => cleaned.chars().take(1).collect(),
+            SortKey::SurnameSoundex => cleaned
+                .split_whitespace()
+                .last()
+                .map(encode::apply_soundex)
+                .unwrap_or_default(),
+        }
+    }
+}
+
+// sort_key returns the composite sort key of a cleaned name, its components
+// separated by '|' which never appears in cleaned names.
+pub fn sort_key(components: &[SortKey], cleaned: &str) -> String {
+    components
+        .iter()
+        .map(|component| component.key(cleaned))
+        .collect::<Vec<String>>()
+        .join("|")
+}
+
+// SortedNeighbourhood generates candidate pairs with the sorted
+// neighbourhood method. For every pass, names are sorted by the pass's
+// composite key and every name is paired with the next window - 1 names.
+// Pairs emitted by several passes are returned once.
+//   - window: Size of the sliding window, at least 2.
+//   - passes: Composite sort key of every pass.
+pub struct SortedNeighbourhood {
+    pub window: usize,
+    pub passes: Vec<Vec<SortKey>>,
+}
+
+impl SortedNeighbourhood {
+    pub fn new(window: usize, passes: Vec<Vec<SortKey>>) -> SortedNeighbourhood {
+        SortedNeighbourhood {
+            window: window.max(2),
+            passes,
+        }
+    }
+
+    // pairs returns candidate pairs within a single list, to be scored by
+    // Deduplicator::cluster_candidates. left is always lower than right.
+    pub fn pairs(&self, names: &[&str]) -> Vec<(usize, usize)> {
+        let records: Vec<(usize, &str)> = names.iter().cloned().enumerate().collect();
+        let mut pairs: HashSet<(usize, usize)> = HashSet::new();
+        for components in self.passes.iter() {
+            for (id_1, id_2) in self.pass(components, &records) {
+                pairs.insert((id_1.min(id_2), id_1.max(id_2)));
+            }
+        }
+        let mut pairs: Vec<(usize, usize)> = pairs.into_iter().collect();
+        pairs.sort_unstable();
+        pairs
+    }
+
+    // linkage_pairs returns candidate (left, right) pairs between two
+    // lists, to be scored by Linker::link_candidates. Both lists are sorted
+    // together and only pairs across lists are emitted.
+    pub fn linkage_pairs(&self, left: &[&str], right: &[&str]) -> Vec<(usize, usize)> {
+        // Right ids are offset by the size of left.
+        let offset = left.len();
+        let records: Vec<(usize, &str)> = left
+            .iter()
+            .chain(right.iter())
+            .cloned()
+            .enumerate()
+            .collect();
+        let mut pairs: HashSet<(usize, usize)> = HashSet::new();
+        for components in self.passes.iter() {
+            for (id_1, id_2) in self.pass(components, &records) {
+                let (id_1, id_2) = (id_1.min(id_2), id_1.max(id_2));
+                if id_1 < offset && id_2 >= offset {
+                    pairs.insert((id_1, id_2 - offset));
+                }
+            }
+        }
+        let mut pairs: Vec<(usize, usize)> = pairs.into_iter().collect();
+        pairs.sort_unstable();
+        pairs
+    }
+
+    // pass sorts records by the composite key and emits the pairs within
+    // the sliding window.
+    fn pass(&self, components: &[SortKey], records: &[(usize, &str)]) -> Vec<(usize, usize)> {
+        let mut keyed: Vec<(String, usize)> = records
+            .par_iter()
+            .map(|(id, name)| (sort_key(components, &self.clean(name)[..]), *id))
+            .collect();
+        keyed.par_sort_unstable();
+
+        let mut pairs = Vec::new();
+        for (i, (_, id_1)) in keyed.iter().enumerate() {
+            let end = (i + self.window).min(keyed.len());
+            for (_, id_2) in keyed[i + 1..end].iter() {
+                pairs.push((*id_1, *id_2));
+            }
+        }
+        pairs
+    }
+}
+
+impl Clean for SortedNeighbourhood {}
+
+#[cfg(test)]
+mod test {
+    use super::{SortKey, SortedNeighbourhood};
+
+    #[test]
+    fn test_sort_keys() {
+        assert_eq!(SortKey::Cleaned.key("JOHN  DOE"), "JOHN DOE");
+        assert_eq!(SortKey::ReversedTokens.key("JOHN DOE"), "DOE JOHN");
+        assert_eq!(SortKey::ReversedChars.key("JOHN DOE"), "EOD NHOJ");
+        assert_eq!(SortKey::SortedTokens.key("JOHN DOE"), "DOE JOHN");
+        assert_eq!(SortKey::FirstInitial.key("JOHN DOE"), "J");
+        assert_eq!(SortKey::SurnameSoundex.key("JOHN DOE"), "D000");
+        assert_eq!(SortKey::SurnameSoundex.key(""), "");
+    }
+
+    #[test]
+    fn test_composite_sort_key() {
+        let key = super::sort_key(
+            &[SortKey::SurnameSoundex, SortKey::FirstInitial],
+            "JOHN DOE",
+        );
+        assert_eq!(key, "D000|J");
+    }
+
+    #[test]
+    fn test_pairs_window() {
+        let method = SortedNeighbourhood::new(2, vec![vec![SortKey::Cleaned]]);
+        let names = vec!["Carl", "Adam", "Bob", "Dave"];
+        // Sorted: Adam(1), Bob(2), Carl(0), Dave(3)
+        assert_eq!(method.pairs(&names), vec![(0, 2), (0, 3), (1, 2)]);
+
+        let method = SortedNeighbourhood::new(3, vec![vec![SortKey::Cleaned]]);
+        assert_eq!(
+            method.pairs(&names),
+            vec![(0, 1), (0, 2), (0, 3), (1, 2), (2, 3)]
+        );
+    }
+
+    #[test]
+    fn test_multi_pass_pairs() {
+        let names = vec!["John Doe", "Alan Brown", "Doe John", "Fred Green"];
+        let single = SortedNeighbourhood::new(2, vec![vec![SortKey::Cleaned]]);
+        assert!(!single.pairs(&names).contains(&(0, 2)));
+
+        let multi =
+            SortedNeighbourhood::new(2, vec![vec![SortKey::Cleaned], vec![SortKey::SortedTokens]]);
+        let pairs = multi.pairs(&names);
+        assert!(pairs.contains(&(0, 2)));
+        let mut deduped = pairs.clone();
+        deduped.dedup();
+        assert_eq!(pairs, deduped);
+    }
+
+    #[test]
+    fn test_linkage_pairs() {
+        let method = SortedNeighbourhood::new(2, vec![vec![SortKey::Cleaned]]);
+        let left = vec!["John Doe", "Mary Smith"];
+        let right = vec!["Jon Doe", "Marie Smith"];
+        // Sorted: JOHN DOE(l0), JON DOE(r0), MARIE SMITH(r1), MARY SMITH(l1)
+        assert_eq!(method.linkage_pairs(&left, &right), vec![(0, 0), (1, 1)]);
+    }
+}
@@ -0,0 +1,439 @@
+use super::blocking::keys::{self, BlockingKey};
+use super::ensemble::Ensemble;
+use super::prelude::*;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+// Clustering describes how the similarity graph is turned into clusters.
+//   - ConnectedComponents: Names linked by any chain of similar pairs are
+//     clustered together. Prone to chaining, A ~ B ~ C clusters A with C.
+//   - Center: Pairs are visited best first. A name becomes the center of
+//     a cluster and only names similar to the center itself join it.
+//   - Correlation: Pivot clustering, every unclustered name in order becomes
+//     a pivot and is clustered with its unclustered neighbours.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum Clustering {
+    ConnectedComponents,
+    Center,
+    Correlation,
+}
+
+// Representative describes how the representative of a cluster is selected.
+//   - MostSimilar: Member with the highest total score to other members.
+//   - Longest: Member with the longest name, usually the most complete one.
+//   - First: Member that appears first in the list.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum Representative {
+    MostSimilar,
+    Longest,
+    First,
+}
+
+// ScoredPair is a pair of names from the list with their aggregated score.
+// left is always lower than right.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ScoredPair {
+    pub left: usize,
+    pub right: usize,
+    pub score: f64,
+}
+
+// Cluster holds the ids of duplicated names, sorted, and the id of the
+// name representing them.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Cluster {
+    pub ids: Vec<usize>,
+    pub representative: usize,
+}
+
+// DisjointSet is a union-find structure over ids 0..n.
+struct DisjointSet {
+    parents: Vec<usize>,
+}
+
+impl DisjointSet {
+    fn new(size: usize) -> DisjointSet {
+        DisjointSet {
+            parents: (0..size).collect(),
+        }
+    }
+
+    fn find(&mut self, id: usize) -> usize {
+        let mut root = id;
+        while self.parents[root] != root {
+            root = self.parents[root];
+        }
+        // Path compression
+        let mut current = id;
+        while self.parents[current] != root {
+            let next = self.parents[current];
+            self.parents[current] = root;
+            current = next;
+        }
+        root
+    }
+
+    fn union(&mut self, id_1: usize, id_2: usize) {
+        let root_1 = self.find(id_1);
+        let root_2 = self.find(id_2);
+        if root_1 != root_2 {
+            self.parents[root_1.max(root_2)] = root_1.min(root_2);
+        }
+    }
+}
+
+// Deduplicator finds duplicated names within a single list.
+// Candidate pairs are generated by blocking, scored in parallel with an
+// Ensemble, and pairs scoring at least threshold form a similarity graph
+// which is then clustered.
+//   - blocking: Kinds of blocking keys used to generate candidate pairs.
+//   - max_block_size: Blocks larger than this are skipped, they would
+//     generate too many pairs.
+pub struct Deduplicator {
+    pub blocking: Vec<BlockingKey>,
+    pub max_block_size: Option<usize>,
+    pub threshold: f64,
+    pub clustering: Clustering,
+    pub representative: Representative,
+}
+
+impl Deduplicator {
+    pub fn new(threshold: f64) -> Deduplicator {
+        Deduplicator {
+            blocking: vec![BlockingKey::Soundex, BlockingKey::SortedTokenPrefix(4)],
+            max_block_size: None,
+            threshold,
+            clustering: Clustering::ConnectedComponents,
+            representative: Representative::MostSimilar,
+        }
+    }
+
+    // candidate_pairs returns every pair of names sharing a blocking key,
+    // each pair once, sorted.
+    pub fn candidate_pairs(&self, names: &[&str]) -> Vec<(usize, usize)> {
+        let name_keys: Vec<Vec<String>> = names
+            .par_iter()
+            .map(|name| keys::blocking_keys(&self.blocking, &self.clean(name)[..]))
+            .collect();
+        let mut blocks: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (id, keys) in name_keys.iter().enumerate() {
+            for key in keys.iter() {
+                blocks.entry(&key[..]).or_default().push(id);
+            }
+        }
+
+        let mut pairs: HashSet<(usize, usize)> = HashSet::new();
+        for block in blocks.values() {
+            if let Some(max_block_size) = self.max_block_size {
+                if block.len() > max_block_size {
+                    continue;
+                }
+            }
+            for (i, left) in block.iter().enumerate() {
+                for right in block[i + 1..].iter() {
+                    pairs.insert((*left.min(right), *left.max(right)));
+                }
+            }
+        }
+        let mut pairs: Vec<(usize, usize)> = pairs.into_iter().collect();
+        pairs.sort_unstable();
+        pairs
+    }
+
+    // score_pairs scores candidate pairs in parallel and keeps those scoring
+    // at least threshold.
+    pub fn score_pairs(
+        &self,
+        ensemble: &Ensemble,
+        names: &[&str],
+        pairs: &[(usize, usize)],
+    ) -> Vec<ScoredPair> {
+        pairs
+            .par_iter()
+            .filter_map(|(left, right)| {
+                ensemble
+                    .get_aggregated_score_above(names[*left], names[*right], self.threshold)
+                    .map(|score| ScoredPair {
+                        left: *left.min(right),
+                        right: *left.max(right),
+                        score,
+                    })
+            })
+            .collect()
+    }
+
+    // cluster returns the clusters of duplicated names in the list.
+    // Names without duplicates are not returned.
+    pub fn cluster(&self, ensemble: &Ensemble, names: &[&str]) -> Vec<Cluster> {
+        let pairs = self.candidate_pairs(names);
+        self.cluster_candidates(ensemble, names, &pairs)
+    }
+
+    // cluster_candidates clusters names using the given candidate pairs
+    // instead of blocking.
+    pub fn cluster_candidates(
+        &self,
+        ensemble: &Ensemble,
+        names: &[&str],
+        pairs: &[(usize, usize)],
+    ) -> Vec<Cluster> {
+        let scored_pairs = self.score_pairs(ensemble, names, pairs);
+        self.cluster_scored_pairs(names, scored_pairs)
+    }
+
+    // cluster_scored_pairs clusters names from already scored pairs.
+    pub fn cluster_scored_pairs(
+        &self,
+        names: &[&str],
+        mut scored_pairs: Vec<ScoredPair>,
+    ) -> Vec<Cluster> {
+        scored_pairs.retain(|pair| pair.score >= self.threshold);
+        scored_pairs.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then((a.left, a.right).cmp(&(b.left, b.right)))
+        });
+        let groups = match self.clustering {
+            Clustering::ConnectedComponents => connected_components(names.len(), &scored_pairs),
+            Clustering::Center => center_clusters(names.len(), &scored_pairs),
+            Clustering::Correlation => pivot_clusters(names.len(), &scored_pairs),
+        };
+
+        let mut clusters: Vec<Cluster> = groups
+            .into_iter()
+            .filter(|ids| ids.len() > 1)
+            .map(|mut ids| {
+                ids.sort_unstable();
+                let representative = self.select_representative(names, &ids, &scored_pairs);
+                Cluster {
+                    ids,
+                    representative,
+                }
+            })
+            .collect();
+        clusters.sort_by_key(|cluster| cluster.ids[0]);
+        clusters
+    }
+
+    fn select_representative(&self, names: &[&str], ids: &[usize], pairs: &[ScoredPair]) -> usize {
+        match self.representative {
+            Representative::First => ids[0],
+            Representative::Longest => *ids
+                .iter()
+                .max_by(|a, b| {
+                    let length_a = self.clean(names[**a]).chars().count();
+                    let length_b = self.clean(names[**b]).chars().count();
+                    length_a.cmp(&length_b).then(b.cmp(a))
+                })
+                .unwrap(),
+            Representative::MostSimilar => {
+                let members: HashSet<usize> = ids.iter().cloned().collect();
+                let mut totals: HashMap<usize, f64> = HashMap::new();
+                for pair in pairs.iter() {
+                    if members.contains(&pair.left) && members.contains(&pair.right) {
+                        *totals.entry(pair.left).or_insert(0.0) += pair.score;
+                        *totals.entry(pair.right).or_insert(0.0) += pair.score;
+                    }
+                }
+                *ids.iter()
+                    .max_by(|a, b| {
+                        let total_a = totals.get(a).unwrap_or(&0.0);
+                        let total_b = totals.get(b).unwrap_or(&0.0);
+                        total_a.total_cmp(total_b).then(b.cmp(a))
+                    })
+                    .unwrap()
+            }
+        }
+    }
+}
+
+impl Clean for Deduplicator {}
+
+// connected_components groups names linked by any chain of pairs.
+fn connected_components(size: usize, pairs: &[ScoredPair]) -> Vec<Vec<usize>> {
+    let mut disjoint_set = DisjointSet::new(size);
+    for pair in pairs.iter() {
+        disjoint_set.union(pair.left, pair.right);
+    }
+    let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+    for id in 0..size {
+        groups.entry(disjoint_set.find(id)).or_default().push(id);
+    }
+    groups.into_values().collect()
+}
+
+// center_clusters visits pairs best first. Two unassigned names form a new
+// cluster centered on the first one, an unassigned name similar to a
+// center joins its cluster. Other pairs are ignored.
+fn center_clusters(size: usize, pairs: &[ScoredPair]) -> Vec<Vec<usize>> {
+    // Center of the cluster every name belongs to.
+    let mut centers: Vec<Option<usize>> = vec![None; size];
+    for pair in pairs.iter() {
+        match (centers[pair.left], centers[pair.right]) {
+            (None, None) => {
+                centers[pair.left] = Some(pair.left);
+                centers[pair.right] = Some(pair.left);
+            }
+            (Some(center), None) if center == pair.left => centers[pair.right] = Some(center),
+            (None, Some(center)) if center == pair.right => centers[pair.left] = Some(center),
+            _ => {}
+        }
+    }
+    let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+    for (id, center) in centers.into_iter().enumerate() {
+        groups.entry(center.unwrap_or(id)).or_default().push(id);
+    }
+    groups.into_values().collect()
+}
+
+// pivot_clusters visits names in list order. Every unclustered name becomes
+// a pivot and is clustered with its unclustered neighbours.
+fn pivot_clusters(size: usize, pairs: &[ScoredPair]) -> Vec<Vec<usize>> {
+    let mut neighbours: Vec<Vec<usize>> = vec![Vec::new(); size];
+    for pair in pairs.iter() {
+        neighbours[pair.left].push(pair.right);
+        neighbours[pair.right].push(pair.left);
+    }
+    let mut clustered = vec![false; size];
+    let mut groups = Vec::new();
+    for pivot in 0..size {
+        if clustered[pivot] {
+            continue;
+        }
+        clustered[pivot] = true;
+        let mut group = vec![pivot];
+        for neighbour in neighbours[pivot].iter() {
+            if !clustered[*neighbour] {
+                clustered[*neighbour] = true;
+                group.push(*neighbour);
+            }
+        }
+        groups.push(group);
+    }
+    groups
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::ensemble::Ensemble;
+    use super::super::jaro::compare::JaroWinklerMatcher;
+    use super::super::prelude::*;
+    use super::{Clustering, Deduplicator, Representative, ScoredPair};
+
+    fn build_ensemble() -> Ensemble {
+        let matchers: Vec<Box<dyn Matcher>> = vec![Box::new(JaroWinklerMatcher::default())];
+        Ensemble::new(matchers)
+    }
+
+    fn pair(left: usize, right: usize, score: f64) -> ScoredPair {
+        ScoredPair { left, right, score }
+    }
+
+    // A ~ B ~ C chain where A and C are not similar.
+    fn chained_pairs() -> Vec<ScoredPair> {
+        vec![pair(0, 1, 0.95), pair(1, 2, 0.9), pair(3, 4, 0.99)]
+    }
+
+    #[test]
+    fn test_candidate_pairs() {
+        let deduplicator = Deduplicator::new(0.9);
+        let names = vec!["John Doe", "Mary Smith", "Jon Doe", "Doe John"];
+        let pairs = deduplicator.candidate_pairs(&names);
+        assert_eq!(pairs, vec![(0, 2), (0, 3), (2, 3)]);
+    }
+
+    #[test]
+    fn test_candidate_pairs_max_block_size() {
+        let mut deduplicator = Deduplicator::new(0.9);
+        deduplicator.max_block_size = Some(2);
+        let names = vec!["John Doe", "Mary Smith", "Jon Doe", "Doe John"];
+        let pairs = deduplicator.candidate_pairs(&names);
+        assert!(pairs.is_empty());
+    }
+
+    #[test]
+    fn test_cluster() {
+        let deduplicator = Deduplicator::new(0.9);
+        let names = vec![
+            "John Doe",
+            "Mary Smith",
+            "Jon Doe",
+            "Mary Smyth",
+            "Peter Parker",
+        ];
+        let clusters = deduplicator.cluster(&build_ensemble(), &names);
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(clusters[0].ids, vec![0, 2]);
+        assert_eq!(clusters[1].ids, vec![1, 3]);
+    }
+
+    #[test]
+    fn test_connected_components_chaining() {
+        let deduplicator = Deduplicator::new(0.9);
+        let names = vec!["A", "B", "C", "D", "E", "F"];
+        let clusters = deduplicator.cluster_scored_pairs(&names, chained_pairs());
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(clusters[0].ids, vec![0, 1, 2]);
+        assert_eq!(clusters[1].ids, vec![3, 4]);
+    }
+
+    #[test]
+    fn test_center_clustering() {
+        let mut deduplicator = Deduplicator::new(0.9);
+        deduplicator.clustering = Clustering::Center;
+        let names = vec!["A", "B", "C", "D", "E", "F"];
+        let clusters = deduplicator.cluster_scored_pairs(&names, chained_pairs());
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(clusters[0].ids, vec![0, 1]);
+        assert_eq!(clusters[1].ids, vec![3, 4]);
+    }
+
+    #[test]
+    fn test_correlation_clustering() {
+        let mut deduplicator = Deduplicator::new(0.9);
+        deduplicator.clustering = Clustering::Correlation;
+        let names = vec!["A", "B", "C", "D", "E", "F"];
+        let pairs = vec![pair(0, 1, 0.95), pair(1, 2, 0.9), pair(2, 3, 0.9)];
+        let clusters = deduplicator.cluster_scored_pairs(&names, pairs);
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(clusters[0].ids, vec![0, 1]);
+        assert_eq!(clusters[1].ids, vec![2, 3]);
+    }
+
+    #[test]
+    fn test_threshold_filters_pairs() {
+        let deduplicator = Deduplicator::new(0.92);
+        let names = vec!["A", "B", "C", "D", "E", "F"];
+        let clusters = deduplicator.cluster_scored_pairs(&names, chained_pairs());
+        let ids: Vec<Vec<usize>> = clusters.into_iter().map(|c| c.ids).collect();
+        assert_eq!(ids, vec![vec![0, 1], vec![3, 4]]);
+    }
+
+    #[test]
+    fn test_representative_most_similar() {
+        let deduplicator = Deduplicator::new(0.9);
+        let names = vec!["A", "B", "C", "D", "E", "F"];
+        let clusters = deduplicator.cluster_scored_pairs(&names, chained_pairs());
+        assert_eq!(clusters[0].representative, 1);
+    }
+
+    #[test]
+    fn test_representative_longest() {
+        let mut deduplicator = Deduplicator::new(0.9);
+        deduplicator.representative = Representative::Longest;
+        let names = vec!["Jon Doe", "John Doe", "J. Doe"];
+        let clusters =
+            deduplicator.cluster_scored_pairs(&names, vec![pair(0, 1, 0.95), pair(0, 2, 0.9)]);
+        assert_eq!(clusters[0].representative, 1);
+    }
+
+    #[test]
+    fn test_representative_first() {
+        let mut deduplicator = Deduplicator::new(0.9);
+        deduplicator.representative = Representative::First;
+        let names = vec!["A", "B", "C", "D", "E", "F"];
+        let clusters = deduplicator.cluster_scored_pairs(&names, chained_pairs());
+        assert_eq!(clusters[0].representative, 0);
+    }
+}
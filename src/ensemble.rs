@@ -1,6 +1,10 @@
 use super::prelude::*;
+use super::registry::MatcherRegistry;
+use super::spec::{EnsembleSpec, SpecError};
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct EnsembleResult {
@@ -10,13 +14,169 @@ pub struct EnsembleResult {
     pub results: Vec<MatchResult>,
 }
 
+// Upper bounds are accumulated in a different order than scores,
+// rounding errors must not prune a result that reaches the threshold.
+const BOUND_TOLERANCE: f64 = 1e-9;
+
+// weighted_bound returns the upper bound of weight * score when score is at
+// most bound. Scores are never negative, so a negative weight contributes
+// at most 0.
+fn weighted_bound(weight: f64, bound: f64) -> f64 {
+    if weight < 0.0 {
+        0.0
+    } else {
+        weight * bound
+    }
+}
+
+// Ranked is a result top_k can rank by score. cmp_ties breaks ties between
+// equal scores, Ordering::Less ranks self first, so that rankings are
+// deterministic.
+pub(crate) trait Ranked: Send {
+    fn score(&self) -> f64;
+
+    fn cmp_ties(&self, other: &Self) -> Ordering;
+}
+
+// EnsembleResult ties are broken by name.
+impl Ranked for EnsembleResult {
+    fn score(&self) -> f64 {
+        self.score
+    }
+
+    fn cmp_ties(&self, other: &Self) -> Ordering {
+        self.name2.cmp(&other.name2)
+    }
+}
+
+// (position, EnsembleResult) ties are broken by position.
+impl Ranked for (usize, EnsembleResult) {
+    fn score(&self) -> f64 {
+        self.1.score
+    }
+
+    fn cmp_ties(&self, other: &Self) -> Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+// RankedResult orders results by score so that they can be kept in a heap.
+struct RankedResult<T>(T);
+
+impl<T: Ranked> PartialEq for RankedResult<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T: Ranked> Eq for RankedResult<T> {}
+
+impl<T: Ranked> PartialOrd for RankedResult<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: Ranked> Ord for RankedResult<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        cmp_scores(self.0.score(), other.0.score()).then_with(|| other.0.cmp_ties(&self.0))
+    }
+}
+
+// cmp_scores orders scores with NaN below every other score, some matchers
+// score two empty names as NaN and those must rank last.
+fn cmp_scores(a: f64, b: f64) -> Ordering {
+    match (a.is_nan(), b.is_nan()) {
+        (false, false) => a.total_cmp(&b),
+        (nan_a, nan_b) => nan_b.cmp(&nan_a),
+    }
+}
+
+// top_k returns the k best results, best first.
+// Every rayon worker keeps its own min-heap of at most k results,
+// heaps are then merged so memory stays O(k) per worker.
+pub(crate) fn top_k<T, I>(results: I, k: usize) -> Vec<T>
+where
+    T: Ranked,
+    I: ParallelIterator<Item = T>,
+{
+    if k == 0 {
+        return Vec::new();
+    }
+    let heap = results
+        .fold(BinaryHeap::new, |mut heap, result| {
+            heap.push(Reverse(RankedResult(result)));
+            if heap.len() > k {
+                heap.pop();
+            }
+            heap
+        })
+        .reduce(BinaryHeap::new, |mut heap, other| {
+            for result in other.into_iter() {
+                heap.push(result);
+                if heap.len() > k {
+                    heap.pop();
+                }
+            }
+            heap
+        });
+    heap.into_sorted_vec()
+        .into_iter()
+        .map(|Reverse(RankedResult(result))| result)
+        .collect()
+}
+
+// sort_by_score sorts results best first, ties are broken by name.
+pub(crate) fn sort_by_score(results: &mut [EnsembleResult]) {
+    results.sort_by(|a, b| cmp_scores(b.score, a.score).then_with(|| a.name2.cmp(&b.name2)));
+}
+
+// Aggregation describes how the scores of the matchers of an Ensemble are
+// combined into a single score.
+//   - WeightedSum: Sum of weighted scores, the default.
+//   - WeightedMean: Sum of weighted scores divided by the sum of weights.
+//   - Max: Best absolute score of any matcher, weights are ignored.
+//   - Min: Worst absolute score of any matcher, weights are ignored.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum Aggregation {
+    #[default]
+    WeightedSum,
+    WeightedMean,
+    Max,
+    Min,
+}
+
 pub struct Ensemble {
     pub matchers: Vec<Box<dyn Matcher>>,
+    pub aggregation: Aggregation,
 }
 
 impl Ensemble {
     pub fn new(matchers: Vec<Box<dyn Matcher>>) -> Ensemble {
-        Ensemble { matchers }
+        Ensemble {
+            matchers,
+            aggregation: Aggregation::WeightedSum,
+        }
+    }
+
+    // from_spec builds the Ensemble described by spec from the built-in
+    // matchers, see MatcherRegistry::build_ensemble for other matchers.
+    // Matchers without a weight get a weight of 1.0.
+    pub fn from_spec(spec: &EnsembleSpec) -> Result<Ensemble, SpecError> {
+        MatcherRegistry::builtin().build_ensemble(spec)
+    }
+
+    // to_spec describes the Ensemble, Ensemble::from_spec(&ensemble.to_spec())
+    // scores names like ensemble.
+    pub fn to_spec(&self) -> EnsembleSpec {
+        EnsembleSpec {
+            matchers: self
+                .matchers
+                .iter()
+                .map(|matcher| matcher.get_spec())
+                .collect(),
+            aggregation: self.aggregation,
+        }
     }
 
     pub fn set_equal_weight(&mut self) {
@@ -27,13 +187,142 @@ impl Ensemble {
         }
     }
 
+    fn total_weight(&self) -> f64 {
+        self.matchers
+            .iter()
+            .map(|matcher| matcher.get_weight())
+            .sum()
+    }
+
+    // aggregate combines (weight, absolute score) pairs of every matcher.
+    fn aggregate<I>(&self, scores: I) -> f64
+    where
+        I: Iterator<Item = (f64, f64)>,
+    {
+        match self.aggregation {
+            Aggregation::WeightedSum => scores.map(|(weight, score)| weight * score).sum(),
+            Aggregation::WeightedMean => {
+                let (sum, total) = scores.fold((0.0, 0.0), |(sum, total), (weight, score)| {
+                    (sum + weight * score, total + weight)
+                });
+                if total == 0.0 {
+                    0.0
+                } else {
+                    sum / total
+                }
+            }
+            Aggregation::Max => scores
+                .map(|(_, score)| score)
+                .reduce(f64::max)
+                .unwrap_or(0.0),
+            Aggregation::Min => scores
+                .map(|(_, score)| score)
+                .reduce(f64::min)
+                .unwrap_or(0.0),
+        }
+    }
+
+    fn aggregate_results(&self, results: &[MatchResult]) -> f64 {
+        self.aggregate(
+            results
+                .iter()
+                .map(|result| (result.weight, result.absolute_score)),
+        )
+    }
+
+    // sum_threshold returns the sum of weighted scores needed to reach
+    // threshold, None when the aggregation is not a sum and matchers can not
+    // be skipped.
+    fn sum_threshold(&self, threshold: f64) -> Option<f64> {
+        match self.aggregation {
+            Aggregation::WeightedSum => Some(threshold),
+            Aggregation::WeightedMean => {
+                let total = self.total_weight();
+                if total > 0.0 {
+                    Some(threshold * total)
+                } else {
+                    None
+                }
+            }
+            Aggregation::Max | Aggregation::Min => None,
+        }
+    }
+
     pub fn get_aggregated_score(&self, name1: &str, name2: &str) -> f64 {
+        self.aggregate(
+            self.matchers
+                .iter()
+                .map(|matcher| (matcher.get_weight(), matcher.get_score(name1, name2))),
+        )
+    }
+
+    // get_aggregated_score_above returns the aggregated score when it is at
+    // least threshold, None otherwise. Matchers are skipped as in
+    // evaluate_above.
+    pub fn get_aggregated_score_above(
+        &self,
+        name1: &str,
+        name2: &str,
+        threshold: f64,
+    ) -> Option<f64> {
+        let scores = self.evaluate_above(
+            threshold,
+            |i| self.matchers[i].get_upper_bound(name1, name2),
+            |i| {
+                let weight = self.matchers[i].get_weight();
+                let score = self.matchers[i].get_score(name1, name2);
+                (weight * score, (weight, score))
+            },
+        )?;
+        let score = self.aggregate(scores.into_iter());
+        if score >= threshold {
+            Some(score)
+        } else {
+            None
+        }
+    }
+
+    // evaluate_above evaluates matchers in order, evaluate returns the
+    // weighted score of the matcher at the given index along with its
+    // result. When scores are summed, evaluation stops as soon as the score
+    // so far plus the weighted upper bounds of the remaining matchers can no
+    // longer reach threshold. upper_bound returns the unweighted upper bound
+    // of the matcher at the given index.
+    fn evaluate_above<T, B, E>(
+        &self,
+        threshold: f64,
+        upper_bound: B,
+        mut evaluate: E,
+    ) -> Option<Vec<T>>
+    where
+        B: Fn(usize) -> f64,
+        E: FnMut(usize) -> (f64, T),
+    {
+        let count = self.matchers.len();
+        let sum_threshold = match self.sum_threshold(threshold) {
+            Some(sum_threshold) => sum_threshold,
+            // Without a sum every matcher is needed, the bounds can never prune.
+            None => return Some((0..count).map(|i| evaluate(i).1).collect()),
+        };
+        let bounds: Vec<f64> = (0..count)
+            .map(|i| weighted_bound(self.matchers[i].get_weight(), upper_bound(i)))
+            .collect();
+        let mut remaining: f64 = bounds.iter().sum();
+        if remaining < sum_threshold - BOUND_TOLERANCE {
+            return None;
+        }
+        let mut results = Vec::with_capacity(count);
         let mut sum = 0.0;
-        for matcher in self.matchers.iter() {
-            let score = matcher.get_weighted_score(name1, name2);
-            sum += score
+        for (i, bound) in bounds.iter().enumerate() {
+            remaining -= bound;
+            let (weighted_score, result) = evaluate(i);
+            sum += weighted_score;
+            results.push(result);
+            if sum + remaining < sum_threshold - BOUND_TOLERANCE {
+                return None;
+            }
         }
-        sum
+        Some(results)
     }
 
     pub fn get_match_results(&self, name1: &str, name2: &str) -> Vec<MatchResult> {
@@ -47,7 +336,7 @@ impl Ensemble {
 
     pub fn get_ensemble_result(&self, name1: &str, name2: &str) -> EnsembleResult {
         let results = self.get_match_results(name1, name2);
-        let score = results.iter().map(|result| result.weighted_score).sum();
+        let score = self.aggregate_results(&results);
 
         EnsembleResult {
             name1: name1.to_owned(),
@@ -57,20 +346,233 @@ impl Ensemble {
         }
     }
 
+    // get_ensemble_result_above returns the EnsembleResult when its score is at
+    // least threshold, None otherwise. Matchers are skipped as in
+    // get_aggregated_score_above.
+    pub fn get_ensemble_result_above(
+        &self,
+        name1: &str,
+        name2: &str,
+        threshold: f64,
+    ) -> Option<EnsembleResult> {
+        let (score, results) = self.get_match_results_above(
+            threshold,
+            |i| self.matchers[i].get_upper_bound(name1, name2),
+            |i| self.matchers[i].get_match_result(name1, name2),
+        )?;
+        Some(EnsembleResult {
+            name1: name1.to_owned(),
+            name2: name2.to_owned(),
+            score,
+            results,
+        })
+    }
+
+    // get_match_results_above is evaluate_above over the MatchResult of
+    // every matcher, match_result returns the result of the matcher at the
+    // given index.
+    fn get_match_results_above<B, F>(
+        &self,
+        threshold: f64,
+        upper_bound: B,
+        match_result: F,
+    ) -> Option<(f64, Vec<MatchResult>)>
+    where
+        B: Fn(usize) -> f64,
+        F: Fn(usize) -> MatchResult,
+    {
+        let results = self.evaluate_above(threshold, upper_bound, |i| {
+            let result = match_result(i);
+            (result.weighted_score, result)
+        })?;
+        let score = self.aggregate_results(&results);
+        if score < threshold {
+            return None;
+        }
+        Some((score, results))
+    }
+
+    // get_prepared_ensemble_result is get_ensemble_result on prepared names,
+    // names are not cleaned again by every matcher.
+    pub fn get_prepared_ensemble_result(
+        &self,
+        name1: &PreparedName,
+        name2: &PreparedName,
+    ) -> EnsembleResult {
+        let results: Vec<MatchResult> = self
+            .matchers
+            .iter()
+            .map(|matcher| matcher.get_prepared_match_result(name1, name2))
+            .collect();
+        let score = self.aggregate_results(&results);
+        EnsembleResult {
+            name1: name1.raw().to_owned(),
+            name2: name2.raw().to_owned(),
+            score,
+            results,
+        }
+    }
+
+    // get_prepared_ensemble_result_above is get_ensemble_result_above on
+    // prepared names.
+    pub fn get_prepared_ensemble_result_above(
+        &self,
+        name1: &PreparedName,
+        name2: &PreparedName,
+        threshold: f64,
+    ) -> Option<EnsembleResult> {
+        let (score, results) = self.get_match_results_above(
+            threshold,
+            |i| self.matchers[i].get_prepared_upper_bound(name1, name2),
+            |i| self.matchers[i].get_prepared_match_result(name1, name2),
+        )?;
+        Some(EnsembleResult {
+            name1: name1.raw().to_owned(),
+            name2: name2.raw().to_owned(),
+            score,
+            results,
+        })
+    }
+
+    // prepare_query prepares every matcher for query_name, see
+    // Matcher::prepare_query.
+    pub fn prepare_query(&self, query_name: &str) -> EnsembleQuery<'_> {
+        EnsembleQuery {
+            ensemble: self,
+            query_name: query_name.to_owned(),
+            scorers: self
+                .matchers
+                .iter()
+                .map(|matcher| matcher.prepare_query(query_name))
+                .collect(),
+        }
+    }
+
+    // get_ensemble_result_arr compares query_name against every name of
+    // name_list. The query is prepared once and shared by every comparison.
     pub fn get_ensemble_result_arr(
         &self,
         query_name: &str,
         name_list: Vec<&str>,
     ) -> Vec<EnsembleResult> {
+        let query = self.prepare_query(query_name);
         name_list
             .par_iter()
-            .map(|name| self.get_ensemble_result(query_name, name))
+            .map(|name| query.get_ensemble_result(&PreparedName::new(name)))
             .collect()
     }
+
+    // get_top_k returns the k best matches of query_name in name_list,
+    // sorted by score. Memory stays O(k) regardless of the list size.
+    pub fn get_top_k(
+        &self,
+        query_name: &str,
+        name_list: Vec<&str>,
+        k: usize,
+    ) -> Vec<EnsembleResult> {
+        let query = self.prepare_query(query_name);
+        let results = name_list
+            .par_iter()
+            .map(|name| query.get_ensemble_result(&PreparedName::new(name)));
+        top_k(results, k)
+    }
+
+    // get_results_above returns every match of query_name in name_list
+    // scoring at least min_score, sorted by score.
+    pub fn get_results_above(
+        &self,
+        query_name: &str,
+        name_list: Vec<&str>,
+        min_score: f64,
+    ) -> Vec<EnsembleResult> {
+        let query = self.prepare_query(query_name);
+        let mut results: Vec<EnsembleResult> = name_list
+            .par_iter()
+            .filter_map(|name| query.get_ensemble_result_above(&PreparedName::new(name), min_score))
+            .collect();
+        sort_by_score(&mut results);
+        results
+    }
+
+    // get_top_k_above returns the k best matches of query_name in name_list
+    // scoring at least min_score, sorted by score.
+    pub fn get_top_k_above(
+        &self,
+        query_name: &str,
+        name_list: Vec<&str>,
+        k: usize,
+        min_score: f64,
+    ) -> Vec<EnsembleResult> {
+        let query = self.prepare_query(query_name);
+        let results = name_list.par_iter().filter_map(|name| {
+            query.get_ensemble_result_above(&PreparedName::new(name), min_score)
+        });
+        top_k(results, k)
+    }
+}
+
+// EnsembleQuery holds the QueryScorer of every matcher of an Ensemble for a
+// fixed query, it scores candidates like Ensemble scores name pairs.
+pub struct EnsembleQuery<'a> {
+    ensemble: &'a Ensemble,
+    query_name: String,
+    scorers: Vec<Box<dyn QueryScorer + 'a>>,
+}
+
+impl<'a> EnsembleQuery<'a> {
+    pub fn query_name(&self) -> &str {
+        &self.query_name[..]
+    }
+
+    pub fn get_aggregated_score(&self, candidate: &PreparedName) -> f64 {
+        self.ensemble.aggregate(
+            self.ensemble
+                .matchers
+                .iter()
+                .zip(self.scorers.iter())
+                .map(|(matcher, scorer)| (matcher.get_weight(), scorer.score(candidate))),
+        )
+    }
+
+    pub fn get_ensemble_result(&self, candidate: &PreparedName) -> EnsembleResult {
+        let results: Vec<MatchResult> = self
+            .scorers
+            .iter()
+            .map(|scorer| scorer.match_result(candidate))
+            .collect();
+        let score = self.ensemble.aggregate_results(&results);
+        EnsembleResult {
+            name1: self.query_name.clone(),
+            name2: candidate.raw().to_owned(),
+            score,
+            results,
+        }
+    }
+
+    // get_ensemble_result_above is Ensemble::get_ensemble_result_above
+    // between the query and candidate.
+    pub fn get_ensemble_result_above(
+        &self,
+        candidate: &PreparedName,
+        threshold: f64,
+    ) -> Option<EnsembleResult> {
+        let (score, results) = self.ensemble.get_match_results_above(
+            threshold,
+            |i| self.scorers[i].upper_bound(candidate),
+            |i| self.scorers[i].match_result(candidate),
+        )?;
+        Some(EnsembleResult {
+            name1: self.query_name.clone(),
+            name2: candidate.raw().to_owned(),
+            score,
+            results,
+        })
+    }
 }
 
 #[cfg(test)]
 mod test {
+    use super::super::jaccard::compare::JaccardMatcher;
     use super::super::jaro::compare::JaroWinklerMatcher;
     use super::super::prelude::*;
     use super::super::soundex::compare::SoundexMatcher;
@@ -107,4 +609,235 @@ mod test {
 
         assert_eq!(score, 1.0);
     }
+
+    fn build_ensemble() -> super::Ensemble {
+        let jw = JaroWinklerMatcher::default();
+        let soundex = SoundexMatcher::default();
+        let matchers: Vec<Box<dyn Matcher>> = vec![Box::new(jw), Box::new(soundex)];
+        let mut ensemble = super::Ensemble::new(matchers);
+        ensemble.set_equal_weight();
+        ensemble
+    }
+
+    #[test]
+    fn test_ensemble_prepare_query() {
+        let ensemble = build_ensemble();
+        let query = ensemble.prepare_query("John Doe");
+        for name in ["Jon Doe", "Doe John", "Peter Parker"] {
+            let candidate = PreparedName::new(name);
+            let result = query.get_ensemble_result(&candidate);
+            assert_eq!(result.name1, "John Doe");
+            assert_eq!(
+                result.score,
+                ensemble.get_aggregated_score("John Doe", name)
+            );
+            assert_eq!(query.get_aggregated_score(&candidate), result.score);
+        }
+        let candidate = PreparedName::new("Jon Doe");
+        assert!(query.get_ensemble_result_above(&candidate, 0.9).is_some());
+        assert!(query.get_ensemble_result_above(&candidate, 0.99).is_none());
+    }
+
+    #[test]
+    fn test_ensemble_get_top_k() {
+        let ensemble = build_ensemble();
+        let name_list = vec![
+            "Mary Smith",
+            "Jon Doe",
+            "John Doe",
+            "Jane Doe",
+            "Peter Parker",
+        ];
+        let results = ensemble.get_top_k("John Doe", name_list, 2);
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].name2, "John Doe");
+        assert_eq!(results[1].name2, "Jon Doe");
+    }
+
+    #[test]
+    fn test_ensemble_get_top_k_larger_than_list() {
+        let ensemble = build_ensemble();
+        let name_list = vec!["Jon Doe", "John Doe"];
+        let results = ensemble.get_top_k("John Doe", name_list, 10);
+
+        assert_eq!(results.len(), 2);
+        assert!(results[0].score >= results[1].score);
+    }
+
+    #[test]
+    fn test_ensemble_get_top_k_zero() {
+        let ensemble = build_ensemble();
+        let results = ensemble.get_top_k("John Doe", vec!["John Doe"], 0);
+        assert!(results.is_empty());
+    }
+
+    #[test]
+    fn test_ensemble_get_top_k_many_names() {
+        let ensemble = build_ensemble();
+        let names: Vec<String> = (0..1000).map(|i| format!("John Doe {}", i)).collect();
+        let mut name_list: Vec<&str> = names.iter().map(|name| &name[..]).collect();
+        name_list.push("John Doe");
+        let results = ensemble.get_top_k("John Doe", name_list, 5);
+
+        assert_eq!(results.len(), 5);
+        assert_eq!(results[0].name2, "John Doe");
+        for pair in results.windows(2) {
+            assert!(pair[0].score >= pair[1].score);
+        }
+    }
+
+    #[test]
+    fn test_ensemble_get_results_above() {
+        let ensemble = build_ensemble();
+        let name_list = vec!["Mary Smith", "Jon Doe", "John Doe", "Peter Parker"];
+        let results = ensemble.get_results_above("John Doe", name_list, 0.9);
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].name2, "John Doe");
+        assert_eq!(results[1].name2, "Jon Doe");
+    }
+
+    #[test]
+    fn test_ensemble_get_top_k_above() {
+        let ensemble = build_ensemble();
+        let name_list = vec!["Mary Smith", "Jon Doe", "John Doe", "Peter Parker"];
+        let results = ensemble.get_top_k_above("John Doe", name_list, 5, 0.99);
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].name2, "John Doe");
+    }
+
+    #[test]
+    fn test_ensemble_get_aggregated_score_above() {
+        let ensemble = build_ensemble();
+        let score = ensemble.get_aggregated_score_above("John Doe", "Jon Doe", 0.5);
+        assert_eq!(
+            score,
+            Some(ensemble.get_aggregated_score("John Doe", "Jon Doe"))
+        );
+    }
+
+    #[test]
+    fn test_ensemble_get_aggregated_score_above_exact_threshold() {
+        let ensemble = build_ensemble();
+        let score = ensemble.get_aggregated_score("John Doe", "Jon Doe");
+        let above = ensemble.get_aggregated_score_above("John Doe", "Jon Doe", score);
+        assert_eq!(above, Some(score));
+    }
+
+    #[test]
+    fn test_ensemble_get_aggregated_score_above_pruned() {
+        let ensemble = build_ensemble();
+        let score = ensemble.get_aggregated_score_above("John Doe", "Mary Smith", 0.85);
+        assert_eq!(score, None);
+    }
+
+    #[test]
+    fn test_ensemble_get_aggregated_score_above_by_bound() {
+        // Jaccard can not exceed 1/4 and Jaro-Winkler its length ratio bound.
+        let matchers: Vec<Box<dyn Matcher>> = vec![
+            Box::new(JaccardMatcher::default()),
+            Box::new(JaroWinklerMatcher::default()),
+        ];
+        let mut ensemble = super::Ensemble::new(matchers);
+        ensemble.set_equal_weight();
+        let name1 = "Al";
+        let name2 = "Al Bert Charlie Dennison";
+        assert!(ensemble.get_aggregated_score(name1, name2) < 0.85);
+        assert_eq!(
+            ensemble.get_aggregated_score_above(name1, name2, 0.85),
+            None
+        );
+    }
+
+    #[test]
+    fn test_ensemble_negative_weight_is_not_pruned() {
+        // The Jaccard score lowers the total, its upper bound must not.
+        let mut jaccard = JaccardMatcher::default();
+        jaccard.set_weight(-0.5);
+        let matchers: Vec<Box<dyn Matcher>> =
+            vec![Box::new(JaroWinklerMatcher::default()), Box::new(jaccard)];
+        let ensemble = super::Ensemble::new(matchers);
+        let (name1, name2) = ("John Doe", "Jon Doe");
+        let score = ensemble.get_aggregated_score(name1, name2);
+        assert!(score > 0.0);
+        assert_eq!(
+            ensemble.get_aggregated_score_above(name1, name2, score),
+            Some(score)
+        );
+        let result = ensemble.get_ensemble_result_above(name1, name2, score);
+        assert_eq!(result.map(|result| result.score), Some(score));
+        assert_eq!(
+            ensemble.get_top_k_above(name1, vec![name2], 1, score).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_ensemble_get_ensemble_result_above() {
+        let ensemble = build_ensemble();
+        let result = ensemble.get_ensemble_result_above("John Doe", "Jon Doe", 0.5);
+        let expected = ensemble.get_ensemble_result("John Doe", "Jon Doe");
+        let result = result.unwrap();
+        assert_eq!(result.score, expected.score);
+        assert_eq!(result.results.len(), 2);
+        assert!(ensemble
+            .get_ensemble_result_above("John Doe", "Mary Smith", 0.85)
+            .is_none());
+    }
+
+    #[test]
+    fn test_ensemble_aggregation() {
+        let mut ensemble = build_ensemble();
+        ensemble.matchers[0].set_weight(3.0);
+        ensemble.matchers[1].set_weight(1.0);
+        let (name1, name2) = ("John Doe", "Jonathan Doe");
+        let jw = ensemble.matchers[0].get_score(name1, name2);
+        let soundex = ensemble.matchers[1].get_score(name1, name2);
+        assert_eq!(soundex, 0.0);
+
+        let expected = [
+            (super::Aggregation::WeightedSum, 3.0 * jw),
+            (super::Aggregation::WeightedMean, 3.0 * jw / 4.0),
+            (super::Aggregation::Max, jw),
+            (super::Aggregation::Min, 0.0),
+        ];
+        for (aggregation, score) in expected.iter() {
+            ensemble.aggregation = *aggregation;
+            assert_eq!(ensemble.get_aggregated_score(name1, name2), *score);
+            assert_eq!(ensemble.get_ensemble_result(name1, name2).score, *score);
+            let query = ensemble.prepare_query(name1);
+            let candidate = PreparedName::new(name2);
+            assert_eq!(query.get_aggregated_score(&candidate), *score);
+            assert_eq!(
+                ensemble.get_aggregated_score_above(name1, name2, *score),
+                Some(*score)
+            );
+            assert!(query
+                .get_ensemble_result_above(&candidate, *score + 0.01)
+                .is_none());
+        }
+    }
+
+    #[test]
+    fn test_ensemble_ranks_nan_last() {
+        // Jaccard scores two empty names as NaN.
+        let mut ensemble = super::Ensemble::new(vec![Box::new(JaccardMatcher::default())]);
+        ensemble.set_equal_weight();
+        let name_list = vec!["", "John Doe", "Jon Doe", ""];
+        assert!(ensemble.get_aggregated_score("", "").is_nan());
+
+        let results = ensemble.get_top_k("", name_list.clone(), 4);
+        assert_eq!(results.len(), 4);
+        assert!(results[2].score.is_nan());
+        assert!(results[3].score.is_nan());
+        let results = ensemble.get_top_k("", name_list.clone(), 2);
+        assert!(results.iter().all(|result| !result.score.is_nan()));
+
+        let mut results = ensemble.get_ensemble_result_arr("", name_list);
+        super::sort_by_score(&mut results);
+        assert!(!results[0].score.is_nan());
+        assert!(results[3].score.is_nan());
+    }
 }
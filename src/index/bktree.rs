@@ -0,0 +1,299 @@
+use super::super::prelude::*;
+use super::metric::{self, EditMetric, NearestNeighbours, Neighbour};
+use super::persist::{self, IndexKind, PersistError};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+// BKNode holds a cleaned name and the ids of every name cleaned to it.
+// Children are keyed by their distance to the node.
+#[derive(Serialize, Deserialize)]
+struct BKNode {
+    key: String,
+    ids: Vec<usize>,
+    children: Vec<(usize, usize)>,
+}
+
+// BKTree (Burkhard-Keller tree) indexes names by an integer metric distance,
+// eg Levenshtein. Names within distance d of a query are found without
+// comparing the query against every name, thanks to the triangle inequality.
+// Names are cleaned by the metric before insertion and lookup.
+//
+// Eg:
+// Root "JOHN", children "JON" (1) and "JANE" (2)
+// Searching "JOAN" within 1 only visits children at distance [1, 3] of "JOHN".
+#[derive(Serialize, Deserialize)]
+pub struct BKTree<M: EditMetric> {
+    metric: M,
+    names: Vec<String>,
+    nodes: Vec<BKNode>,
+}
+
+impl<M: EditMetric> BKTree<M> {
+    pub fn new(metric: M) -> BKTree<M> {
+        BKTree {
+            metric,
+            names: Vec::new(),
+            nodes: Vec::new(),
+        }
+    }
+
+    // build creates a tree over names in the given order.
+    pub fn build<I, S>(metric: M, names: I) -> BKTree<M>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut tree = BKTree::new(metric);
+        tree.extend(names);
+        tree
+    }
+
+    pub fn extend<I, S>(&mut self, names: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        for name in names.into_iter() {
+            self.insert(name.as_ref());
+        }
+    }
+
+    // insert adds a name to the tree and returns its id.
+    pub fn insert(&mut self, name: &str) -> usize {
+        let id = self.names.len();
+        let key = self.metric.clean(name);
+        self.names.push(name.to_owned());
+
+        if self.nodes.is_empty() {
+            self.nodes.push(BKNode {
+                key,
+                ids: vec![id],
+                children: Vec::new(),
+            });
+            return id;
+        }
+
+        let mut current = 0;
+        loop {
+            let distance = self
+                .metric
+                .edit_distance(&key[..], &self.nodes[current].key[..]);
+            if distance == 0 {
+                self.nodes[current].ids.push(id);
+                return id;
+            }
+            let child = self.nodes[current]
+                .children
+                .iter()
+                .find(|(child_distance, _)| *child_distance == distance)
+                .map(|(_, child)| *child);
+            match child {
+                Some(child) => current = child,
+                None => {
+                    let child = self.nodes.len();
+                    self.nodes.push(BKNode {
+                        key,
+                        ids: vec![id],
+                        children: Vec::new(),
+                    });
+                    self.nodes[current].children.push((distance, child));
+                    return id;
+                }
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub fn get(&self, id: usize) -> Option<&str> {
+        self.names.get(id).map(|name| &name[..])
+    }
+
+    fn neighbours(&self, node: &BKNode, distance: usize) -> Vec<Neighbour> {
+        node.ids
+            .iter()
+            .map(|id| Neighbour {
+                id: *id,
+                name: self.names[*id].to_owned(),
+                distance: distance as f64,
+            })
+            .collect()
+    }
+
+    // find returns every name within max_distance of the query,
+    // closest first.
+    pub fn find(&self, query: &str, max_distance: usize) -> Vec<Neighbour> {
+        let mut found = Vec::new();
+        if self.nodes.is_empty() {
+            return found;
+        }
+        let query = self.metric.clean(query);
+        let mut stack = vec![0];
+        while let Some(current) = stack.pop() {
+            let node = &self.nodes[current];
+            let distance = self.metric.edit_distance(&query[..], &node.key[..]);
+            if distance <= max_distance {
+                found.extend(self.neighbours(node, distance));
+            }
+            for (child_distance, child) in node.children.iter() {
+                if *child_distance + max_distance >= distance
+                    && *child_distance <= distance + max_distance
+                {
+                    stack.push(*child);
+                }
+            }
+        }
+        metric::sort_by_distance(&mut found);
+        found
+    }
+
+    // nearest returns the k names closest to the query, closest first.
+    pub fn nearest(&self, query: &str, k: usize) -> Vec<Neighbour> {
+        let mut nearest = NearestNeighbours::new(k);
+        if self.nodes.is_empty() || k == 0 {
+            return nearest.into_vec();
+        }
+        let query = self.metric.clean(query);
+        let mut stack = vec![0];
+        while let Some(current) = stack.pop() {
+            let node = &self.nodes[current];
+            let distance = self.metric.edit_distance(&query[..], &node.key[..]);
+            for neighbour in self.neighbours(node, distance) {
+                nearest.push(neighbour);
+            }
+            let radius = nearest.radius();
+            for (child_distance, child) in node.children.iter() {
+                let gap = (*child_distance as f64 - distance as f64).abs();
+                if gap <= radius {
+                    stack.push(*child);
+                }
+            }
+        }
+        nearest.into_vec()
+    }
+}
+
+impl<M: EditMetric + Named + Serialize + DeserializeOwned> BKTree<M> {
+    // save writes the tree to a versioned binary file, along with the name
+    // of its metric.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), PersistError> {
+        persist::save_with_metric(self.metric.get_name(), self, IndexKind::BKTree, path)
+    }
+
+    // load reads a tree saved with the same metric.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<BKTree<M>, PersistError> {
+        persist::load_with_metric(IndexKind::BKTree, path, |tree: &BKTree<M>| {
+            tree.metric.get_name()
+        })
+    }
+}
+
+impl<M: EditMetric + Default> Default for BKTree<M> {
+    fn default() -> BKTree<M> {
+        BKTree::new(M::default())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::metric::{DamerauLevenshtein, Levenshtein};
+    use super::super::persist::test::temp_path;
+    use super::super::persist::PersistError;
+    use super::BKTree;
+
+    fn names() -> Vec<&'static str> {
+        vec![
+            "John", "Jon", "Joan", "Jane", "Jhon", "Johnny", "Mary", "Marie", "john",
+        ]
+    }
+
+    #[test]
+    fn test_insert() {
+        let mut tree = BKTree::new(Levenshtein);
+        assert!(tree.is_empty());
+        assert_eq!(tree.insert("John"), 0);
+        assert_eq!(tree.insert("Jon"), 1);
+        assert_eq!(tree.len(), 2);
+        assert_eq!(tree.get(1), Some("Jon"));
+    }
+
+    #[test]
+    fn test_find_exact() {
+        let tree = BKTree::build(Levenshtein, names());
+        let found = tree.find("JOHN", 0);
+        let ids: Vec<usize> = found.iter().map(|n| n.id).collect();
+        assert_eq!(ids, vec![0, 8]);
+    }
+
+    #[test]
+    fn test_find_within_distance() {
+        let tree = BKTree::build(Levenshtein, names());
+        let found = tree.find("John", 1);
+        let found_names: Vec<&str> = found.iter().map(|n| &n.name[..]).collect();
+        assert_eq!(found_names, vec!["John", "john", "Jon", "Joan"]);
+    }
+
+    #[test]
+    fn test_find_matches_brute_force() {
+        use super::super::super::levenshtein::compute::levenshtein_distance;
+        let tree = BKTree::build(Levenshtein, names());
+        for max_distance in 0..4 {
+            let found = tree.find("Jonny", max_distance);
+            let expected = names()
+                .iter()
+                .filter(|name| {
+                    levenshtein_distance(&name.to_uppercase()[..], "JONNY") <= max_distance
+                })
+                .count();
+            assert_eq!(found.len(), expected);
+        }
+    }
+
+    #[test]
+    fn test_find_damerau() {
+        let tree = BKTree::build(DamerauLevenshtein, names());
+        let found = tree.find("John", 1);
+        let found_names: Vec<&str> = found.iter().map(|n| &n.name[..]).collect();
+        assert!(found_names.contains(&"Jhon"));
+    }
+
+    #[test]
+    fn test_nearest() {
+        let tree = BKTree::build(Levenshtein, names());
+        let nearest = tree.nearest("Mari", 2);
+        let found_names: Vec<&str> = nearest.iter().map(|n| &n.name[..]).collect();
+        assert_eq!(found_names, vec!["Mary", "Marie"]);
+        assert_eq!(nearest[0].distance, 1.0);
+    }
+
+    #[test]
+    fn test_empty_tree() {
+        let tree: BKTree<Levenshtein> = BKTree::default();
+        assert!(tree.find("John", 2).is_empty());
+        assert!(tree.nearest("John", 2).is_empty());
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let path = temp_path("bktree");
+        let tree = BKTree::build(Levenshtein, names());
+        tree.save(&path).unwrap();
+        let loaded: BKTree<Levenshtein> = BKTree::load(&path).unwrap();
+        assert_eq!(loaded.len(), tree.len());
+        assert_eq!(loaded.find("John", 1), tree.find("John", 1));
+
+        let mismatched = BKTree::<DamerauLevenshtein>::load(&path);
+        assert!(matches!(
+            mismatched,
+            Err(PersistError::MetricMismatch { .. })
+        ));
+        std::fs::remove_file(path).unwrap();
+    }
+}
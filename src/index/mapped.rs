@@ -0,0 +1,434 @@
+use super::super::blocking::keys::{self, BlockingKey};
+use super::super::ensemble::{self, Ensemble, EnsembleResult};
+use super::super::prelude::*;
+use super::super::soundex::encode;
+use super::name_index::{self, NameIndex};
+use super::persist::{self, IndexKind, PersistError, HEADER_LEN};
+use memmap2::Mmap;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+// Layout of a NameIndex file after the header. Integers are little endian u64.
+//   - meta_len, followed by the bincode encoding of NameIndexMeta
+//   - name_count, key_count, strings_len, keys_len, postings_len
+//   - string offsets: 3 x name_count + 1 offsets into strings, every name
+//     is stored as its original, cleaned and Soundex encoded forms
+//   - key offsets: key_count + 1 offsets into keys
+//   - posting offsets: key_count + 1 offsets into postings
+//   - strings, keys: UTF-8 blobs. Keys are sorted.
+//   - postings: name ids of every key, postings_len u64
+const FORMS: usize = 3;
+
+#[derive(Serialize, Deserialize)]
+struct NameIndexMeta {
+    kinds: Vec<BlockingKey>,
+    min_shared_keys: usize,
+    max_block_size: Option<usize>,
+}
+
+// soundex_form returns the Soundex code of every token of a cleaned name.
+fn soundex_form(cleaned: &str) -> String {
+    cleaned
+        .split_whitespace()
+        .map(encode::apply_soundex)
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+pub(crate) fn write<P: AsRef<Path>>(
+    index: &NameIndex,
+    names: &[String],
+    blocks: &HashMap<String, Vec<usize>>,
+    path: P,
+) -> Result<(), PersistError> {
+    let meta = bincode::serialize(&NameIndexMeta {
+        kinds: index.kinds().to_vec(),
+        min_shared_keys: index.min_shared_keys,
+        max_block_size: index.max_block_size,
+    })?;
+    let forms: Vec<(String, String)> = names
+        .par_iter()
+        .map(|name| {
+            let cleaned = index.clean(name);
+            let soundex = soundex_form(&cleaned);
+            (cleaned, soundex)
+        })
+        .collect();
+    let mut keys: Vec<&String> = blocks.keys().collect();
+    keys.sort_unstable();
+
+    let mut strings: Vec<u8> = Vec::new();
+    let mut string_offsets: Vec<u64> = vec![0];
+    for (name, (cleaned, soundex)) in names.iter().zip(forms.iter()) {
+        for form in [name, cleaned, soundex] {
+            strings.extend_from_slice(form.as_bytes());
+            string_offsets.push(strings.len() as u64);
+        }
+    }
+    let mut key_bytes: Vec<u8> = Vec::new();
+    let mut key_offsets: Vec<u64> = vec![0];
+    let mut postings: Vec<u64> = Vec::new();
+    let mut posting_offsets: Vec<u64> = vec![0];
+    for key in keys.iter() {
+        key_bytes.extend_from_slice(key.as_bytes());
+        key_offsets.push(key_bytes.len() as u64);
+        postings.extend(blocks[*key].iter().map(|id| *id as u64));
+        posting_offsets.push(postings.len() as u64);
+    }
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    persist::write_header(&mut writer, IndexKind::NameIndex)?;
+    writer.write_all(&(meta.len() as u64).to_le_bytes())?;
+    writer.write_all(&meta)?;
+    let counts = [
+        names.len(),
+        keys.len(),
+        strings.len(),
+        key_bytes.len(),
+        postings.len(),
+    ];
+    for count in counts.iter() {
+        writer.write_all(&(*count as u64).to_le_bytes())?;
+    }
+    for value in string_offsets
+        .iter()
+        .chain(key_offsets.iter())
+        .chain(posting_offsets.iter())
+    {
+        writer.write_all(&value.to_le_bytes())?;
+    }
+    writer.write_all(&strings)?;
+    writer.write_all(&key_bytes)?;
+    for id in postings.iter() {
+        writer.write_all(&id.to_le_bytes())?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+// MappedNameIndex is a NameIndex reopened from a file saved by NameIndex::save.
+// The file is memory mapped and read in place, opening it does not depend
+// on the number of names. Cleaned and Soundex encoded forms of names are
+// read from the file instead of being recomputed.
+//   - min_shared_keys, max_block_size: Loaded from the file, can be changed.
+pub struct MappedNameIndex {
+    mmap: Mmap,
+    kinds: Vec<BlockingKey>,
+    name_count: usize,
+    key_count: usize,
+    string_offsets: usize,
+    key_offsets: usize,
+    posting_offsets: usize,
+    strings: usize,
+    keys: usize,
+    postings: usize,
+    pub min_shared_keys: usize,
+    pub max_block_size: Option<usize>,
+}
+
+impl MappedNameIndex {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<MappedNameIndex, PersistError> {
+        let mmap = persist::open(path, IndexKind::NameIndex)?;
+        let corrupted = |reason: &str| PersistError::Corrupted(reason.to_owned());
+        let read = |offset: usize| -> Result<usize, PersistError> {
+            if offset + 8 > mmap.len() {
+                return Err(corrupted("truncated file"));
+            }
+            Ok(persist::read_u64(&mmap, offset) as usize)
+        };
+
+        let meta_len = read(HEADER_LEN)?;
+        let meta_start = HEADER_LEN + 8;
+        let meta_end = meta_start
+            .checked_add(meta_len)
+            .filter(|end| *end <= mmap.len())
+            .ok_or_else(|| corrupted("truncated metadata"))?;
+        let meta: NameIndexMeta = persist::decode(&mmap[meta_start..meta_end])?;
+
+        let name_count = read(meta_end)?;
+        let key_count = read(meta_end + 8)?;
+        let strings_len = read(meta_end + 16)?;
+        let keys_len = read(meta_end + 24)?;
+        let postings_len = read(meta_end + 32)?;
+
+        // Section sizes are checked against the file size before use.
+        let sections = [
+            (
+                name_count.checked_mul(FORMS).and_then(|n| n.checked_add(1)),
+                8,
+            ),
+            (key_count.checked_add(1), 8),
+            (key_count.checked_add(1), 8),
+            (Some(strings_len), 1),
+            (Some(keys_len), 1),
+            (Some(postings_len), 8),
+        ];
+        let mut starts = Vec::with_capacity(sections.len());
+        let mut position = meta_end + 40;
+        for (count, width) in sections.iter() {
+            starts.push(position);
+            position = count
+                .and_then(|count| count.checked_mul(*width))
+                .and_then(|size| position.checked_add(size))
+                .ok_or_else(|| corrupted("invalid section size"))?;
+        }
+        if position != mmap.len() {
+            return Err(corrupted("unexpected file size"));
+        }
+
+        let index = MappedNameIndex {
+            kinds: meta.kinds,
+            name_count,
+            key_count,
+            string_offsets: starts[0],
+            key_offsets: starts[1],
+            posting_offsets: starts[2],
+            strings: starts[3],
+            keys: starts[4],
+            postings: starts[5],
+            min_shared_keys: meta.min_shared_keys,
+            max_block_size: meta.max_block_size,
+            mmap,
+        };
+        if index.offset(index.string_offsets, name_count * FORMS) != strings_len
+            || index.offset(index.key_offsets, key_count) != keys_len
+            || index.offset(index.posting_offsets, key_count) != postings_len
+        {
+            return Err(corrupted("offsets do not match section sizes"));
+        }
+        Ok(index)
+    }
+
+    fn offset(&self, section: usize, i: usize) -> usize {
+        persist::read_u64(&self.mmap, section + i * 8) as usize
+    }
+
+    // slice returns the bytes between offsets i and i + 1 of a section,
+    // None if the offsets are out of bounds.
+    fn slice(&self, offsets: usize, i: usize, data: usize, data_end: usize) -> Option<&[u8]> {
+        let start = data.checked_add(self.offset(offsets, i))?;
+        let end = data.checked_add(self.offset(offsets, i + 1))?;
+        if start > end || end > data_end {
+            return None;
+        }
+        Some(&self.mmap[start..end])
+    }
+
+    fn form(&self, id: usize, form: usize) -> Option<&str> {
+        if id >= self.name_count {
+            return None;
+        }
+        let bytes = self.slice(
+            self.string_offsets,
+            id * FORMS + form,
+            self.strings,
+            self.keys,
+        )?;
+        std::str::from_utf8(bytes).ok()
+    }
+
+    fn key(&self, i: usize) -> &[u8] {
+        self.slice(self.key_offsets, i, self.keys, self.postings)
+            .unwrap_or(&[])
+    }
+
+    // block returns the ids of names having the blocking key.
+    fn block(&self, key: &str) -> Option<Vec<usize>> {
+        let mut low = 0;
+        let mut high = self.key_count;
+        while low < high {
+            let middle = (low + high) / 2;
+            match self.key(middle).cmp(key.as_bytes()) {
+                std::cmp::Ordering::Less => low = middle + 1,
+                std::cmp::Ordering::Greater => high = middle,
+                std::cmp::Ordering::Equal => {
+                    // Only the last offset is checked by open, the others
+                    // are checked here.
+                    let start = self.offset(self.posting_offsets, middle);
+                    let end = self.offset(self.posting_offsets, middle + 1);
+                    if start > end || end > (self.mmap.len() - self.postings) / 8 {
+                        return None;
+                    }
+                    let ids = (start..end)
+                        .map(|i| persist::read_u64(&self.mmap, self.postings + i * 8) as usize)
+                        .collect();
+                    return Some(ids);
+                }
+            }
+        }
+        None
+    }
+
+    pub fn len(&self) -> usize {
+        self.name_count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.name_count == 0
+    }
+
+    pub fn get(&self, id: usize) -> Option<&str> {
+        self.form(id, 0)
+    }
+
+    // cleaned returns the stored cleaned form of a name.
+    pub fn cleaned(&self, id: usize) -> Option<&str> {
+        self.form(id, 1)
+    }
+
+    // soundex returns the stored Soundex code of every token of a name,
+    // separated by whitespace.
+    pub fn soundex(&self, id: usize) -> Option<&str> {
+        self.form(id, 2)
+    }
+
+    pub fn kinds(&self) -> &[BlockingKey] {
+        &self.kinds
+    }
+
+    // candidates returns the ids of names sharing at least min_shared_keys
+    // blocking keys with the query, most shared keys first.
+    pub fn candidates(&self, query: &str) -> Vec<usize> {
+        let query_keys = keys::blocking_keys(&self.kinds, &self.clean(query)[..]);
+        let mut shared: HashMap<usize, usize> = HashMap::new();
+        for key in query_keys.iter() {
+            let block = match self.block(key) {
+                Some(block) => block,
+                None => continue,
+            };
+            if let Some(max_block_size) = self.max_block_size {
+                if block.len() > max_block_size {
+                    continue;
+                }
+            }
+            for id in block.into_iter() {
+                *shared.entry(id).or_insert(0) += 1;
+            }
+        }
+        name_index::rank_candidates(shared, self.min_shared_keys)
+    }
+
+    // search runs the ensemble on candidates only and returns the k best
+    // results scoring at least threshold, best first.
+    pub fn search(
+        &self,
+        ensemble: &Ensemble,
+        query: &str,
+        k: usize,
+        threshold: f64,
+    ) -> Vec<EnsembleResult> {
+        let candidates = self.candidates(query);
+        let query = ensemble.prepare_query(query);
+        let results = candidates.par_iter().filter_map(|id| {
+            let name = self.prepared(*id)?;
+            query.get_ensemble_result_above(&name, threshold)
+        });
+        ensemble::top_k(results, k)
+    }
+
+    // prepared returns a name prepared from its stored cleaned and Soundex
+    // encoded forms.
+    pub fn prepared(&self, id: usize) -> Option<PreparedName> {
+        let name = PreparedName::from_cleaned(self.get(id)?, self.cleaned(id)?.to_owned());
+        Some(name.with_soundex_tokens(self.soundex(id)?))
+    }
+}
+
+impl Clean for MappedNameIndex {}
+
+#[cfg(test)]
+mod test {
+    use super::super::super::blocking::keys::BlockingKey;
+    use super::super::super::ensemble::Ensemble;
+    use super::super::super::jaro::compare::JaroWinklerMatcher;
+    use super::super::super::prelude::*;
+    use super::super::name_index::NameIndex;
+    use super::super::persist::test::temp_path;
+    use super::super::persist::PersistError;
+    use super::MappedNameIndex;
+
+    fn build_index() -> NameIndex {
+        NameIndex::build(
+            vec![BlockingKey::Soundex, BlockingKey::SortedTokenPrefix(4)],
+            vec![
+                "John Doe",
+                "Jon Doe",
+                "Mary Smith",
+                "Doe, John",
+                "Peter Parker",
+                "John Smith",
+            ],
+        )
+    }
+
+    #[test]
+    fn test_save_and_open() {
+        let path = temp_path("mapped_name_index");
+        let mut index = build_index();
+        index.max_block_size = Some(3);
+        index.save(&path).unwrap();
+
+        let mapped = MappedNameIndex::open(&path).unwrap();
+        assert_eq!(mapped.len(), 6);
+        assert_eq!(mapped.kinds(), index.kinds());
+        assert_eq!(mapped.max_block_size, Some(3));
+        assert_eq!(mapped.get(3), Some("Doe, John"));
+        assert_eq!(mapped.cleaned(3), Some("DOE  JOHN"));
+        assert_eq!(mapped.soundex(3), Some("D000 J500"));
+        assert_eq!(mapped.get(6), None);
+        for query in ["John Doe", "Mary Smith", "Jon Smith", "Nobody"] {
+            assert_eq!(mapped.candidates(query), index.candidates(query));
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_search() {
+        let path = temp_path("mapped_name_index_search");
+        build_index().save(&path).unwrap();
+        let mapped = MappedNameIndex::open(&path).unwrap();
+        let matchers: Vec<Box<dyn Matcher>> = vec![Box::new(JaroWinklerMatcher::default())];
+        let ensemble = Ensemble::new(matchers);
+        let results = mapped.search(&ensemble, "John Doe", 2, 0.0);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].name2, "John Doe");
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_open_truncated_file() {
+        let path = temp_path("mapped_name_index_truncated");
+        build_index().save(&path).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::write(&path, &bytes[..bytes.len() - 1]).unwrap();
+        assert!(matches!(
+            MappedNameIndex::open(&path),
+            Err(PersistError::Corrupted(_))
+        ));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_corrupted_posting_offsets() {
+        let path = temp_path("mapped_name_index_postings");
+        let index = build_index();
+        index.save(&path).unwrap();
+        let offsets = MappedNameIndex::open(&path).unwrap().posting_offsets;
+        let mut bytes = std::fs::read(&path).unwrap();
+        for i in 1..3 {
+            let position = offsets + i * 8;
+            bytes[position..position + 8].copy_from_slice(&(1u64 << 40).to_le_bytes());
+        }
+        std::fs::write(&path, bytes).unwrap();
+
+        let mapped = MappedNameIndex::open(&path).unwrap();
+        for query in ["John Doe", "Mary Smith", "Jon Smith", "Peter Parker"] {
+            mapped.candidates(query);
+        }
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,201 @@
+use super::super::levenshtein::compute;
+use super::super::prelude::*;
+use serde::{Deserialize, Serialize};
+
+// Metric measures the distance between two cleaned names.
+// Distances must satisfy the triangle inequality for tree indexes to
+// return exact results.
+pub trait Metric: Clean + Send + Sync {
+    fn distance(&self, s1: &str, s2: &str) -> f64;
+}
+
+// EditMetric is a Metric with integer distances, as required by BKTree.
+pub trait EditMetric: Metric {
+    fn edit_distance(&self, s1: &str, s2: &str) -> usize;
+}
+
+// Levenshtein counts insertions, deletions and substitutions.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+pub struct Levenshtein;
+
+impl Clean for Levenshtein {}
+
+impl Named for Levenshtein {
+    fn get_name(&self) -> &str {
+        "Levenshtein"
+    }
+}
+
+impl Metric for Levenshtein {
+    fn distance(&self, s1: &str, s2: &str) -> f64 {
+        self.edit_distance(s1, s2) as f64
+    }
+}
+
+impl EditMetric for Levenshtein {
+    fn edit_distance(&self, s1: &str, s2: &str) -> usize {
+        compute::levenshtein_distance(s1, s2)
+    }
+}
+
+// DamerauLevenshtein counts insertions, deletions, substitutions and
+// transpositions of adjacent characters.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+pub struct DamerauLevenshtein;
+
+impl Clean for DamerauLevenshtein {}
+
+impl Named for DamerauLevenshtein {
+    fn get_name(&self) -> &str {
+        "Damerau-Levenshtein"
+    }
+}
+
+impl Metric for DamerauLevenshtein {
+    fn distance(&self, s1: &str, s2: &str) -> f64 {
+        self.edit_distance(s1, s2) as f64
+    }
+}
+
+impl EditMetric for DamerauLevenshtein {
+    fn edit_distance(&self, s1: &str, s2: &str) -> usize {
+        compute::damerau_levenshtein_distance(s1, s2)
+    }
+}
+
+// NormalizedLevenshtein is the Levenshtein distance normalized
+// between 0.0 and 1.0.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+pub struct NormalizedLevenshtein;
+
+impl Clean for NormalizedLevenshtein {}
+
+impl Named for NormalizedLevenshtein {
+    fn get_name(&self) -> &str {
+        "Normalized-Levenshtein"
+    }
+}
+
+impl Metric for NormalizedLevenshtein {
+    fn distance(&self, s1: &str, s2: &str) -> f64 {
+        compute::normalized_levenshtein_distance(s1, s2)
+    }
+}
+
+// Neighbour is a name returned by a metric index search.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Neighbour {
+    pub id: usize,
+    pub name: String,
+    pub distance: f64,
+}
+
+// sort_by_distance sorts neighbours closest first, ties are broken by id.
+pub(crate) fn sort_by_distance(neighbours: &mut [Neighbour]) {
+    neighbours.sort_by(|a, b| {
+        a.distance
+            .total_cmp(&b.distance)
+            .then_with(|| a.id.cmp(&b.id))
+    });
+}
+
+// NearestNeighbours keeps the k closest neighbours seen so far.
+pub(crate) struct NearestNeighbours {
+    k: usize,
+    neighbours: Vec<Neighbour>,
+}
+
+impl NearestNeighbours {
+    pub(crate) fn new(k: usize) -> NearestNeighbours {
+        NearestNeighbours {
+            k,
+            neighbours: Vec::with_capacity(k + 1),
+        }
+    }
+
+    // radius returns the distance a neighbour must not exceed to be kept.
+    pub(crate) fn radius(&self) -> f64 {
+        if self.neighbours.len() < self.k {
+            f64::INFINITY
+        } else {
+            self.neighbours[self.k - 1].distance
+        }
+    }
+
+    pub(crate) fn push(&mut self, neighbour: Neighbour) {
+        if self.k == 0 || neighbour.distance > self.radius() {
+            return;
+        }
+        let position = self
+            .neighbours
+            .iter()
+            .position(|other| {
+                other
+                    .distance
+                    .total_cmp(&neighbour.distance)
+                    .then_with(|| other.id.cmp(&neighbour.id))
+                    .is_gt()
+            })
+            .unwrap_or(self.neighbours.len());
+        self.neighbours.insert(position, neighbour);
+        self.neighbours.truncate(self.k);
+    }
+
+    pub(crate) fn into_vec(self) -> Vec<Neighbour> {
+        self.neighbours
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::super::prelude::*;
+    use super::*;
+
+    fn neighbour(id: usize, distance: f64) -> Neighbour {
+        Neighbour {
+            id,
+            name: String::new(),
+            distance,
+        }
+    }
+
+    #[test]
+    fn test_levenshtein_metric() {
+        let metric = Levenshtein;
+        assert_eq!(metric.distance("JOHN", "JHON"), 2.0);
+        assert_eq!(metric.clean("john-doe"), "JOHN DOE");
+    }
+
+    #[test]
+    fn test_damerau_levenshtein_metric() {
+        let metric = DamerauLevenshtein;
+        assert_eq!(metric.edit_distance("JOHN", "JHON"), 1);
+    }
+
+    #[test]
+    fn test_normalized_levenshtein_metric() {
+        let metric = NormalizedLevenshtein;
+        assert_eq!(metric.distance("JOHN", "JOHN"), 0.0);
+        assert_eq!(metric.distance("AB", "CD"), 2.0 / 3.0);
+    }
+
+    #[test]
+    fn test_nearest_neighbours() {
+        let mut nearest = NearestNeighbours::new(2);
+        assert_eq!(nearest.radius(), f64::INFINITY);
+        nearest.push(neighbour(0, 3.0));
+        nearest.push(neighbour(1, 1.0));
+        nearest.push(neighbour(2, 2.0));
+        nearest.push(neighbour(3, 5.0));
+        assert_eq!(nearest.radius(), 2.0);
+        let ids: Vec<usize> = nearest.into_vec().iter().map(|n| n.id).collect();
+        assert_eq!(ids, vec![1, 2]);
+    }
+
+    #[test]
+    fn test_nearest_neighbours_zero() {
+        let mut nearest = NearestNeighbours::new(0);
+        nearest.push(neighbour(0, 1.0));
+        assert!(nearest.into_vec().is_empty());
+    }
+}
@@ -0,0 +1,455 @@
+use super::super::ensemble::{self, Ensemble, EnsembleResult};
+use super::super::jaccard::compare::JaccardMatcher;
+use super::super::prelude::*;
+use super::super::shared::compute;
+use super::super::soundex::compare::SoundexJaccardMatcher;
+use super::persist::{self, IndexKind, PersistError};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::Path;
+
+// Mersenne prime 2^61 - 1, modulus of the permutation hash functions.
+const MERSENNE_PRIME: u64 = (1 << 61) - 1;
+
+// fnv1a returns the 64 bit FNV-1a hash of bytes.
+// Unlike std's hashers, it is stable across Rust versions and platforms.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in bytes.iter() {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+// splitmix64 advances state and returns the next pseudo random number.
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+// TokenSource describes which token set of a name is hashed.
+//   - Tokens: Token set used by JaccardMatcher.
+//   - SoundexTokens: Soundex set used by SoundexJaccardMatcher.
+//   - QGrams(q): Set of q-grams of every token.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum TokenSource {
+    Tokens,
+    SoundexTokens,
+    QGrams(usize),
+}
+
+impl TokenSource {
+    pub fn token_set(&self, name: &str) -> HashSet<String> {
+        match self {
+            TokenSource::Tokens => {
+                let matcher = JaccardMatcher::default();
+                matcher.as_tokenized_set(&matcher.clean(name)[..])
+            }
+            TokenSource::SoundexTokens => {
+                let matcher = SoundexJaccardMatcher::default();
+                matcher.as_tokenized_set(&matcher.clean(name)[..])
+            }
+            TokenSource::QGrams(q) => {
+                let cleaned = JaccardMatcher::default().clean(name);
+                compute::qgrams(&cleaned[..], *q).into_iter().collect()
+            }
+        }
+    }
+}
+
+// MinHasher generates MinHash signatures of token sets. The probability that
+// two signatures agree at any position is the Jaccard Index of the two sets.
+// Hash functions are derived from seed, so signatures are reproducible.
+#[derive(Serialize, Deserialize)]
+pub struct MinHasher {
+    seed: u64,
+    coefficients: Vec<(u64, u64)>,
+}
+
+impl MinHasher {
+    pub fn new(num_perm: usize, seed: u64) -> MinHasher {
+        let mut state = seed;
+        let coefficients = (0..num_perm)
+            .map(|_| {
+                let a = splitmix64(&mut state) % (MERSENNE_PRIME - 1) + 1;
+                let b = splitmix64(&mut state) % MERSENNE_PRIME;
+                (a, b)
+            })
+            .collect();
+        MinHasher { seed, coefficients }
+    }
+
+    pub fn num_perm(&self) -> usize {
+        self.coefficients.len()
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    // signature returns the minimum of every hash function over tokens.
+    // Empty sets get a signature of u64::MAX.
+    pub fn signature(&self, tokens: &HashSet<String>) -> Vec<u64> {
+        let mut signature = vec![u64::MAX; self.coefficients.len()];
+        for token in tokens.iter() {
+            let x = (fnv1a(token.as_bytes()) % MERSENNE_PRIME) as u128;
+            for (i, (a, b)) in self.coefficients.iter().enumerate() {
+                let hash = ((*a as u128 * x + *b as u128) % MERSENNE_PRIME as u128) as u64;
+                if hash < signature[i] {
+                    signature[i] = hash;
+                }
+            }
+        }
+        signature
+    }
+}
+
+// estimate_jaccard returns the fraction of positions where two signatures
+// agree, an estimate of the Jaccard Index of the underlying sets.
+pub fn estimate_jaccard(signature_1: &[u64], signature_2: &[u64]) -> f64 {
+    if signature_1.is_empty() || signature_1.len() != signature_2.len() {
+        return 0.0;
+    }
+    let agreeing = signature_1
+        .iter()
+        .zip(signature_2.iter())
+        .filter(|(a, b)| a == b)
+        .count();
+    agreeing as f64 / signature_1.len() as f64
+}
+
+// optimal_bands returns the number of bands and rows per band whose
+// S-curve threshold (1 / bands) ^ (1 / rows) is closest to threshold.
+pub fn optimal_bands(threshold: f64, num_perm: usize) -> (usize, usize) {
+    let mut best = (1, num_perm.max(1));
+    let mut best_error = f64::INFINITY;
+    for bands in 1..=num_perm {
+        let rows = num_perm / bands;
+        let curve_threshold = (1.0 / bands as f64).powf(1.0 / rows as f64);
+        let error = (curve_threshold - threshold).abs();
+        if error < best_error {
+            best_error = error;
+            best = (bands, rows);
+        }
+    }
+    best
+}
+
+// LshConfigError is returned when an LshIndex is configured without bands
+// or rows, such an index could never return a candidate.
+#[derive(Debug, PartialEq)]
+pub enum LshConfigError {
+    NoBands,
+    NoRows,
+}
+
+impl fmt::Display for LshConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LshConfigError::NoBands => write!(f, "LshIndex needs at least one band"),
+            LshConfigError::NoRows => write!(f, "LshIndex needs at least one row per band"),
+        }
+    }
+}
+
+impl std::error::Error for LshConfigError {}
+
+// LshIndex groups names whose MinHash signatures agree on a whole band.
+// Names sharing a bucket in any band are candidates, names with a Jaccard
+// Index above the target threshold are likely to share at least one.
+#[derive(Serialize, Deserialize)]
+pub struct LshIndex {
+    source: TokenSource,
+    hasher: MinHasher,
+    bands: usize,
+    rows: usize,
+    names: Vec<String>,
+    signatures: Vec<Vec<u64>>,
+    buckets: Vec<HashMap<u64, Vec<usize>>>,
+}
+
+impl LshIndex {
+    // new returns an error when bands or rows is 0.
+    pub fn new(
+        source: TokenSource,
+        bands: usize,
+        rows: usize,
+        seed: u64,
+    ) -> Result<LshIndex, LshConfigError> {
+        if bands == 0 {
+            return Err(LshConfigError::NoBands);
+        }
+        if rows == 0 {
+            return Err(LshConfigError::NoRows);
+        }
+        Ok(LshIndex::with_bands(source, bands, rows, seed))
+    }
+
+    fn with_bands(source: TokenSource, bands: usize, rows: usize, seed: u64) -> LshIndex {
+        LshIndex {
+            source,
+            hasher: MinHasher::new(bands * rows, seed),
+            bands,
+            rows,
+            names: Vec::new(),
+            signatures: Vec::new(),
+            buckets: vec![HashMap::new(); bands],
+        }
+    }
+
+    // with_threshold picks the bands and rows matching a target Jaccard threshold.
+    pub fn with_threshold(
+        source: TokenSource,
+        threshold: f64,
+        num_perm: usize,
+        seed: u64,
+    ) -> LshIndex {
+        // optimal_bands always returns at least one band and one row.
+        let (bands, rows) = optimal_bands(threshold, num_perm);
+        LshIndex::with_bands(source, bands, rows, seed)
+    }
+
+    pub fn bands(&self) -> usize {
+        self.bands
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub fn get(&self, id: usize) -> Option<&str> {
+        self.names.get(id).map(|name| &name[..])
+    }
+
+    pub fn signature(&self, name: &str) -> Vec<u64> {
+        self.hasher.signature(&self.source.token_set(name))
+    }
+
+    // band_hashes returns the hash of every band of a signature.
+    fn band_hashes(&self, signature: &[u64]) -> Vec<u64> {
+        signature
+            .chunks(self.rows)
+            .take(self.bands)
+            .map(|band| {
+                let bytes: Vec<u8> = band.iter().flat_map(|value| value.to_le_bytes()).collect();
+                fnv1a(&bytes)
+            })
+            .collect()
+    }
+
+    // insert adds a name to the index and returns its id.
+    pub fn insert(&mut self, name: &str) -> usize {
+        let signature = self.signature(name);
+        self.insert_with_signature(name.to_owned(), signature)
+    }
+
+    // extend adds every name to the index, signatures are computed in parallel.
+    pub fn extend<I, S>(&mut self, names: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let names: Vec<String> = names
+            .into_iter()
+            .map(|name| name.as_ref().to_owned())
+            .collect();
+        let signatures: Vec<Vec<u64>> = names
+            .par_iter()
+            .map(|name| self.signature(&name[..]))
+            .collect();
+        for (name, signature) in names.into_iter().zip(signatures) {
+            self.insert_with_signature(name, signature);
+        }
+    }
+
+    fn insert_with_signature(&mut self, name: String, signature: Vec<u64>) -> usize {
+        let id = self.names.len();
+        for (band, hash) in self.band_hashes(&signature).into_iter().enumerate() {
+            self.buckets[band].entry(hash).or_default().push(id);
+        }
+        self.names.push(name);
+        self.signatures.push(signature);
+        id
+    }
+
+    // candidates returns the ids of names sharing a bucket with the query
+    // along with their estimated Jaccard Index, highest first.
+    pub fn candidates(&self, query: &str) -> Vec<(usize, f64)> {
+        let signature = self.signature(query);
+        let mut ids: HashSet<usize> = HashSet::new();
+        for (band, hash) in self.band_hashes(&signature).into_iter().enumerate() {
+            if let Some(bucket) = self.buckets[band].get(&hash) {
+                ids.extend(bucket.iter());
+            }
+        }
+        let mut candidates: Vec<(usize, f64)> = ids
+            .into_iter()
+            .map(|id| (id, estimate_jaccard(&signature, &self.signatures[id])))
+            .collect();
+        candidates.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        candidates
+    }
+
+    // search runs the ensemble on candidates only and returns the k best
+    // results scoring at least threshold, best first.
+    pub fn search(
+        &self,
+        ensemble: &Ensemble,
+        query: &str,
+        k: usize,
+        threshold: f64,
+    ) -> Vec<EnsembleResult> {
+        let candidates = self.candidates(query);
+        let results = candidates.par_iter().filter_map(|(id, _)| {
+            ensemble.get_ensemble_result_above(query, &self.names[*id][..], threshold)
+        });
+        ensemble::top_k(results, k)
+    }
+
+    // save writes the index to a versioned binary file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), PersistError> {
+        persist::save(self, IndexKind::LshIndex, path)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<LshIndex, PersistError> {
+        persist::load(IndexKind::LshIndex, path)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::super::ensemble::Ensemble;
+    use super::super::super::jaccard::compare::JaccardMatcher;
+    use super::super::super::prelude::*;
+    use super::super::super::shared::compute::jaccard_index;
+    use super::super::persist::test::temp_path;
+    use super::{LshConfigError, LshIndex, MinHasher, TokenSource};
+
+    #[test]
+    fn test_token_source_tokens() {
+        let tokens = TokenSource::Tokens.token_set("John, Doe");
+        assert_eq!(tokens.len(), 2);
+        assert!(tokens.contains("JOHN"));
+    }
+
+    #[test]
+    fn test_token_source_soundex() {
+        let tokens = TokenSource::SoundexTokens.token_set("Robert Rupert");
+        assert_eq!(tokens.len(), 1);
+        assert!(tokens.contains("R163"));
+    }
+
+    #[test]
+    fn test_token_source_qgrams() {
+        let tokens = TokenSource::QGrams(2).token_set("Anna");
+        assert_eq!(tokens.len(), 3);
+    }
+
+    #[test]
+    fn test_signature_is_deterministic() {
+        let tokens = TokenSource::Tokens.token_set("John Doe");
+        let signature_1 = MinHasher::new(64, 42).signature(&tokens);
+        let signature_2 = MinHasher::new(64, 42).signature(&tokens);
+        let signature_3 = MinHasher::new(64, 7).signature(&tokens);
+        assert_eq!(signature_1, signature_2);
+        assert_ne!(signature_1, signature_3);
+    }
+
+    #[test]
+    fn test_estimate_jaccard() {
+        let source = TokenSource::QGrams(2);
+        let hasher = MinHasher::new(512, 1);
+        let set_1 = source.token_set("Christopher Columbus");
+        let set_2 = source.token_set("Kristopher Colombus");
+        let estimate =
+            super::estimate_jaccard(&hasher.signature(&set_1), &hasher.signature(&set_2));
+        let exact = jaccard_index(&set_1, &set_2);
+        assert!((estimate - exact).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_estimate_jaccard_identical() {
+        let hasher = MinHasher::new(16, 1);
+        let signature = hasher.signature(&TokenSource::Tokens.token_set("John Doe"));
+        assert_eq!(super::estimate_jaccard(&signature, &signature), 1.0);
+    }
+
+    #[test]
+    fn test_optimal_bands() {
+        let (bands, rows) = super::optimal_bands(0.5, 128);
+        assert!(bands * rows <= 128);
+        let curve_threshold = (1.0 / bands as f64).powf(1.0 / rows as f64);
+        assert!((curve_threshold - 0.5).abs() < 0.05);
+    }
+
+    fn build_index() -> LshIndex {
+        let mut index = LshIndex::with_threshold(TokenSource::QGrams(2), 0.5, 128, 1);
+        index.extend(vec![
+            "Christopher Columbus",
+            "Kristopher Columbus",
+            "Mary Smith",
+            "Peter Parker",
+        ]);
+        index
+    }
+
+    #[test]
+    fn test_new() {
+        let index = LshIndex::new(TokenSource::Tokens, 4, 8, 42).unwrap();
+        assert_eq!((index.bands(), index.rows()), (4, 8));
+        assert_eq!(
+            LshIndex::new(TokenSource::Tokens, 4, 0, 42).err(),
+            Some(LshConfigError::NoRows)
+        );
+        assert_eq!(
+            LshIndex::new(TokenSource::Tokens, 0, 4, 42).err(),
+            Some(LshConfigError::NoBands)
+        );
+    }
+
+    #[test]
+    fn test_candidates() {
+        let index = build_index();
+        let candidates = index.candidates("Christopher Colombus");
+        let ids: Vec<usize> = candidates.iter().map(|(id, _)| *id).collect();
+        assert!(ids.contains(&0));
+        assert!(ids.contains(&1));
+        assert!(!ids.contains(&3));
+    }
+
+    #[test]
+    fn test_search() {
+        let index = build_index();
+        let matchers: Vec<Box<dyn Matcher>> = vec![Box::new(JaccardMatcher::default())];
+        let ensemble = Ensemble::new(matchers);
+        let results = index.search(&ensemble, "Columbus, Christopher", 1, 0.0);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].name2, "Christopher Columbus");
+        assert_eq!(results[0].score, 1.0);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let path = temp_path("lsh");
+        let index = build_index();
+        index.save(&path).unwrap();
+        let loaded = LshIndex::load(&path).unwrap();
+        assert_eq!(loaded.len(), index.len());
+        assert_eq!(loaded.candidates("Jon Doe"), index.candidates("Jon Doe"));
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,8 @@
+pub mod bktree;
+pub mod mapped;
+pub mod metric;
+pub mod minhash;
+pub mod name_index;
+pub mod persist;
+pub mod symspell;
+pub mod vptree;
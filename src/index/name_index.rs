@@ -0,0 +1,260 @@
+use super::super::blocking::keys::{self, BlockingKey};
+use super::super::ensemble::{self, Ensemble, EnsembleResult};
+use super::super::prelude::*;
+use super::mapped;
+use super::persist::PersistError;
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::path::Path;
+
+// NameIndex ingests a corpus of names once and groups them by blocking keys.
+// A query is only compared against names sharing enough blocking keys with it,
+// instead of every name in the corpus.
+//   - min_shared_keys: Minimum number of blocking keys a name must share
+//     with the query to become a candidate.
+//   - max_block_size: Blocks larger than this are ignored at query time,
+//     they are too common to tell names apart.
+pub struct NameIndex {
+    kinds: Vec<BlockingKey>,
+    names: Vec<String>,
+    blocks: HashMap<String, Vec<usize>>,
+    pub min_shared_keys: usize,
+    pub max_block_size: Option<usize>,
+}
+
+impl NameIndex {
+    pub fn new(kinds: Vec<BlockingKey>) -> NameIndex {
+        NameIndex {
+            kinds,
+            names: Vec::new(),
+            blocks: HashMap::new(),
+            min_shared_keys: 1,
+            max_block_size: None,
+        }
+    }
+
+    // build creates an index over names using every kind of blocking key.
+    // Blocking keys are computed in parallel.
+    pub fn build<I, S>(kinds: Vec<BlockingKey>, names: I) -> NameIndex
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut index = NameIndex::new(kinds);
+        index.extend(names);
+        index
+    }
+
+    // insert adds a name to the index and returns its id.
+    pub fn insert(&mut self, name: &str) -> usize {
+        let name_keys = keys::blocking_keys(&self.kinds, &self.clean(name)[..]);
+        self.insert_with_keys(name.to_owned(), name_keys)
+    }
+
+    // extend adds every name to the index.
+    // Blocking keys are computed in parallel.
+    pub fn extend<I, S>(&mut self, names: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let names: Vec<String> = names
+            .into_iter()
+            .map(|name| name.as_ref().to_owned())
+            .collect();
+        let name_keys: Vec<Vec<String>> = names
+            .par_iter()
+            .map(|name| keys::blocking_keys(&self.kinds, &self.clean(name)[..]))
+            .collect();
+        for (name, name_keys) in names.into_iter().zip(name_keys) {
+            self.insert_with_keys(name, name_keys);
+        }
+    }
+
+    fn insert_with_keys(&mut self, name: String, name_keys: Vec<String>) -> usize {
+        let id = self.names.len();
+        self.names.push(name);
+        for key in name_keys.into_iter() {
+            self.blocks.entry(key).or_default().push(id);
+        }
+        id
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub fn get(&self, id: usize) -> Option<&str> {
+        self.names.get(id).map(|name| &name[..])
+    }
+
+    pub fn kinds(&self) -> &[BlockingKey] {
+        &self.kinds
+    }
+
+    // candidates returns the ids of names sharing at least min_shared_keys
+    // blocking keys with the query, most shared keys first.
+    pub fn candidates(&self, query: &str) -> Vec<usize> {
+        let query_keys = keys::blocking_keys(&self.kinds, &self.clean(query)[..]);
+        let mut shared: HashMap<usize, usize> = HashMap::new();
+        for key in query_keys.iter() {
+            let block = match self.blocks.get(key) {
+                Some(block) => block,
+                None => continue,
+            };
+            if let Some(max_block_size) = self.max_block_size {
+                if block.len() > max_block_size {
+                    continue;
+                }
+            }
+            for id in block.iter() {
+                *shared.entry(*id).or_insert(0) += 1;
+            }
+        }
+
+        rank_candidates(shared, self.min_shared_keys)
+    }
+
+    // search runs the ensemble on candidates only and returns the k best
+    // results scoring at least threshold, best first.
+    pub fn search(
+        &self,
+        ensemble: &Ensemble,
+        query: &str,
+        k: usize,
+        threshold: f64,
+    ) -> Vec<EnsembleResult> {
+        let candidates = self.candidates(query);
+        let query = ensemble.prepare_query(query);
+        let results = candidates.par_iter().filter_map(|id| {
+            let name = PreparedName::new(&self.names[*id][..]);
+            query.get_ensemble_result_above(&name, threshold)
+        });
+        ensemble::top_k(results, k)
+    }
+
+    // save writes the index to a versioned binary file, along with the
+    // cleaned and Soundex encoded forms of every name.
+    // The file is reopened with MappedNameIndex::open.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), PersistError> {
+        mapped::write(self, &self.names, &self.blocks, path)
+    }
+}
+
+// rank_candidates keeps ids sharing at least min_shared_keys blocking keys,
+// most shared keys first.
+pub(crate) fn rank_candidates(shared: HashMap<usize, usize>, min_shared_keys: usize) -> Vec<usize> {
+    let mut candidates: Vec<(usize, usize)> = shared
+        .into_iter()
+        .filter(|(_, count)| *count >= min_shared_keys)
+        .collect();
+    candidates.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    candidates.into_iter().map(|(id, _)| id).collect()
+}
+
+impl Default for NameIndex {
+    // Default index blocks on Soundex codes and sorted token prefixes of length 4.
+    fn default() -> NameIndex {
+        NameIndex::new(vec![
+            BlockingKey::Soundex,
+            BlockingKey::SortedTokenPrefix(4),
+        ])
+    }
+}
+
+impl Clean for NameIndex {}
+
+#[cfg(test)]
+mod test {
+    use super::super::super::blocking::keys::BlockingKey;
+    use super::super::super::ensemble::Ensemble;
+    use super::super::super::jaro::compare::JaroWinklerMatcher;
+    use super::super::super::prelude::*;
+    use super::super::super::soundex::compare::SoundexJaccardMatcher;
+    use super::NameIndex;
+
+    fn build_ensemble() -> Ensemble {
+        let matchers: Vec<Box<dyn Matcher>> = vec![
+            Box::new(JaroWinklerMatcher::default()),
+            Box::new(SoundexJaccardMatcher::default()),
+        ];
+        let mut ensemble = Ensemble::new(matchers);
+        ensemble.set_equal_weight();
+        ensemble
+    }
+
+    fn build_index() -> NameIndex {
+        NameIndex::build(
+            vec![BlockingKey::Soundex, BlockingKey::SortedTokenPrefix(4)],
+            vec![
+                "John Doe",
+                "Jon Doe",
+                "Mary Smith",
+                "Doe, John",
+                "Peter Parker",
+                "John Smith",
+            ],
+        )
+    }
+
+    #[test]
+    fn test_insert() {
+        let mut index = NameIndex::default();
+        assert!(index.is_empty());
+        let id = index.insert("John Doe");
+        assert_eq!(id, 0);
+        assert_eq!(index.len(), 1);
+        assert_eq!(index.get(0), Some("John Doe"));
+    }
+
+    #[test]
+    fn test_candidates() {
+        let index = build_index();
+        let candidates = index.candidates("John Doe");
+        assert!(candidates.contains(&0));
+        assert!(candidates.contains(&1));
+        assert!(candidates.contains(&3));
+        assert!(!candidates.contains(&2));
+        assert!(!candidates.contains(&4));
+    }
+
+    #[test]
+    fn test_candidates_min_shared_keys() {
+        let mut index = build_index();
+        assert!(index.candidates("John Doe").contains(&5));
+        index.min_shared_keys = 2;
+        let candidates = index.candidates("John Doe");
+        assert_eq!(candidates, vec![0, 1, 3]);
+    }
+
+    #[test]
+    fn test_candidates_max_block_size() {
+        let mut index = build_index();
+        index.max_block_size = Some(3);
+        let candidates = index.candidates("John Doe");
+        assert_eq!(candidates, vec![0, 1, 3]);
+    }
+
+    #[test]
+    fn test_search_top_k() {
+        let index = build_index();
+        let ensemble = build_ensemble();
+        let results = index.search(&ensemble, "John Doe", 2, 0.0);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].name2, "John Doe");
+        assert!(results[0].score >= results[1].score);
+    }
+
+    #[test]
+    fn test_search_threshold() {
+        let index = build_index();
+        let ensemble = build_ensemble();
+        let results = index.search(&ensemble, "John Doe", 10, 0.99);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].score, 1.0);
+    }
+}
@@ -0,0 +1,256 @@
+use bincode::Options;
+use memmap2::Mmap;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+// Every index file starts with a 16 bytes header:
+//   - 8 bytes magic
+//   - 4 bytes format version, little endian
+//   - 4 bytes index kind, little endian
+pub const MAGIC: [u8; 8] = *b"NAMEIDX\0";
+pub const FORMAT_VERSION: u32 = 1;
+pub const HEADER_LEN: usize = 16;
+
+// IndexKind identifies the index stored in a file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IndexKind {
+    NameIndex = 1,
+    BKTree = 2,
+    VPTree = 3,
+    SymSpell = 4,
+    SymSpellNameIndex = 5,
+    LshIndex = 6,
+}
+
+impl IndexKind {
+    fn from_u32(value: u32) -> Option<IndexKind> {
+        match value {
+            1 => Some(IndexKind::NameIndex),
+            2 => Some(IndexKind::BKTree),
+            3 => Some(IndexKind::VPTree),
+            4 => Some(IndexKind::SymSpell),
+            5 => Some(IndexKind::SymSpellNameIndex),
+            6 => Some(IndexKind::LshIndex),
+            _ => None,
+        }
+    }
+}
+
+// PersistError is returned when an index can not be saved or loaded.
+#[derive(Debug)]
+pub enum PersistError {
+    Io(io::Error),
+    Encoding(bincode::Error),
+    InvalidMagic,
+    UnsupportedVersion(u32),
+    KindMismatch { expected: IndexKind, found: u32 },
+    MetricMismatch { expected: String, found: String },
+    Corrupted(String),
+}
+
+impl fmt::Display for PersistError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PersistError::Io(err) => write!(f, "io error: {}", err),
+            PersistError::Encoding(err) => write!(f, "encoding error: {}", err),
+            PersistError::InvalidMagic => write!(f, "not an index file"),
+            PersistError::UnsupportedVersion(version) => {
+                write!(f, "unsupported format version {}", version)
+            }
+            PersistError::KindMismatch { expected, found } => {
+                write!(f, "expected {:?} index, found kind {}", expected, found)
+            }
+            PersistError::MetricMismatch { expected, found } => {
+                write!(f, "expected {} metric, found {}", expected, found)
+            }
+            PersistError::Corrupted(reason) => write!(f, "corrupted index file: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for PersistError {}
+
+impl From<io::Error> for PersistError {
+    fn from(err: io::Error) -> PersistError {
+        PersistError::Io(err)
+    }
+}
+
+impl From<bincode::Error> for PersistError {
+    fn from(err: bincode::Error) -> PersistError {
+        PersistError::Encoding(err)
+    }
+}
+
+pub(crate) fn write_header<W: Write>(writer: &mut W, kind: IndexKind) -> io::Result<()> {
+    writer.write_all(&MAGIC)?;
+    writer.write_all(&FORMAT_VERSION.to_le_bytes())?;
+    writer.write_all(&(kind as u32).to_le_bytes())
+}
+
+// open maps an index file in memory and checks its header.
+pub(crate) fn open<P: AsRef<Path>>(path: P, kind: IndexKind) -> Result<Mmap, PersistError> {
+    let file = File::open(path)?;
+    // Safety: the file must not be modified while it is mapped.
+    let mmap = unsafe { Mmap::map(&file)? };
+    if mmap.len() < HEADER_LEN || mmap[..8] != MAGIC {
+        return Err(PersistError::InvalidMagic);
+    }
+    let version = read_u32(&mmap, 8);
+    if version != FORMAT_VERSION {
+        return Err(PersistError::UnsupportedVersion(version));
+    }
+    let found = read_u32(&mmap, 12);
+    if IndexKind::from_u32(found) != Some(kind) {
+        return Err(PersistError::KindMismatch {
+            expected: kind,
+            found,
+        });
+    }
+    Ok(mmap)
+}
+
+// save writes the header followed by the bincode encoding of value.
+pub fn save<T: Serialize, P: AsRef<Path>>(
+    value: &T,
+    kind: IndexKind,
+    path: P,
+) -> Result<(), PersistError> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_header(&mut writer, kind)?;
+    bincode::serialize_into(&mut writer, value)?;
+    writer.flush()?;
+    Ok(())
+}
+
+// decode reads a bincode value, allocating at most the size of bytes, so a
+// corrupted length prefix is reported as an error.
+pub(crate) fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, PersistError> {
+    let options = bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .with_limit(bytes.len() as u64);
+    Ok(options.deserialize(bytes)?)
+}
+
+// load decodes the whole value following the header onto the heap, so it
+// takes time linear in the file size. Only MappedNameIndex reads its file in
+// place.
+pub fn load<T: DeserializeOwned, P: AsRef<Path>>(
+    kind: IndexKind,
+    path: P,
+) -> Result<T, PersistError> {
+    let mmap = open(path, kind)?;
+    decode(&mmap[HEADER_LEN..])
+}
+
+// save_with_metric saves an index along with the name of its metric.
+pub(crate) fn save_with_metric<T: Serialize, P: AsRef<Path>>(
+    metric: &str,
+    value: &T,
+    kind: IndexKind,
+    path: P,
+) -> Result<(), PersistError> {
+    save(&(metric, value), kind, path)
+}
+
+// load_with_metric loads an index saved with save_with_metric and checks
+// its metric name.
+pub(crate) fn load_with_metric<T, P, F>(
+    kind: IndexKind,
+    path: P,
+    metric: F,
+) -> Result<T, PersistError>
+where
+    T: DeserializeOwned,
+    P: AsRef<Path>,
+    F: Fn(&T) -> &str,
+{
+    let (found, value): (String, T) = load(kind, path)?;
+    let expected = metric(&value);
+    if found != expected {
+        return Err(PersistError::MetricMismatch {
+            expected: expected.to_owned(),
+            found,
+        });
+    }
+    Ok(value)
+}
+
+pub(crate) fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    let mut buffer = [0; 4];
+    buffer.copy_from_slice(&bytes[offset..offset + 4]);
+    u32::from_le_bytes(buffer)
+}
+
+pub(crate) fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    let mut buffer = [0; 8];
+    buffer.copy_from_slice(&bytes[offset..offset + 8]);
+    u64::from_le_bytes(buffer)
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::{IndexKind, PersistError};
+    use std::path::PathBuf;
+
+    // temp_path returns a file path unique to the test process.
+    pub(crate) fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("name_match_{}_{}.idx", std::process::id(), name))
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let path = temp_path("persist_roundtrip");
+        let value = vec!["JOHN".to_owned(), "DOE".to_owned()];
+        super::save(&value, IndexKind::SymSpell, &path).unwrap();
+        let loaded: Vec<String> = super::load(IndexKind::SymSpell, &path).unwrap();
+        assert_eq!(loaded, value);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_load_wrong_kind() {
+        let path = temp_path("persist_kind");
+        super::save(&1u64, IndexKind::SymSpell, &path).unwrap();
+        let loaded: Result<u64, PersistError> = super::load(IndexKind::BKTree, &path);
+        assert!(matches!(
+            loaded,
+            Err(PersistError::KindMismatch { found: 4, .. })
+        ));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_load_invalid_file() {
+        let path = temp_path("persist_magic");
+        std::fs::write(&path, b"not an index").unwrap();
+        let loaded: Result<u64, PersistError> = super::load(IndexKind::SymSpell, &path);
+        assert!(matches!(loaded, Err(PersistError::InvalidMagic)));
+
+        let mut bytes = super::MAGIC.to_vec();
+        bytes.extend_from_slice(&99u32.to_le_bytes());
+        bytes.extend_from_slice(&4u32.to_le_bytes());
+        std::fs::write(&path, bytes).unwrap();
+        let loaded: Result<u64, PersistError> = super::load(IndexKind::SymSpell, &path);
+        assert!(matches!(loaded, Err(PersistError::UnsupportedVersion(99))));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_load_corrupted_length() {
+        let path = temp_path("persist_length");
+        let mut bytes = super::MAGIC.to_vec();
+        bytes.extend_from_slice(&super::FORMAT_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&4u32.to_le_bytes());
+        bytes.extend_from_slice(&u64::MAX.to_le_bytes());
+        std::fs::write(&path, bytes).unwrap();
+        let loaded: Result<Vec<u64>, PersistError> = super::load(IndexKind::SymSpell, &path);
+        assert!(matches!(loaded, Err(PersistError::Encoding(_))));
+        std::fs::remove_file(path).unwrap();
+    }
+}
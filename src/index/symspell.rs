@@ -0,0 +1,442 @@
+use super::super::ensemble::{self, Ensemble, EnsembleResult};
+use super::super::levenshtein::compute;
+use super::super::prelude::*;
+use super::persist::{self, IndexKind, PersistError};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+// Highest edit distance supported by the symmetric delete dictionary.
+pub const MAX_EDIT_DISTANCE: usize = 3;
+
+// TokenSuggestion is a dictionary token close to a looked up token.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenSuggestion {
+    pub token: String,
+    pub distance: usize,
+}
+
+// deletes returns every string obtained by deleting up to max_distance
+// characters from token, token included.
+// Eg:
+//   ("ABC", 1) -> ["ABC", "BC", "AC", "AB"]
+fn deletes(token: &str, max_distance: usize) -> HashSet<String> {
+    let mut result = HashSet::new();
+    result.insert(token.to_owned());
+    let mut frontier = vec![token.to_owned()];
+    for _ in 0..max_distance {
+        let mut next = Vec::new();
+        for word in frontier.iter() {
+            let chars: Vec<char> = word.chars().collect();
+            for i in 0..chars.len() {
+                let deleted: String = chars[..i].iter().chain(chars[i + 1..].iter()).collect();
+                if result.insert(deleted.clone()) {
+                    next.push(deleted);
+                }
+            }
+        }
+        frontier = next;
+    }
+    result
+}
+
+// prefix returns the first prefix_length characters of token.
+fn prefix(token: &str, prefix_length: usize) -> String {
+    token.chars().take(prefix_length).collect()
+}
+
+// SymSpell is a symmetric delete dictionary over cleaned name tokens.
+// Every token is stored under all strings obtained by deleting up to
+// max_distance characters from its prefix. A lookup generates the deletes
+// of the query token and only verifies tokens stored under them,
+// instead of comparing the query against the whole dictionary.
+//   - max_distance: Highest Damerau-Levenshtein distance returned by lookups,
+//     between 1 and MAX_EDIT_DISTANCE.
+//   - prefix_length: Number of leading characters deletes are generated from.
+//     Shorter prefixes use less memory at the cost of more verifications.
+#[derive(Serialize, Deserialize)]
+pub struct SymSpell {
+    max_distance: usize,
+    prefix_length: usize,
+    tokens: Vec<String>,
+    token_ids: HashMap<String, usize>,
+    deletes: HashMap<String, Vec<usize>>,
+}
+
+impl SymSpell {
+    pub fn new(max_distance: usize, prefix_length: usize) -> SymSpell {
+        let max_distance = max_distance.clamp(1, MAX_EDIT_DISTANCE);
+        SymSpell {
+            max_distance,
+            prefix_length: prefix_length.max(max_distance + 1),
+            tokens: Vec::new(),
+            token_ids: HashMap::new(),
+            deletes: HashMap::new(),
+        }
+    }
+
+    pub fn max_distance(&self) -> usize {
+        self.max_distance
+    }
+
+    pub fn prefix_length(&self) -> usize {
+        self.prefix_length
+    }
+
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    // insert adds a cleaned token to the dictionary and returns its id.
+    // Inserting a known token returns its existing id.
+    pub fn insert(&mut self, token: &str) -> usize {
+        if let Some(id) = self.token_ids.get(token) {
+            return *id;
+        }
+        let id = self.tokens.len();
+        self.tokens.push(token.to_owned());
+        self.token_ids.insert(token.to_owned(), id);
+        for deleted in deletes(&prefix(token, self.prefix_length)[..], self.max_distance) {
+            self.deletes.entry(deleted).or_default().push(id);
+        }
+        id
+    }
+
+    // lookup returns dictionary tokens within max_distance of a cleaned
+    // token, closest first. max_distance is capped by the dictionary's.
+    pub fn lookup(&self, token: &str, max_distance: usize) -> Vec<TokenSuggestion> {
+        let max_distance = max_distance.min(self.max_distance);
+        let token_length = token.chars().count();
+        let mut verified: HashSet<usize> = HashSet::new();
+        let mut suggestions = Vec::new();
+
+        for deleted in deletes(&prefix(token, self.prefix_length)[..], max_distance) {
+            let ids = match self.deletes.get(&deleted) {
+                Some(ids) => ids,
+                None => continue,
+            };
+            for id in ids.iter() {
+                if !verified.insert(*id) {
+                    continue;
+                }
+                let candidate = &self.tokens[*id];
+                let length_difference =
+                    (candidate.chars().count() as i64 - token_length as i64).unsigned_abs();
+                if length_difference as usize > max_distance {
+                    continue;
+                }
+                let distance = compute::damerau_levenshtein_distance(token, &candidate[..]);
+                if distance <= max_distance {
+                    suggestions.push(TokenSuggestion {
+                        token: candidate.to_owned(),
+                        distance,
+                    });
+                }
+            }
+        }
+        suggestions.sort_by(|a, b| a.distance.cmp(&b.distance).then(a.token.cmp(&b.token)));
+        suggestions
+    }
+
+    // save writes the dictionary to a versioned binary file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), PersistError> {
+        persist::save(self, IndexKind::SymSpell, path)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<SymSpell, PersistError> {
+        persist::load(IndexKind::SymSpell, path)
+    }
+}
+
+// NameCandidate is a name sharing close tokens with a query.
+//   - matched_tokens: Number of query tokens with a close token in the name.
+//   - total_distance: Sum of the distances of the closest tokens.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NameCandidate {
+    pub id: usize,
+    pub name: String,
+    pub matched_tokens: usize,
+    pub total_distance: usize,
+}
+
+// SymSpellNameIndex finds names whose tokens are close to the tokens of
+// a query. Per token suggestions from SymSpell are combined into name
+// candidates, which can then be re-scored with an Ensemble.
+//   - min_matched_tokens: Minimum number of query tokens a name must match.
+#[derive(Serialize, Deserialize)]
+pub struct SymSpellNameIndex {
+    dictionary: SymSpell,
+    names: Vec<String>,
+    postings: Vec<Vec<usize>>,
+    pub min_matched_tokens: usize,
+}
+
+impl SymSpellNameIndex {
+    pub fn new(max_distance: usize, prefix_length: usize) -> SymSpellNameIndex {
+        SymSpellNameIndex {
+            dictionary: SymSpell::new(max_distance, prefix_length),
+            names: Vec::new(),
+            postings: Vec::new(),
+            min_matched_tokens: 1,
+        }
+    }
+
+    pub fn build<I, S>(max_distance: usize, prefix_length: usize, names: I) -> SymSpellNameIndex
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut index = SymSpellNameIndex::new(max_distance, prefix_length);
+        for name in names.into_iter() {
+            index.insert(name.as_ref());
+        }
+        index
+    }
+
+    // insert adds a name to the index and returns its id.
+    pub fn insert(&mut self, name: &str) -> usize {
+        let id = self.names.len();
+        self.names.push(name.to_owned());
+        let cleaned = self.clean(name);
+        let mut token_ids: Vec<usize> = cleaned
+            .split_whitespace()
+            .map(|token| self.dictionary.insert(token))
+            .collect();
+        token_ids.sort_unstable();
+        token_ids.dedup();
+        for token_id in token_ids.into_iter() {
+            if token_id == self.postings.len() {
+                self.postings.push(Vec::new());
+            }
+            self.postings[token_id].push(id);
+        }
+        id
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub fn get(&self, id: usize) -> Option<&str> {
+        self.names.get(id).map(|name| &name[..])
+    }
+
+    pub fn dictionary(&self) -> &SymSpell {
+        &self.dictionary
+    }
+
+    // candidates returns names matching at least min_matched_tokens tokens
+    // of the query, most matched tokens first, then smallest total distance.
+    pub fn candidates(&self, query: &str, max_distance: usize) -> Vec<NameCandidate> {
+        let cleaned = self.clean(query);
+        let mut query_tokens: Vec<&str> = cleaned.split_whitespace().collect();
+        query_tokens.sort_unstable();
+        query_tokens.dedup();
+
+        // (matched tokens, total distance) per name id
+        let mut matched: HashMap<usize, (usize, usize)> = HashMap::new();
+        for token in query_tokens.into_iter() {
+            // Closest distance of this query token per name id
+            let mut closest: HashMap<usize, usize> = HashMap::new();
+            for suggestion in self.dictionary.lookup(token, max_distance) {
+                let token_id = self.dictionary.token_ids[&suggestion.token];
+                for id in self.postings[token_id].iter() {
+                    let distance = closest.entry(*id).or_insert(suggestion.distance);
+                    *distance = (*distance).min(suggestion.distance);
+                }
+            }
+            for (id, distance) in closest.into_iter() {
+                let entry = matched.entry(id).or_insert((0, 0));
+                entry.0 += 1;
+                entry.1 += distance;
+            }
+        }
+
+        let mut candidates: Vec<NameCandidate> = matched
+            .into_iter()
+            .filter(|(_, (matched_tokens, _))| *matched_tokens >= self.min_matched_tokens)
+            .map(|(id, (matched_tokens, total_distance))| NameCandidate {
+                id,
+                name: self.names[id].to_owned(),
+                matched_tokens,
+                total_distance,
+            })
+            .collect();
+        candidates.sort_by(|a, b| {
+            b.matched_tokens
+                .cmp(&a.matched_tokens)
+                .then(a.total_distance.cmp(&b.total_distance))
+                .then(a.id.cmp(&b.id))
+        });
+        candidates
+    }
+
+    // search re-scores name candidates with the ensemble and returns the k
+    // best results scoring at least threshold, best first.
+    pub fn search(
+        &self,
+        ensemble: &Ensemble,
+        query: &str,
+        max_distance: usize,
+        k: usize,
+        threshold: f64,
+    ) -> Vec<EnsembleResult> {
+        let candidates = self.candidates(query, max_distance);
+        let results = candidates.par_iter().filter_map(|candidate| {
+            ensemble.get_ensemble_result_above(query, &candidate.name[..], threshold)
+        });
+        ensemble::top_k(results, k)
+    }
+
+    // save writes the index to a versioned binary file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), PersistError> {
+        persist::save(self, IndexKind::SymSpellNameIndex, path)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<SymSpellNameIndex, PersistError> {
+        persist::load(IndexKind::SymSpellNameIndex, path)
+    }
+}
+
+impl Clean for SymSpellNameIndex {}
+
+#[cfg(test)]
+mod test {
+    use super::super::super::ensemble::Ensemble;
+    use super::super::super::jaro::compare::JaroWinklerMatcher;
+    use super::super::super::prelude::*;
+    use super::super::persist::test::temp_path;
+    use super::{SymSpell, SymSpellNameIndex};
+
+    #[test]
+    fn test_deletes() {
+        let deleted = super::deletes("ABC", 1);
+        assert_eq!(deleted.len(), 4);
+        assert!(deleted.contains("AC"));
+    }
+
+    #[test]
+    fn test_deletes_distance_two() {
+        let deleted = super::deletes("ABC", 2);
+        assert_eq!(deleted.len(), 7);
+        assert!(deleted.contains("C"));
+    }
+
+    #[test]
+    fn test_new_caps_max_distance() {
+        let dictionary = SymSpell::new(5, 1);
+        assert_eq!(dictionary.max_distance(), 3);
+        assert_eq!(dictionary.prefix_length(), 4);
+    }
+
+    #[test]
+    fn test_insert_duplicate_token() {
+        let mut dictionary = SymSpell::new(2, 7);
+        assert_eq!(dictionary.insert("JOHN"), 0);
+        assert_eq!(dictionary.insert("JOHN"), 0);
+        assert_eq!(dictionary.len(), 1);
+    }
+
+    #[test]
+    fn test_lookup() {
+        let mut dictionary = SymSpell::new(2, 7);
+        for token in ["JOHN", "JOAN", "JON", "JONATHAN", "MARY"].iter() {
+            dictionary.insert(token);
+        }
+        let suggestions = dictionary.lookup("JHON", 1);
+        let tokens: Vec<&str> = suggestions.iter().map(|s| &s.token[..]).collect();
+        assert_eq!(tokens, vec!["JOHN", "JON"]);
+        assert_eq!(suggestions[0].distance, 1);
+    }
+
+    #[test]
+    fn test_lookup_matches_brute_force() {
+        use super::super::super::levenshtein::compute::damerau_levenshtein_distance;
+        let tokens = [
+            "CHRISTOPHER",
+            "CHRISTOPH",
+            "KRISTOFER",
+            "CHRISTINE",
+            "CHRIS",
+            "KRIS",
+        ];
+        let mut dictionary = SymSpell::new(3, 5);
+        for token in tokens.iter() {
+            dictionary.insert(token);
+        }
+        for max_distance in 1..=3 {
+            let suggestions = dictionary.lookup("CRISTOPHER", max_distance);
+            let expected = tokens
+                .iter()
+                .filter(|token| damerau_levenshtein_distance("CRISTOPHER", token) <= max_distance)
+                .count();
+            assert_eq!(suggestions.len(), expected);
+        }
+    }
+
+    fn build_index() -> SymSpellNameIndex {
+        SymSpellNameIndex::build(
+            2,
+            7,
+            vec![
+                "John Doe",
+                "Jon Dow",
+                "Mary Smith",
+                "John Smith",
+                "Doe John",
+            ],
+        )
+    }
+
+    #[test]
+    fn test_candidates() {
+        let index = build_index();
+        let candidates = index.candidates("Jhon Doe", 1);
+        let ids: Vec<usize> = candidates.iter().map(|c| c.id).collect();
+        assert_eq!(ids, vec![0, 4, 1, 3]);
+        assert_eq!(candidates[0].matched_tokens, 2);
+        assert_eq!(candidates[0].total_distance, 1);
+    }
+
+    #[test]
+    fn test_candidates_min_matched_tokens() {
+        let mut index = build_index();
+        index.min_matched_tokens = 2;
+        let candidates = index.candidates("Jhon Doe", 1);
+        let ids: Vec<usize> = candidates.iter().map(|c| c.id).collect();
+        assert_eq!(ids, vec![0, 4, 1]);
+    }
+
+    #[test]
+    fn test_search() {
+        let index = build_index();
+        let matchers: Vec<Box<dyn Matcher>> = vec![Box::new(JaroWinklerMatcher::default())];
+        let ensemble = Ensemble::new(matchers);
+        let results = index.search(&ensemble, "Jhon Doe", 1, 2, 0.0);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].name2, "John Doe");
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let path = temp_path("symspell");
+        let index = build_index();
+        index.save(&path).unwrap();
+        let loaded = SymSpellNameIndex::load(&path).unwrap();
+        assert_eq!(loaded.len(), index.len());
+        assert_eq!(
+            loaded.candidates("Jhon Do", 1),
+            index.candidates("Jhon Do", 1)
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,465 @@
+use super::super::prelude::*;
+use super::metric::{self, Metric, NearestNeighbours, Neighbour};
+use super::persist::{self, IndexKind, PersistError};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+// Maximum number of names held by a leaf when the tree is bulk loaded.
+// Leaves are split again once insertions double their size.
+const LEAF_CAPACITY: usize = 8;
+
+// VPNode either holds names directly (Leaf) or splits them around a
+// vantage point: names within radius of the vantage point go inside,
+// others go outside. Names that are all at the same distance from the first
+// one can not be split and are kept in an Equidistant leaf, whatever its size.
+#[derive(Serialize, Deserialize)]
+enum VPNode {
+    Leaf(Vec<usize>),
+    Equidistant {
+        ids: Vec<usize>,
+        distance: f64,
+    },
+    Internal {
+        vantage: usize,
+        radius: f64,
+        inside: Box<VPNode>,
+        outside: Box<VPNode>,
+    },
+}
+
+// build_node splits ids around the median distance to the first id.
+// When the median is also the largest distance, names at that distance go
+// outside so that both sides hold names.
+fn build_node<M: Metric>(metric: &M, keys: &[String], mut ids: Vec<usize>) -> VPNode {
+    if ids.len() <= LEAF_CAPACITY {
+        return VPNode::Leaf(ids);
+    }
+    let vantage = ids.remove(0);
+    let mut distances: Vec<(f64, usize)> = ids
+        .into_iter()
+        .map(|id| (metric.distance(&keys[vantage][..], &keys[id][..]), id))
+        .collect();
+    distances.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let median = distances[distances.len() / 2].0;
+    let largest = distances[distances.len() - 1].0;
+    let below_median = distances
+        .iter()
+        .rev()
+        .map(|(distance, _)| *distance)
+        .find(|distance| *distance < median);
+    let radius = match below_median {
+        _ if median < largest => median,
+        Some(distance) => distance,
+        None => {
+            let mut ids = vec![vantage];
+            ids.extend(distances.into_iter().map(|(_, id)| id));
+            return VPNode::Equidistant {
+                ids,
+                distance: largest,
+            };
+        }
+    };
+    let mut inside: Vec<usize> = Vec::new();
+    let mut outside: Vec<usize> = Vec::new();
+    for (distance, id) in distances.into_iter() {
+        if distance <= radius {
+            inside.push(id);
+        } else {
+            outside.push(id);
+        }
+    }
+    VPNode::Internal {
+        vantage,
+        radius,
+        inside: Box::new(build_node(metric, keys, inside)),
+        outside: Box::new(build_node(metric, keys, outside)),
+    }
+}
+
+// VPTree (vantage-point tree) indexes names by a real valued metric distance,
+// eg normalized Levenshtein. Radius and nearest neighbour queries skip
+// whole subtrees thanks to the triangle inequality.
+// Names are cleaned by the metric before insertion and lookup.
+#[derive(Serialize, Deserialize)]
+pub struct VPTree<M: Metric> {
+    metric: M,
+    names: Vec<String>,
+    keys: Vec<String>,
+    root: VPNode,
+}
+
+impl<M: Metric> VPTree<M> {
+    pub fn new(metric: M) -> VPTree<M> {
+        VPTree {
+            metric,
+            names: Vec::new(),
+            keys: Vec::new(),
+            root: VPNode::Leaf(Vec::new()),
+        }
+    }
+
+    // build bulk loads a balanced tree over names,
+    // splitting every node around the median distance.
+    pub fn build<I, S>(metric: M, names: I) -> VPTree<M>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut tree = VPTree::new(metric);
+        for name in names.into_iter() {
+            tree.keys.push(tree.metric.clean(name.as_ref()));
+            tree.names.push(name.as_ref().to_owned());
+        }
+        let ids: Vec<usize> = (0..tree.names.len()).collect();
+        tree.root = build_node(&tree.metric, &tree.keys, ids);
+        tree
+    }
+
+    // insert adds a name to the tree and returns its id.
+    // The name is appended to the leaf it falls into,
+    // leaves are split once they hold twice LEAF_CAPACITY names.
+    pub fn insert(&mut self, name: &str) -> usize {
+        let id = self.names.len();
+        self.names.push(name.to_owned());
+        self.keys.push(self.metric.clean(name));
+
+        let VPTree {
+            metric, keys, root, ..
+        } = self;
+        let mut node = root;
+        while let VPNode::Internal {
+            vantage,
+            radius,
+            inside,
+            outside,
+        } = node
+        {
+            let distance = metric.distance(&keys[*vantage][..], &keys[id][..]);
+            node = if distance <= *radius { inside } else { outside };
+        }
+        match node {
+            VPNode::Leaf(ids) => {
+                ids.push(id);
+                if ids.len() > 2 * LEAF_CAPACITY {
+                    let ids = std::mem::take(ids);
+                    *node = build_node(metric, keys, ids);
+                }
+            }
+            VPNode::Equidistant { ids, distance } => {
+                // The leaf can only be split once a name at another distance
+                // joins it.
+                let to_first = metric.distance(&keys[ids[0]][..], &keys[id][..]);
+                ids.push(id);
+                if to_first != *distance {
+                    let ids = std::mem::take(ids);
+                    *node = build_node(metric, keys, ids);
+                }
+            }
+            VPNode::Internal { .. } => unreachable!(),
+        }
+        id
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub fn get(&self, id: usize) -> Option<&str> {
+        self.names.get(id).map(|name| &name[..])
+    }
+
+    fn neighbour(&self, id: usize, distance: f64) -> Neighbour {
+        Neighbour {
+            id,
+            name: self.names[id].to_owned(),
+            distance,
+        }
+    }
+
+    // find returns every name within max_distance of the query,
+    // closest first.
+    pub fn find(&self, query: &str, max_distance: f64) -> Vec<Neighbour> {
+        let query = self.metric.clean(query);
+        let mut found = Vec::new();
+        let mut stack = vec![&self.root];
+        while let Some(node) = stack.pop() {
+            match node {
+                VPNode::Leaf(ids) => {
+                    for id in ids.iter() {
+                        let distance = self.metric.distance(&query[..], &self.keys[*id][..]);
+                        if distance <= max_distance {
+                            found.push(self.neighbour(*id, distance));
+                        }
+                    }
+                }
+                VPNode::Equidistant { ids, distance } => {
+                    let to_first = self.metric.distance(&query[..], &self.keys[ids[0]][..]);
+                    if to_first <= max_distance {
+                        found.push(self.neighbour(ids[0], to_first));
+                    }
+                    // Other names are at least |to_first - distance| away.
+                    if (to_first - distance).abs() > max_distance {
+                        continue;
+                    }
+                    for id in ids[1..].iter() {
+                        let distance = self.metric.distance(&query[..], &self.keys[*id][..]);
+                        if distance <= max_distance {
+                            found.push(self.neighbour(*id, distance));
+                        }
+                    }
+                }
+                VPNode::Internal {
+                    vantage,
+                    radius,
+                    inside,
+                    outside,
+                } => {
+                    let distance = self.metric.distance(&query[..], &self.keys[*vantage][..]);
+                    if distance <= max_distance {
+                        found.push(self.neighbour(*vantage, distance));
+                    }
+                    if distance - max_distance <= *radius {
+                        stack.push(inside);
+                    }
+                    if distance + max_distance > *radius {
+                        stack.push(outside);
+                    }
+                }
+            }
+        }
+        metric::sort_by_distance(&mut found);
+        found
+    }
+
+    // nearest returns the k names closest to the query, closest first.
+    pub fn nearest(&self, query: &str, k: usize) -> Vec<Neighbour> {
+        let query = self.metric.clean(query);
+        let mut nearest = NearestNeighbours::new(k);
+        if k > 0 {
+            self.search_nearest(&self.root, &query[..], &mut nearest);
+        }
+        nearest.into_vec()
+    }
+
+    // search_nearest visits the side of the vantage point holding the query
+    // first, so that the search radius shrinks as early as possible.
+    fn search_nearest(&self, node: &VPNode, query: &str, nearest: &mut NearestNeighbours) {
+        match node {
+            VPNode::Leaf(ids) => {
+                for id in ids.iter() {
+                    let distance = self.metric.distance(query, &self.keys[*id][..]);
+                    nearest.push(self.neighbour(*id, distance));
+                }
+            }
+            VPNode::Equidistant { ids, distance } => {
+                let to_first = self.metric.distance(query, &self.keys[ids[0]][..]);
+                nearest.push(self.neighbour(ids[0], to_first));
+                if (to_first - distance).abs() > nearest.radius() {
+                    return;
+                }
+                for id in ids[1..].iter() {
+                    let distance = self.metric.distance(query, &self.keys[*id][..]);
+                    nearest.push(self.neighbour(*id, distance));
+                }
+            }
+            VPNode::Internal {
+                vantage,
+                radius,
+                inside,
+                outside,
+            } => {
+                let distance = self.metric.distance(query, &self.keys[*vantage][..]);
+                nearest.push(self.neighbour(*vantage, distance));
+                if distance <= *radius {
+                    self.search_nearest(inside, query, nearest);
+                    if distance + nearest.radius() > *radius {
+                        self.search_nearest(outside, query, nearest);
+                    }
+                } else {
+                    self.search_nearest(outside, query, nearest);
+                    if distance - nearest.radius() <= *radius {
+                        self.search_nearest(inside, query, nearest);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<M: Metric + Named + Serialize + DeserializeOwned> VPTree<M> {
+    // save writes the tree to a versioned binary file, along with the name
+    // of its metric.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), PersistError> {
+        persist::save_with_metric(self.metric.get_name(), self, IndexKind::VPTree, path)
+    }
+
+    // load reads a tree saved with the same metric.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<VPTree<M>, PersistError> {
+        persist::load_with_metric(IndexKind::VPTree, path, |tree: &VPTree<M>| {
+            tree.metric.get_name()
+        })
+    }
+}
+
+impl<M: Metric + Default> Default for VPTree<M> {
+    fn default() -> VPTree<M> {
+        VPTree::new(M::default())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::super::prelude::Clean;
+    use super::super::metric::{Metric, NormalizedLevenshtein};
+    use super::super::persist::test::temp_path;
+    use super::{VPNode, VPTree};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // Counting is NormalizedLevenshtein counting distance computations.
+    #[derive(Default)]
+    struct Counting {
+        calls: AtomicUsize,
+    }
+
+    impl Clean for Counting {}
+
+    impl Metric for Counting {
+        fn distance(&self, s1: &str, s2: &str) -> f64 {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            NormalizedLevenshtein.distance(s1, s2)
+        }
+    }
+
+    fn names() -> Vec<String> {
+        let first_names = ["John", "Jon", "Joan", "Jane", "Mary", "Marie", "Peter"];
+        let last_names = ["Doe", "Smith", "Parker", "Smyth", "Dough"];
+        let mut names = Vec::new();
+        for first_name in first_names.iter() {
+            for last_name in last_names.iter() {
+                names.push(format!("{} {}", first_name, last_name));
+            }
+        }
+        names
+    }
+
+    fn brute_force(query: &str, max_distance: f64) -> usize {
+        let metric = NormalizedLevenshtein;
+        names()
+            .iter()
+            .filter(|name| metric.distance(&name.to_uppercase()[..], query) <= max_distance)
+            .count()
+    }
+
+    #[test]
+    fn test_find_matches_brute_force() {
+        let tree = VPTree::build(NormalizedLevenshtein, names());
+        for max_distance in [0.0, 0.1, 0.2, 0.3, 0.5] {
+            let found = tree.find("Jon Smith", max_distance);
+            assert_eq!(found.len(), brute_force("JON SMITH", max_distance));
+        }
+    }
+
+    #[test]
+    fn test_find_exact() {
+        let tree = VPTree::build(NormalizedLevenshtein, names());
+        let found = tree.find("john doe", 0.0);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].name, "John Doe");
+    }
+
+    #[test]
+    fn test_insert_matches_build() {
+        let mut tree = VPTree::new(NormalizedLevenshtein);
+        for name in names().iter() {
+            tree.insert(name);
+        }
+        assert_eq!(tree.len(), names().len());
+        for max_distance in [0.0, 0.2, 0.4] {
+            let found = tree.find("Mary Smyth", max_distance);
+            assert_eq!(found.len(), brute_force("MARY SMYTH", max_distance));
+        }
+    }
+
+    #[test]
+    fn test_nearest() {
+        let tree = VPTree::build(NormalizedLevenshtein, names());
+        let nearest = tree.nearest("Jon Smyth", 3);
+        assert_eq!(nearest.len(), 3);
+        assert_eq!(nearest[0].name, "Jon Smyth");
+        assert!(nearest[1].distance <= nearest[2].distance);
+    }
+
+    #[test]
+    fn test_nearest_matches_brute_force() {
+        let metric = NormalizedLevenshtein;
+        let tree = VPTree::build(NormalizedLevenshtein, names());
+        let nearest = tree.nearest("Joanne Parkes", 5);
+        let mut distances: Vec<f64> = names()
+            .iter()
+            .map(|name| metric.distance(&name.to_uppercase()[..], "JOANNE PARKES"))
+            .collect();
+        distances.sort_by(|a, b| a.total_cmp(b));
+        let found: Vec<f64> = nearest.iter().map(|n| n.distance).collect();
+        assert_eq!(found, distances[..5].to_vec());
+    }
+
+    #[test]
+    fn test_empty_tree() {
+        let tree: VPTree<NormalizedLevenshtein> = VPTree::default();
+        assert!(tree.is_empty());
+        assert!(tree.find("John", 0.5).is_empty());
+        assert!(tree.nearest("John", 2).is_empty());
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let path = temp_path("vptree");
+        let tree = VPTree::build(NormalizedLevenshtein, names());
+        tree.save(&path).unwrap();
+        let loaded: VPTree<NormalizedLevenshtein> = VPTree::load(&path).unwrap();
+        assert_eq!(loaded.len(), tree.len());
+        assert_eq!(loaded.find("Jon Smith", 0.3), tree.find("Jon Smith", 0.3));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_split_at_largest_distance() {
+        // Most names share nothing with the first one, the median distance
+        // is also the largest.
+        let mut names = vec!["AAAA", "AAAB", "AABB", "ABBB"];
+        names.extend(["CCCC", "DDDD", "EEEE", "FFFF", "GGGG", "HHHH", "IIII"]);
+        let tree = VPTree::build(NormalizedLevenshtein, names.clone());
+        assert!(matches!(tree.root, VPNode::Internal { .. }));
+        for name in names.iter() {
+            assert_eq!(tree.find(name, 0.0)[0].name, *name);
+        }
+    }
+
+    #[test]
+    fn test_duplicates() {
+        let mut tree = VPTree::build(Counting::default(), vec!["John Doe"; 100]);
+        assert!(matches!(tree.root, VPNode::Equidistant { .. }));
+        for name in names().iter() {
+            tree.insert(name);
+        }
+        tree.metric.calls.store(0, Ordering::Relaxed);
+        for _ in 0..1000 {
+            tree.insert("John Doe");
+        }
+        // Every insertion walks down the tree instead of splitting a leaf of
+        // duplicates again.
+        assert!(tree.metric.calls.load(Ordering::Relaxed) < 20 * 1000);
+        assert_eq!(tree.find("John Doe", 0.0).len(), 1101);
+        assert_eq!(tree.nearest("Jon Doe", 3)[2].name, "John Doe");
+
+        tree.metric.calls.store(0, Ordering::Relaxed);
+        assert_eq!(tree.find("Peter Parker", 0.0).len(), 1);
+        assert!(tree.metric.calls.load(Ordering::Relaxed) < 100);
+    }
+}
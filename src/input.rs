@@ -0,0 +1,46 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+// read_names reads every name of a file holding one name per line, for
+// inputs small enough to be held in memory. Blank lines are skipped.
+// Errors are prefixed with the path of the file.
+pub fn read_names<P: AsRef<Path>>(path: P) -> io::Result<Vec<String>> {
+    let path = path.as_ref();
+    File::open(path)
+        .and_then(|file| {
+            BufReader::new(file)
+                .lines()
+                .filter(|line| !matches!(line, Ok(name) if name.trim().is_empty()))
+                .collect()
+        })
+        .map_err(|err| io::Error::new(err.kind(), format!("{}: {}", path.display(), err)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::index::persist::test::temp_path;
+    use super::read_names;
+    use std::fs;
+    use std::io::ErrorKind;
+
+    #[test]
+    fn test_read_names() {
+        let path = temp_path("input_names");
+        fs::write(&path, "Jon Doe\n\n  \nMary Smith\n").unwrap();
+        assert_eq!(read_names(&path).unwrap(), vec!["Jon Doe", "Mary Smith"]);
+        fs::write(&path, [b'J', 0xff, b'\n']).unwrap();
+        let err = read_names(&path).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert!(err.to_string().starts_with(&path.display().to_string()));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_read_names_missing_file() {
+        let path = temp_path("input_missing");
+        let err = read_names(&path).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+        assert!(err.to_string().starts_with(&path.display().to_string()));
+    }
+}
@@ -1,11 +1,14 @@
 use super::super::prelude::*;
 use super::super::shared::compute;
+use super::super::shared::tokenize;
+use super::super::spec::with_tokenization;
 use std::collections::HashSet;
 use std::sync::RwLock;
 
 pub struct JaccardMatcher {
     name: String,
     weight: RwLock<f64>,
+    tokenization: Tokenization,
 }
 
 // JaccardMatcher calculates Jaccard Index or (Overlapping Coefficient)
@@ -20,6 +23,7 @@ impl JaccardMatcher {
         JaccardMatcher {
             name: "Jaccard".to_owned(),
             weight: locked_weight,
+            tokenization: Tokenization::Whitespace,
         }
     }
 
@@ -28,10 +32,11 @@ impl JaccardMatcher {
         JaccardMatcher {
             name: "Jaccard".to_owned(),
             weight: weight,
+            tokenization: Tokenization::Whitespace,
         }
     }
 
-    fn as_tokenized_set(&self, string: &str) -> HashSet<String> {
+    pub fn as_tokenized_set(&self, string: &str) -> HashSet<String> {
         let mut token_set = HashSet::new();
         for token in string.split_whitespace() {
             let token = token.to_owned();
@@ -39,6 +44,21 @@ impl JaccardMatcher {
         }
         token_set
     }
+
+    fn as_token_set(&self, tokens: &[String]) -> HashSet<String> {
+        tokens.iter().cloned().collect()
+    }
+
+    // from_spec builds the matcher described by spec, tokenization is
+    // Whitespace unless given.
+    pub fn from_spec(spec: &MatcherSpec) -> Result<JaccardMatcher, SpecError> {
+        spec.check_params(&["tokenization"])?;
+        let mut matcher = JaccardMatcher::new(spec.weight);
+        if let Some(tokenization) = spec.tokenization_param("tokenization")? {
+            matcher.set_tokenization(tokenization);
+        }
+        Ok(matcher)
+    }
 }
 
 impl Named for JaccardMatcher {
@@ -49,6 +69,16 @@ impl Named for JaccardMatcher {
 
 impl Clean for JaccardMatcher {}
 
+impl Tokenized for JaccardMatcher {
+    fn get_tokenization(&self) -> Tokenization {
+        self.tokenization
+    }
+
+    fn set_tokenization(&mut self, tokenization: Tokenization) {
+        self.tokenization = tokenization;
+    }
+}
+
 impl Weighted for JaccardMatcher {
     fn get_weight(&self) -> f64 {
         let weight_ptr = self.weight.read().unwrap();
@@ -61,14 +91,66 @@ impl Weighted for JaccardMatcher {
     }
 }
 
+impl JaccardMatcher {
+    fn segmented_score(&self, cleaned_s1: &str, cleaned_s2: &str) -> f64 {
+        let tokens_1 = tokenize::tokenize(cleaned_s1);
+        let tokens_2 = tokenize::tokenize(cleaned_s2);
+        let (score, _, _) = tokenize::best_segmentation(&tokens_1, &tokens_2, |t1, t2| {
+            compute::jaccard_index(&self.as_token_set(t1), &self.as_token_set(t2))
+        });
+        score
+    }
+}
+
 impl Matcher for JaccardMatcher {
     fn get_score(&self, s1: &str, s2: &str) -> f64 {
         let cleaned_s1 = self.clean(s1);
-        let tokenized_s1 = self.as_tokenized_set(&cleaned_s1[..]);
         let cleaned_s2 = self.clean(s2);
+        if self.tokenization == Tokenization::Segmented {
+            return self.segmented_score(&cleaned_s1[..], &cleaned_s2[..]);
+        }
+        let tokenized_s1 = self.as_tokenized_set(&cleaned_s1[..]);
         let tokenized_s2 = self.as_tokenized_set(&cleaned_s2[..]);
         compute::jaccard_index(&tokenized_s1, &tokenized_s2)
     }
+
+    fn get_prepared_score(&self, p1: &PreparedName, p2: &PreparedName) -> f64 {
+        if self.tokenization == Tokenization::Segmented {
+            return self.segmented_score(p1.cleaned(), p2.cleaned());
+        }
+        compute::jaccard_index(p1.tokens(), p2.tokens())
+    }
+
+    // Jaccard Index can not exceed the ratio between the smaller and
+    // the larger token set. Segmentation changes token counts, so no
+    // bound is given in Segmented mode.
+    fn get_upper_bound(&self, s1: &str, s2: &str) -> f64 {
+        if self.tokenization == Tokenization::Segmented {
+            return 1.0;
+        }
+        let count_1 = self.as_tokenized_set(&self.clean(s1)[..]).len();
+        let count_2 = self.as_tokenized_set(&self.clean(s2)[..]).len();
+        token_count_bound(count_1, count_2)
+    }
+
+    fn get_prepared_upper_bound(&self, p1: &PreparedName, p2: &PreparedName) -> f64 {
+        if self.tokenization == Tokenization::Segmented {
+            return 1.0;
+        }
+        token_count_bound(p1.tokens().len(), p2.tokens().len())
+    }
+
+    fn get_spec(&self) -> MatcherSpec {
+        let spec = MatcherSpec::new(self.get_name()).with_weight(self.get_weight());
+        with_tokenization(spec, self.tokenization)
+    }
+}
+
+fn token_count_bound(count_1: usize, count_2: usize) -> f64 {
+    if count_1 == 0 || count_2 == 0 {
+        return 0.0;
+    }
+    count_1.min(count_2) as f64 / count_1.max(count_2) as f64
 }
 
 #[cfg(test)]
@@ -105,4 +187,46 @@ mod test {
         let name2 = "JOHN DOE";
         assert_eq!(matcher.get_weighted_score(name1, name2), 0.5);
     }
+
+    #[test]
+    fn test_concatenated_name_no_match() {
+        let matcher = super::JaccardMatcher::default();
+        let name1 = "Mary Ann Doe";
+        let name2 = "Maryann Doe";
+        assert!(matcher.get_score(name1, name2) < 0.5);
+    }
+
+    #[test]
+    fn test_segmented_concatenated_name() {
+        let mut matcher = super::JaccardMatcher::default();
+        matcher.set_tokenization(Tokenization::Segmented);
+        let name1 = "Mary Ann Doe";
+        let name2 = "Maryann Doe";
+        assert_eq!(matcher.get_score(name1, name2), 1.0);
+    }
+
+    #[test]
+    fn test_segmented_split_name() {
+        let mut matcher = super::JaccardMatcher::default();
+        matcher.set_tokenization(Tokenization::Segmented);
+        let name1 = "McDonald";
+        let name2 = "Mc Donald";
+        assert_eq!(matcher.get_score(name1, name2), 1.0);
+    }
+
+    #[test]
+    fn test_upper_bound() {
+        let matcher = super::JaccardMatcher::default();
+        let name1 = "John Doe";
+        let name2 = "John Adam Peter Doe";
+        assert_eq!(matcher.get_upper_bound(name1, name2), 0.5);
+        assert!(matcher.get_score(name1, name2) <= 0.5);
+    }
+
+    #[test]
+    fn test_upper_bound_segmented() {
+        let mut matcher = super::JaccardMatcher::default();
+        matcher.set_tokenization(Tokenization::Segmented);
+        assert_eq!(matcher.get_upper_bound("Mary Ann", "Maryann"), 1.0);
+    }
 }
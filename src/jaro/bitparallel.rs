@@ -0,0 +1,228 @@
+// Bit-parallel kernels counting Jaro matches and transpositions.
+// Characters of s2 are turned into bit masks, one bit per position, so that
+// the matching window of a character of s1 is searched in a few word
+// operations instead of a nested scan. Matches are identical to the scalar
+// implementation: every character of s1 takes the first unmatched equal
+// character of s2 within the window.
+
+const WORD_BITS: usize = 64;
+
+// low_bits returns a mask of the n lowest bits.
+fn low_bits(n: usize) -> u64 {
+    if n >= WORD_BITS {
+        !0
+    } else {
+        (1 << n) - 1
+    }
+}
+
+// window returns the positions of s2 within max_matching_dist of position i,
+// as a half open range.
+fn window(i: usize, max_matching_dist: i32, length_2: usize) -> (usize, usize) {
+    let start = (i as i64 - max_matching_dist as i64).max(0) as usize;
+    let end = (i as i64 + max_matching_dist as i64 + 1).clamp(0, length_2 as i64) as usize;
+    (start, end)
+}
+
+// match_counts returns the number of matches and transpositions of Jaro
+// between s1 and s2. Strings of at most 64 bytes are handled without
+// allocation, longer strings use blocks of 64 positions.
+pub fn match_counts(s1: &[u8], s2: &[u8], max_matching_dist: i32) -> (usize, usize) {
+    if s1.len() <= WORD_BITS && s2.len() <= WORD_BITS {
+        let mut pattern = [0u64; 256];
+        for (j, byte) in s2.iter().enumerate() {
+            pattern[*byte as usize] |= 1 << j;
+        }
+        match_counts_64(&pattern, s1, s2, max_matching_dist)
+    } else {
+        let pattern = JaroPattern::new(s2);
+        match_counts_blocked(&pattern.masks, s1, s2, max_matching_dist)
+    }
+}
+
+// JaroPattern holds the position masks of a string, so that it can be
+// compared against many strings without building them again.
+pub(crate) struct JaroPattern {
+    bytes: Vec<u8>,
+    // Masks of block b are stored at [b * 256, (b + 1) * 256).
+    masks: Vec<u64>,
+}
+
+impl JaroPattern {
+    pub(crate) fn new(bytes: &[u8]) -> JaroPattern {
+        let blocks = bytes.len().div_ceil(WORD_BITS).max(1);
+        let mut masks = vec![0u64; blocks * 256];
+        for (j, byte) in bytes.iter().enumerate() {
+            masks[(j / WORD_BITS) * 256 + *byte as usize] |= 1 << (j % WORD_BITS);
+        }
+        JaroPattern {
+            bytes: bytes.to_owned(),
+            masks,
+        }
+    }
+
+    // match_counts returns the number of matches and transpositions of Jaro
+    // between s1 and the pattern, the pattern taking the place of s2.
+    pub(crate) fn match_counts(&self, s1: &[u8], max_matching_dist: i32) -> (usize, usize) {
+        if s1.len() <= WORD_BITS && self.bytes.len() <= WORD_BITS {
+            match_counts_64(&self.masks, s1, &self.bytes, max_matching_dist)
+        } else {
+            match_counts_blocked(&self.masks, s1, &self.bytes, max_matching_dist)
+        }
+    }
+}
+
+// match_counts_64 counts matches between strings of at most 64 bytes,
+// pattern holding the masks of s2.
+fn match_counts_64(
+    pattern: &[u64],
+    s1: &[u8],
+    s2: &[u8],
+    max_matching_dist: i32,
+) -> (usize, usize) {
+    let mut flags_1: u64 = 0;
+    let mut flags_2: u64 = 0;
+    for (i, byte) in s1.iter().enumerate() {
+        let (start, end) = window(i, max_matching_dist, s2.len());
+        if start >= end {
+            continue;
+        }
+        let window_mask = low_bits(end) & !low_bits(start);
+        let candidates = pattern[*byte as usize] & window_mask & !flags_2;
+        if candidates != 0 {
+            // Lowest set bit is the first unmatched character in the window.
+            flags_2 |= candidates & candidates.wrapping_neg();
+            flags_1 |= 1 << i;
+        }
+    }
+
+    let matches = flags_1.count_ones() as usize;
+    let mut transpositions = 0;
+    while flags_1 != 0 {
+        let i = flags_1.trailing_zeros() as usize;
+        let j = flags_2.trailing_zeros() as usize;
+        if s1[i] != s2[j] {
+            transpositions += 1;
+        }
+        flags_1 &= flags_1 - 1;
+        flags_2 &= flags_2 - 1;
+    }
+    (matches, transpositions)
+}
+
+// match_counts_blocked counts matches between strings of any length,
+// pattern holding the masks of s2 by block of 64 positions.
+fn match_counts_blocked(
+    pattern: &[u64],
+    s1: &[u8],
+    s2: &[u8],
+    max_matching_dist: i32,
+) -> (usize, usize) {
+    let blocks_1 = s1.len().div_ceil(WORD_BITS);
+    let blocks_2 = s2.len().div_ceil(WORD_BITS);
+
+    let mut flags_1 = vec![0u64; blocks_1];
+    let mut flags_2 = vec![0u64; blocks_2];
+    for (i, byte) in s1.iter().enumerate() {
+        let (start, end) = window(i, max_matching_dist, s2.len());
+        if start >= end {
+            continue;
+        }
+        for block in start / WORD_BITS..=(end - 1) / WORD_BITS {
+            let offset = block * WORD_BITS;
+            let block_start = start.max(offset) - offset;
+            let block_end = end.min(offset + WORD_BITS) - offset;
+            let window_mask = low_bits(block_end) & !low_bits(block_start);
+            let candidates = pattern[block * 256 + *byte as usize] & window_mask & !flags_2[block];
+            if candidates != 0 {
+                flags_2[block] |= candidates & candidates.wrapping_neg();
+                flags_1[i / WORD_BITS] |= 1 << (i % WORD_BITS);
+                break;
+            }
+        }
+    }
+
+    let matches = flags_1
+        .iter()
+        .map(|flags| flags.count_ones() as usize)
+        .sum();
+    let mut transpositions = 0;
+    let mut block_2 = 0;
+    for (block_1, flags) in flags_1.iter().enumerate() {
+        let mut flags = *flags;
+        while flags != 0 {
+            while flags_2[block_2] == 0 {
+                block_2 += 1;
+            }
+            let i = block_1 * WORD_BITS + flags.trailing_zeros() as usize;
+            let j = block_2 * WORD_BITS + flags_2[block_2].trailing_zeros() as usize;
+            if s1[i] != s2[j] {
+                transpositions += 1;
+            }
+            flags &= flags - 1;
+            flags_2[block_2] &= flags_2[block_2] - 1;
+        }
+    }
+    (matches, transpositions)
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::super::shared::compute::test;
+    use super::super::compute;
+
+    fn random_names(count: usize, max_length: usize) -> Vec<String> {
+        test::random_names(42, &['A', 'B', 'C', 'D', 'E', 'F'], count, max_length)
+    }
+
+    #[test]
+    fn test_match_counts() {
+        // "MARTHA" vs "MARHTA": 6 matches, 2 characters out of order.
+        let max_matching_dist = 2;
+        assert_eq!(
+            super::match_counts(b"MARTHA", b"MARHTA", max_matching_dist),
+            (6, 2)
+        );
+    }
+
+    #[test]
+    fn test_matches_scalar_short_names() {
+        let names = random_names(60, 12);
+        for s1 in names.iter() {
+            for s2 in names.iter() {
+                assert_eq!(
+                    compute::jaro_score(s1, s2),
+                    compute::jaro_score_scalar(s1, s2)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_pattern_matches_scalar() {
+        let names = random_names(40, 90);
+        for s2 in names.iter() {
+            let pattern = super::JaroPattern::new(s2.as_bytes());
+            for s1 in names.iter() {
+                let max_matching_dist = (s1.len().max(s2.len()) / 2) as i32 - 1;
+                assert_eq!(
+                    pattern.match_counts(s1.as_bytes(), max_matching_dist),
+                    super::match_counts(s1.as_bytes(), s2.as_bytes(), max_matching_dist)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_matches_scalar_long_names() {
+        let names = random_names(30, 200);
+        for s1 in names.iter() {
+            for s2 in names.iter() {
+                assert_eq!(
+                    compute::jaro_score(s1, s2),
+                    compute::jaro_score_scalar(s1, s2)
+                );
+            }
+        }
+    }
+}
@@ -1,6 +1,9 @@
 use super::super::prelude::*;
+use super::bitparallel::JaroPattern;
 use super::compute;
 use super::config;
+use super::similar::SimilarCharTable;
+use std::convert::TryFrom;
 use std::sync::RwLock;
 
 // JaroWinklerMatcher implements Jaro-Winkler algorithm between
@@ -10,6 +13,8 @@ use std::sync::RwLock;
 // Basic preprocessing:
 //   - Replaces non-alphanumeric with whitespace
 //   - Convert to uppercase.
+// When max_permutation_tokens is configured, every ordering of the first
+// name's tokens is compared and the best ordering is reported in MatchResult.
 pub struct JaroWinklerMatcher {
     name: String,
     config: config::JaroWinklerConfigOptions,
@@ -41,6 +46,91 @@ impl JaroWinklerMatcher {
             weight: locked_weight,
         }
     }
+
+    // from_spec builds the matcher described by spec, parameters missing
+    // from spec keep their default:
+    //   - similarity_threshold, max_prefix_length, scaling_factor and
+    //     max_permutation_tokens: See JaroWinklerConfigOptions.
+    //     scaling_factor times max_prefix_length must be at most 1.
+    //   - similar_chars: "census" or a list of two character strings,
+    //     eg ["MN", "IL"].
+    //   - similar_credit: Credit of similar_chars between 0.0 and 1.0,
+    //     0.3 by default.
+    pub fn from_spec(spec: &MatcherSpec) -> Result<JaroWinklerMatcher, SpecError> {
+        spec.check_params(&[
+            "similarity_threshold",
+            "max_prefix_length",
+            "scaling_factor",
+            "max_permutation_tokens",
+            "similar_chars",
+            "similar_credit",
+        ])?;
+        let mut config = config::JaroWinklerConfigOptions::default();
+        if let Some(threshold) = unit_param(spec, "similarity_threshold")? {
+            config.similarity_threshold = threshold;
+        }
+        if let Some(length) = spec.usize_param("max_prefix_length")? {
+            config.max_prefix_length = i32::try_from(length)
+                .map_err(|_| spec.invalid_param("max_prefix_length", "value is too large"))?;
+        }
+        if let Some(factor) = unit_param(spec, "scaling_factor")? {
+            config.scaling_factor = factor;
+        }
+        // Larger boosts give scores above 1.0.
+        if config.scaling_factor * config.max_prefix_length as f64 > 1.0 {
+            return Err(spec.invalid_param(
+                "scaling_factor",
+                "scaling_factor times max_prefix_length must be at most 1",
+            ));
+        }
+        config.max_permutation_tokens = spec.usize_param("max_permutation_tokens")?;
+        config.similar_chars = similar_chars_param(spec)?;
+        Ok(JaroWinklerMatcher::new(Some(config), spec.weight))
+    }
+}
+
+// unit_param reads a number between 0.0 and 1.0.
+fn unit_param(spec: &MatcherSpec, name: &str) -> Result<Option<f64>, SpecError> {
+    match spec.f64_param(name)? {
+        Some(value) if !(0.0..=1.0).contains(&value) => {
+            Err(spec.invalid_param(name, "expected a number between 0 and 1"))
+        }
+        value => Ok(value),
+    }
+}
+
+fn similar_chars_param(spec: &MatcherSpec) -> Result<Option<SimilarCharTable>, SpecError> {
+    let credit = unit_param(spec, "similar_credit")?;
+    let mut table = match spec.params.get("similar_chars") {
+        None if credit.is_some() => {
+            return Err(spec.invalid_param("similar_credit", "similar_chars is not set"))
+        }
+        None => return Ok(None),
+        Some(ParamValue::Text(name)) if name.eq_ignore_ascii_case("census") => {
+            SimilarCharTable::census()
+        }
+        Some(ParamValue::List(values)) => {
+            let mut pairs = Vec::with_capacity(values.len());
+            for value in values.iter() {
+                let pair = match value {
+                    ParamValue::Text(pair) => pair.to_uppercase().chars().collect::<Vec<char>>(),
+                    _ => Vec::new(),
+                };
+                if pair.len() != 2 {
+                    return Err(
+                        spec.invalid_param("similar_chars", "expected two character strings")
+                    );
+                }
+                pairs.push((pair[0], pair[1]));
+            }
+            SimilarCharTable::new(pairs, SimilarCharTable::census().credit())
+        }
+        Some(_) => return Err(spec.invalid_param("similar_chars", "expected \"census\" or a list")),
+    };
+    if let Some(credit) = credit {
+        table.set_credit(credit);
+    }
+    Ok(Some(table))
 }
 
 impl Named for JaroWinklerMatcher {
@@ -63,11 +153,146 @@ impl Weighted for JaroWinklerMatcher {
     }
 }
 
+impl JaroWinklerMatcher {
+    // Scores, match results and bounds are computed on cleaned names,
+    // shared by raw and prepared names.
+    fn score_cleaned(&self, s1: &str, s2: &str) -> f64 {
+        if self.config.max_permutation_tokens.is_some() {
+            let (score, _) = compute::best_ordering_score(s1, s2, &self.config);
+            return score;
+        }
+        compute::jaro_winkler_score(s1, s2, &self.config)
+    }
+
+    fn match_result_cleaned(&self, s1: &str, s2: &str) -> MatchResult {
+        if self.config.max_permutation_tokens.is_none() {
+            let score = self.score_cleaned(s1, s2);
+            return MatchResult::new(self.get_name(), self.get_weight(), score);
+        }
+        let (score, ordering) = compute::best_ordering_score(s1, s2, &self.config);
+        MatchResult::new(self.get_name(), self.get_weight(), score).with_ordering(ordering)
+    }
+
+    fn upper_bound_cleaned(&self, s1: &str, s2: &str) -> f64 {
+        let bound = compute::jaro_winkler_upper_bound(s1.len(), s2.len(), &self.config);
+        if self.config.max_permutation_tokens.is_none() {
+            return bound;
+        }
+        // Reordered tokens are joined by a single whitespace,
+        // which may shorten s1.
+        let reordered_length = s1.split_whitespace().collect::<Vec<&str>>().join(" ").len();
+        let reordered_bound =
+            compute::jaro_winkler_upper_bound(reordered_length, s2.len(), &self.config);
+        bound.max(reordered_bound)
+    }
+}
+
 impl Matcher for JaroWinklerMatcher {
     fn get_score(&self, s1: &str, s2: &str) -> f64 {
-        let s1 = self.clean(s1);
-        let s2 = self.clean(s2);
-        compute::jaro_winkler_score(&s1[..], &s2[..], &self.config)
+        self.score_cleaned(&self.clean(s1)[..], &self.clean(s2)[..])
+    }
+
+    fn get_match_result(&self, s1: &str, s2: &str) -> MatchResult {
+        self.match_result_cleaned(&self.clean(s1)[..], &self.clean(s2)[..])
+    }
+
+    fn get_upper_bound(&self, s1: &str, s2: &str) -> f64 {
+        self.upper_bound_cleaned(&self.clean(s1)[..], &self.clean(s2)[..])
+    }
+
+    fn get_prepared_score(&self, p1: &PreparedName, p2: &PreparedName) -> f64 {
+        self.score_cleaned(p1.cleaned(), p2.cleaned())
+    }
+
+    fn get_prepared_match_result(&self, p1: &PreparedName, p2: &PreparedName) -> MatchResult {
+        self.match_result_cleaned(p1.cleaned(), p2.cleaned())
+    }
+
+    fn get_prepared_upper_bound(&self, p1: &PreparedName, p2: &PreparedName) -> f64 {
+        self.upper_bound_cleaned(p1.cleaned(), p2.cleaned())
+    }
+
+    fn get_spec(&self) -> MatcherSpec {
+        let config = &self.config;
+        let mut spec = MatcherSpec::new(self.get_name())
+            .with_weight(self.get_weight())
+            .with_param(
+                "similarity_threshold",
+                ParamValue::Float(config.similarity_threshold),
+            )
+            .with_param(
+                "max_prefix_length",
+                ParamValue::Integer(config.max_prefix_length as i64),
+            )
+            .with_param("scaling_factor", ParamValue::Float(config.scaling_factor));
+        if let Some(tokens) = config.max_permutation_tokens {
+            spec = spec.with_param("max_permutation_tokens", ParamValue::Integer(tokens as i64));
+        }
+        if let Some(table) = &config.similar_chars {
+            let pairs = table
+                .pairs()
+                .into_iter()
+                .map(|(a, b)| ParamValue::Text(format!("{}{}", a, b)))
+                .collect();
+            spec = spec
+                .with_param("similar_chars", ParamValue::List(pairs))
+                .with_param("similar_credit", ParamValue::Float(table.credit()));
+        }
+        spec
+    }
+
+    fn prepare_query(&self, query: &str) -> Box<dyn QueryScorer + '_> {
+        let query = PreparedName::new(query);
+        let pattern = JaroPattern::new(query.cleaned().as_bytes());
+        Box::new(JaroWinklerQuery {
+            matcher: self,
+            query,
+            pattern,
+        })
+    }
+}
+
+// JaroWinklerQuery keeps the position masks of the cleaned query.
+// Similar characters and token orderings are scored without them.
+struct JaroWinklerQuery<'a> {
+    matcher: &'a JaroWinklerMatcher,
+    query: PreparedName,
+    pattern: JaroPattern,
+}
+
+impl<'a> JaroWinklerQuery<'a> {
+    fn uses_pattern(&self) -> bool {
+        let config = &self.matcher.config;
+        config.similar_chars.is_none() && config.max_permutation_tokens.is_none()
+    }
+}
+
+impl<'a> QueryScorer for JaroWinklerQuery<'a> {
+    fn score(&self, candidate: &PreparedName) -> f64 {
+        if !self.uses_pattern() {
+            return self.matcher.get_prepared_score(&self.query, candidate);
+        }
+        compute::jaro_winkler_pattern_score(
+            &self.pattern,
+            self.query.cleaned(),
+            candidate.cleaned(),
+            &self.matcher.config,
+        )
+    }
+
+    fn match_result(&self, candidate: &PreparedName) -> MatchResult {
+        if self.matcher.config.max_permutation_tokens.is_some() {
+            return self
+                .matcher
+                .get_prepared_match_result(&self.query, candidate);
+        }
+        let score = self.score(candidate);
+        MatchResult::new(self.matcher.get_name(), self.matcher.get_weight(), score)
+    }
+
+    fn upper_bound(&self, candidate: &PreparedName) -> f64 {
+        self.matcher
+            .get_prepared_upper_bound(&self.query, candidate)
     }
 }
 
@@ -107,4 +332,64 @@ mod test {
         let name2 = "JOHN DOE";
         assert_eq!(matcher.get_weighted_score(name1, name2), 0.5);
     }
+
+    #[test]
+    fn test_name_order_search() {
+        let mut jw_config = config::JaroWinklerConfigOptions::default();
+        jw_config.max_permutation_tokens = Some(3);
+        let matcher = super::JaroWinklerMatcher::new(Some(jw_config), None);
+        let name1 = "Kim Min-jun";
+        let name2 = "Min Jun Kim";
+        assert_eq!(matcher.get_score(name1, name2), 1.0);
+    }
+
+    #[test]
+    fn test_name_order_search_reports_ordering() {
+        let mut jw_config = config::JaroWinklerConfigOptions::default();
+        jw_config.max_permutation_tokens = Some(3);
+        let matcher = super::JaroWinklerMatcher::new(Some(jw_config), None);
+        let result = matcher.get_match_result("Nagy Istvan", "Istvan Nagy");
+        assert_eq!(result.absolute_score, 1.0);
+        assert_eq!(result.ordering, Some("ISTVAN NAGY".to_owned()));
+    }
+
+    #[test]
+    fn test_no_ordering_by_default() {
+        let matcher = super::JaroWinklerMatcher::default();
+        let result = matcher.get_match_result("Nagy Istvan", "Istvan Nagy");
+        assert!(result.absolute_score < 1.0);
+        assert_eq!(result.ordering, None);
+    }
+
+    #[test]
+    fn test_similar_chars() {
+        use super::super::similar::SimilarCharTable;
+        let mut jw_config = config::JaroWinklerConfigOptions::default();
+        jw_config.similar_chars = Some(SimilarCharTable::census());
+        let matcher = super::JaroWinklerMatcher::new(Some(jw_config), None);
+        let default_matcher = super::JaroWinklerMatcher::default();
+        let name1 = "Snith";
+        let name2 = "Smith";
+        assert!(matcher.get_score(name1, name2) > default_matcher.get_score(name1, name2));
+    }
+
+    #[test]
+    fn test_prepared_query() {
+        let matcher = super::JaroWinklerMatcher::default();
+        let query = matcher.prepare_query("Jon Doe");
+        for name in ["John Doe", "Jane Doe", "Doe John", "", "Ølaf Doe"] {
+            let candidate = PreparedName::new(name);
+            assert_eq!(query.score(&candidate), matcher.get_score("Jon Doe", name));
+        }
+    }
+
+    #[test]
+    fn test_upper_bound() {
+        let matcher = super::JaroWinklerMatcher::default();
+        let name1 = "Al";
+        let name2 = "Alexander Hamilton";
+        let bound = matcher.get_upper_bound(name1, name2);
+        assert!(bound < 1.0);
+        assert!(matcher.get_score(name1, name2) <= bound);
+    }
 }
@@ -1,4 +1,6 @@
+use super::bitparallel::{self, JaroPattern};
 use super::config;
+use super::similar::SimilarCharTable;
 use std::cmp;
 
 fn max_distance_allowed(len1: i32, len2: i32) -> i32 {
@@ -90,7 +92,49 @@ fn transpositions(s1: &str, s2: &str, hash_1: &Vec<i32>, hash_2: &Vec<i32>) -> f
 // jaro_score returns the jaro distance between
 // string s1 and string s2.
 // Range between 0.0 and 1.0.
+// Matches and transpositions are counted by the bit-parallel kernel.
 pub fn jaro_score(s1: &str, s2: &str) -> f64 {
+    bit_parallel_jaro_score(s1, s2, |max_matching_dist| {
+        bitparallel::match_counts(s1.as_bytes(), s2.as_bytes(), max_matching_dist)
+    })
+}
+
+// jaro_pattern_score is jaro_score between the string s1 the pattern was
+// built from and s2. Matches and transpositions are symmetric, so s2 is
+// scanned against the pattern of s1.
+pub(crate) fn jaro_pattern_score(pattern: &JaroPattern, s1: &str, s2: &str) -> f64 {
+    bit_parallel_jaro_score(s1, s2, |max_matching_dist| {
+        pattern.match_counts(s2.as_bytes(), max_matching_dist)
+    })
+}
+
+fn bit_parallel_jaro_score<F>(s1: &str, s2: &str, match_counts: F) -> f64
+where
+    F: Fn(i32) -> (usize, usize),
+{
+    let len1 = s1.len();
+    let len2 = s2.len();
+    if len1 == 0 || len2 == 0 {
+        return 0.0;
+    }
+    if s1 == s2 {
+        return 1.0;
+    }
+
+    let max_matching_dist = max_distance_allowed(len1 as i32, len2 as i32);
+    let (matches, transpositions) = match_counts(max_matching_dist);
+    if matches == 0 {
+        return 0.0;
+    }
+
+    let matches = matches as f64;
+    let transpositions = transpositions as f64 / 2.0;
+    (matches / len1 as f64 + matches / len2 as f64 + (matches - transpositions) / matches) / 3.0
+}
+
+// jaro_score_scalar is jaro_score computed by scanning the matching window
+// of every character. Kept as a reference for the bit-parallel kernel.
+pub fn jaro_score_scalar(s1: &str, s2: &str) -> f64 {
     //Remove all white space
 
     let len1 = s1.len();
@@ -128,10 +172,111 @@ pub fn jaro_score(s1: &str, s2: &str) -> f64 {
     (matches / len1 + matches / len2 + (matches - transpositions) / matches) / 3.0
 }
 
+// similar_matches returns the number of similar character pairs among
+// the characters left unmatched in s1 and s2, regardless of distance.
+// A character of s2 is paired with at most one character of s1.
+//
+// Eg:
+// "MARHN" vs "MARTIN" with ('M', 'N') and ('L', 'I') similar
+// Unmatched: "H" vs "TI" -> 0 similar pairs
+fn similar_matches(
+    s1: &str,
+    s2: &str,
+    hash_1: &[i32],
+    hash_2: &[i32],
+    table: &SimilarCharTable,
+) -> i32 {
+    let mut similar = 0;
+    let mut paired_2 = hash_2.to_vec();
+    for (i, char_i) in s1.bytes().enumerate() {
+        if hash_1[i] == 1 {
+            continue;
+        }
+        for (j, char_j) in s2.bytes().enumerate() {
+            if paired_2[j] == 0 && table.is_similar(char_i as char, char_j as char) {
+                paired_2[j] = 1;
+                similar += 1;
+                break;
+            }
+        }
+    }
+    similar
+}
+
+// adjusted_jaro_score returns the Jaro distance between string s1 and
+// string s2 where similar characters that did not match exactly
+// earn partial credit, as in Winkler's strcmp95.
+// The transposition term only counts exact matches.
+// Range between 0.0 and 1.0.
+pub fn adjusted_jaro_score(s1: &str, s2: &str, table: &SimilarCharTable) -> f64 {
+    let len1 = s1.len();
+    let len2 = s2.len();
+
+    if len1 == 0 || len2 == 0 {
+        return 0.0;
+    }
+
+    if s1 == s2 {
+        return 1.0;
+    }
+
+    let max_matching_dist = max_distance_allowed(len1 as i32, len2 as i32);
+    let mut matching_hash_1 = vec![0; len1];
+    let mut matching_hash_2 = vec![0; len2];
+    let matches = matches(
+        s1,
+        s2,
+        &mut matching_hash_1,
+        &mut matching_hash_2,
+        max_matching_dist,
+    );
+
+    if matches == 0 {
+        return 0.0;
+    }
+
+    let transpositions = transpositions(s1, s2, &matching_hash_1, &matching_hash_2);
+    let similar = similar_matches(s1, s2, &matching_hash_1, &matching_hash_2, table);
+
+    let matches = matches as f64;
+    let adjusted_matches = matches + table.credit() * similar as f64;
+    let len1 = len1 as f64;
+    let len2 = len2 as f64;
+    let score =
+        (adjusted_matches / len1 + adjusted_matches / len2 + (matches - transpositions) / matches)
+            / 3.0;
+    score.min(1.0)
+}
+
 // jaro_winkler_score returns Jaro Winkler score
 // Between 0.0 and 1.0
 pub fn jaro_winkler_score(s1: &str, s2: &str, config: &config::JaroWinklerConfigOptions) -> f64 {
-    let mut jaro_distance = jaro_score(s1, s2);
+    let jaro_distance = match &config.similar_chars {
+        Some(table) => adjusted_jaro_score(s1, s2, table),
+        None => jaro_score(s1, s2),
+    };
+    winkler_boost(jaro_distance, s1, s2, config)
+}
+
+// jaro_winkler_pattern_score is jaro_winkler_score between the string s1 the
+// pattern was built from and s2. Similar characters are not considered.
+pub(crate) fn jaro_winkler_pattern_score(
+    pattern: &JaroPattern,
+    s1: &str,
+    s2: &str,
+    config: &config::JaroWinklerConfigOptions,
+) -> f64 {
+    winkler_boost(jaro_pattern_score(pattern, s1, s2), s1, s2, config)
+}
+
+// winkler_boost raises Jaro scores above the similarity threshold
+// according to the length of the common prefix.
+fn winkler_boost(
+    mut jaro_distance: f64,
+    s1: &str,
+    s2: &str,
+    config: &config::JaroWinklerConfigOptions,
+) -> f64 {
     if jaro_distance > config.similarity_threshold {
         let mut prefix_length = common_prefix_length(s1, s2);
         prefix_length = cmp::min(config.max_prefix_length, prefix_length);
@@ -141,6 +286,97 @@ pub fn jaro_winkler_score(s1: &str, s2: &str, config: &config::JaroWinklerConfig
     return jaro_distance;
 }
 
+// jaro_winkler_upper_bound returns the highest Jaro Winkler score two strings
+// of length len1 and len2 could reach, assuming every character of the
+// shorter string matches without transposition and the longest prefix is shared.
+// Similar characters and token orderings never exceed it.
+// The Winkler boost only grows with the Jaro score when scaling_factor
+// times the prefix length is at most 1. Otherwise lower Jaro scores get
+// higher boosted scores, the highest one just above similarity_threshold.
+pub fn jaro_winkler_upper_bound(
+    len1: usize,
+    len2: usize,
+    config: &config::JaroWinklerConfigOptions,
+) -> f64 {
+    if len1 == 0 || len2 == 0 {
+        return 0.0;
+    }
+    let matches = cmp::min(len1, len2);
+    let mut bound = (matches as f64 / len1 as f64 + matches as f64 / len2 as f64 + 1.0) / 3.0;
+    if bound > config.similarity_threshold {
+        let prefix_length = (config.max_prefix_length as i64).min(matches as i64) as f64;
+        let boost = config.scaling_factor * prefix_length;
+        if boost <= 1.0 {
+            bound = (bound + boost * (1. - bound)).min(1.0);
+        } else {
+            bound = config.similarity_threshold + boost * (1. - config.similarity_threshold);
+        }
+    }
+    bound
+}
+
+// permutations returns every ordering of tokens using Heap's algorithm.
+// The given ordering is always returned first.
+fn permutations<'a>(tokens: &[&'a str]) -> Vec<Vec<&'a str>> {
+    let mut current = tokens.to_vec();
+    let mut result = vec![current.clone()];
+    let mut counters = vec![0; current.len()];
+    let mut i = 1;
+    while i < current.len() {
+        if counters[i] < i {
+            if i % 2 == 0 {
+                current.swap(0, i);
+            } else {
+                current.swap(counters[i], i);
+            }
+            result.push(current.clone());
+            counters[i] += 1;
+            i = 1;
+        } else {
+            counters[i] = 0;
+            i += 1;
+        }
+    }
+    result
+}
+
+// best_ordering_score compares every token ordering of s1 against s2
+// and returns the best Jaro Winkler score with the ordering that produced it.
+// s1 is compared in given order only when it has more tokens than allowed
+// by config.max_permutation_tokens.
+// Eg:
+// "DOE JOHN" vs "JOHN DOE"
+// -> (1.0, "JOHN DOE")
+pub fn best_ordering_score(
+    s1: &str,
+    s2: &str,
+    config: &config::JaroWinklerConfigOptions,
+) -> (f64, String) {
+    let tokens: Vec<&str> = s1.split_whitespace().collect();
+    let max_tokens = config
+        .max_permutation_tokens
+        .unwrap_or(0)
+        .min(config::MAX_PERMUTATION_TOKENS);
+    if tokens.len() < 2 || tokens.len() > max_tokens {
+        return (jaro_winkler_score(s1, s2, config), s1.to_owned());
+    }
+
+    let mut best_score = -1.0;
+    let mut best_ordering = String::new();
+    for ordering in permutations(&tokens) {
+        let ordering = ordering.join(" ");
+        let score = jaro_winkler_score(&ordering[..], s2, config);
+        if score > best_score {
+            best_score = score;
+            best_ordering = ordering;
+        }
+        if best_score >= 1.0 {
+            break;
+        }
+    }
+    (best_score, best_ordering)
+}
+
 #[cfg(test)]
 mod test {
     #[test]
@@ -204,7 +440,7 @@ mod test {
         let matches_count = super::matches(string1, string2, &mut hash_1, &mut hash_2, 4);
         assert_eq!(matches_count, 0);
         assert_eq!(hash_1, vec![0]);
-        assert_eq!(hash_2, vec![]);
+        assert!(hash_2.is_empty());
     }
 
     // Test Case for transpositions
@@ -319,4 +555,138 @@ mod test {
         let expected = 0.901;
         assert!((score - expected).abs() < 0.01);
     }
+
+    // Test Case for name-order search
+    #[test]
+    fn test_permutations_count() {
+        let orderings = super::permutations(&["A", "B", "C"]);
+        assert_eq!(orderings.len(), 6);
+        assert_eq!(orderings[0], vec!["A", "B", "C"]);
+    }
+
+    #[test]
+    fn test_permutations_are_unique() {
+        use std::collections::HashSet;
+        let orderings = super::permutations(&["A", "B", "C", "D"]);
+        let unique: HashSet<Vec<&str>> = orderings.into_iter().collect();
+        assert_eq!(unique.len(), 24);
+    }
+
+    #[test]
+    fn test_best_ordering_score_transposed() {
+        use super::config;
+        let mut jw_config = config::JaroWinklerConfigOptions::default();
+        jw_config.max_permutation_tokens = Some(3);
+        let (score, ordering) = super::best_ordering_score("DOE JOHN", "JOHN DOE", &jw_config);
+        assert_eq!(score, 1.0);
+        assert_eq!(ordering, "JOHN DOE");
+    }
+
+    #[test]
+    fn test_best_ordering_score_too_many_tokens() {
+        use super::config;
+        let mut jw_config = config::JaroWinklerConfigOptions::default();
+        jw_config.max_permutation_tokens = Some(1);
+        let (score, ordering) = super::best_ordering_score("DOE JOHN", "JOHN DOE", &jw_config);
+        assert_eq!(
+            score,
+            super::jaro_winkler_score("DOE JOHN", "JOHN DOE", &jw_config)
+        );
+        assert_eq!(ordering, "DOE JOHN");
+    }
+
+    // Test Cases for adjusted Jaro Score
+    #[test]
+    fn test_similar_matches_ocr_error() {
+        use super::super::similar::SimilarCharTable;
+        let table = SimilarCharTable::census();
+        let string1 = "SMITH";
+        let string2 = "SNITH";
+        let hash_1 = vec![1, 0, 1, 1, 1];
+        let hash_2 = vec![1, 0, 1, 1, 1];
+        let similar = super::similar_matches(string1, string2, &hash_1, &hash_2, &table);
+        assert_eq!(similar, 1);
+    }
+
+    #[test]
+    fn test_adjusted_jaro_ocr_error() {
+        use super::super::similar::SimilarCharTable;
+        let table = SimilarCharTable::census();
+        let string1 = "SMITH";
+        let string2 = "SNITH";
+        let score = super::adjusted_jaro_score(string1, string2, &table);
+        // (4.3 / 5 + 4.3 / 5 + 4 / 4) / 3
+        let expected = 0.9067;
+        assert!((score - expected).abs() < 0.001);
+        assert!(score > super::jaro_score(string1, string2));
+    }
+
+    #[test]
+    fn test_adjusted_jaro_no_similar_chars() {
+        use super::super::similar::SimilarCharTable;
+        let table = SimilarCharTable::census();
+        let string1 = "MARTHA";
+        let string2 = "MHARTA";
+        let score = super::adjusted_jaro_score(string1, string2, &table);
+        assert_eq!(score, super::jaro_score(string1, string2));
+    }
+
+    #[test]
+    fn test_adjusted_jaro_empty_string() {
+        use super::super::similar::SimilarCharTable;
+        let table = SimilarCharTable::census();
+        let score = super::adjusted_jaro_score("", "A", &table);
+        assert_eq!(score, 0.);
+    }
+
+    // Test Cases for upper bound
+    #[test]
+    fn test_jaro_winkler_upper_bound_same_length() {
+        use super::config;
+        let jw_config = config::JaroWinklerConfigOptions::default();
+        let bound = super::jaro_winkler_upper_bound(5, 5, &jw_config);
+        assert_eq!(bound, 1.0);
+    }
+
+    #[test]
+    fn test_jaro_winkler_upper_bound_is_bound() {
+        use super::config;
+        let jw_config = config::JaroWinklerConfigOptions::default();
+        let pairs = vec![
+            ("DUANE", "DWAYNE"),
+            ("MARTHA", "MHARTA"),
+            ("AL", "ALEXANDER"),
+        ];
+        for (s1, s2) in pairs.into_iter() {
+            let bound = super::jaro_winkler_upper_bound(s1.len(), s2.len(), &jw_config);
+            assert!(super::jaro_winkler_score(s1, s2, &jw_config) <= bound);
+        }
+    }
+
+    #[test]
+    fn test_jaro_winkler_upper_bound_large_boost() {
+        use super::config;
+        // 0.3 * 4 > 1, lower Jaro scores get boosted above higher ones.
+        let jw_config = config::JaroWinklerConfigOptions::new(0.7, 4, 0.3);
+        for (s1, s2) in [("JOHNAXX", "JOHNAYY"), ("MARTHA", "MARHTA")] {
+            let bound = super::jaro_winkler_upper_bound(s1.len(), s2.len(), &jw_config);
+            assert!(super::jaro_winkler_score(s1, s2, &jw_config) <= bound);
+        }
+    }
+
+    #[test]
+    fn test_jaro_winkler_upper_bound_length_ratio() {
+        use super::config;
+        let jw_config = config::JaroWinklerConfigOptions::default();
+        // (2 / 2 + 2 / 10 + 1) / 3 = 0.7333, boosted by a prefix of 2
+        let bound = super::jaro_winkler_upper_bound(2, 10, &jw_config);
+        assert!((bound - 0.7867).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_jaro_winkler_upper_bound_empty_string() {
+        use super::config;
+        let jw_config = config::JaroWinklerConfigOptions::default();
+        assert_eq!(super::jaro_winkler_upper_bound(0, 3, &jw_config), 0.0);
+    }
 }
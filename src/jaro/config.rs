@@ -1,7 +1,27 @@
+use super::similar::SimilarCharTable;
+use serde::{Deserialize, Serialize};
+
+// Maximum number of tokens whose orderings can be searched.
+// 6 tokens already produce 720 orderings.
+pub const MAX_PERMUTATION_TOKENS: usize = 6;
+
+// max_permutation_tokens enables name-order search when set. Names with
+// at most that many tokens (capped by MAX_PERMUTATION_TOKENS) are compared
+// in every token ordering and the best score is kept. Disabled by default.
+// similar_chars enables the adjusted Jaro score, giving partial credit to
+// similar characters that did not match exactly. Disabled by default.
+// Missing fields are deserialized to their default.
+// Build options with new or default, then set fields, as more options may
+// be added.
+#[derive(Serialize, Deserialize, Clone)]
+#[non_exhaustive]
+#[serde(default = "JaroWinklerConfigOptions::default")]
 pub struct JaroWinklerConfigOptions {
     pub similarity_threshold: f64,
     pub max_prefix_length: i32,
     pub scaling_factor: f64,
+    pub max_permutation_tokens: Option<usize>,
+    pub similar_chars: Option<SimilarCharTable>,
 }
 
 impl JaroWinklerConfigOptions {
@@ -10,6 +30,8 @@ impl JaroWinklerConfigOptions {
             similarity_threshold: 0.7,
             max_prefix_length: 4,
             scaling_factor: 0.1,
+            max_permutation_tokens: None,
+            similar_chars: None,
         }
     }
 
@@ -22,6 +44,8 @@ impl JaroWinklerConfigOptions {
             similarity_threshold,
             max_prefix_length,
             scaling_factor,
+            max_permutation_tokens: None,
+            similar_chars: None,
         }
     }
 }
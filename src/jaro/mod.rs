@@ -1,3 +1,5 @@
+mod bitparallel;
 pub mod compare;
-mod compute;
+pub mod compute;
 pub mod config;
+pub mod similar;
@@ -0,0 +1,172 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+// Similar character pairs from the U.S. Census Bureau strcmp95 implementation
+// by Winkler. Pairs are commonly confused through keying or OCR errors,
+// or sound alike.
+const CENSUS_PAIRS: [(char, char); 39] = [
+    ('A', 'E'),
+    ('A', 'I'),
+    ('A', 'O'),
+    ('A', 'U'),
+    ('B', 'V'),
+    ('E', 'I'),
+    ('E', 'O'),
+    ('E', 'U'),
+    ('I', 'O'),
+    ('I', 'U'),
+    ('O', 'U'),
+    ('I', 'Y'),
+    ('E', 'Y'),
+    ('C', 'G'),
+    ('E', 'F'),
+    ('W', 'U'),
+    ('W', 'V'),
+    ('X', 'K'),
+    ('S', 'Z'),
+    ('X', 'S'),
+    ('Q', 'C'),
+    ('U', 'V'),
+    ('M', 'N'),
+    ('L', 'I'),
+    ('Q', 'O'),
+    ('P', 'R'),
+    ('I', 'J'),
+    ('2', 'Z'),
+    ('5', 'S'),
+    ('8', 'B'),
+    ('1', 'I'),
+    ('1', 'L'),
+    ('0', 'O'),
+    ('0', 'Q'),
+    ('C', 'K'),
+    ('G', 'J'),
+    ('E', ' '),
+    ('Y', ' '),
+    ('S', ' '),
+];
+
+// SimilarCharTable holds pairs of characters that earn partial credit
+// when they fail to match exactly in the adjusted Jaro score.
+// Pairs are symmetric, ('I', 'L') also covers ('L', 'I').
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(into = "SimilarChars", from = "SimilarChars")]
+pub struct SimilarCharTable {
+    pairs: HashSet<(char, char)>,
+    // Credit given to every similar pair, relative to an exact match.
+    credit: f64,
+}
+
+impl SimilarCharTable {
+    // new builds a table of pairs, credit is clamped between 0.0 and 1.0
+    // so that adjusted scores stay between 0.0 and 1.0.
+    pub fn new(pairs: Vec<(char, char)>, credit: f64) -> SimilarCharTable {
+        let mut table = HashSet::new();
+        for (a, b) in pairs.into_iter() {
+            table.insert((a, b));
+            table.insert((b, a));
+        }
+        let mut table = SimilarCharTable {
+            pairs: table,
+            credit: 0.0,
+        };
+        table.set_credit(credit);
+        table
+    }
+
+    // census returns the classic table bundled with strcmp95,
+    // where every similar pair is worth 0.3 of a match.
+    pub fn census() -> SimilarCharTable {
+        SimilarCharTable::new(CENSUS_PAIRS.to_vec(), 0.3)
+    }
+
+    pub fn credit(&self) -> f64 {
+        self.credit
+    }
+
+    // set_credit clamps credit between 0.0 and 1.0, NaN gives no credit.
+    pub fn set_credit(&mut self, credit: f64) {
+        self.credit = if credit.is_nan() {
+            0.0
+        } else {
+            credit.clamp(0.0, 1.0)
+        };
+    }
+
+    pub fn is_similar(&self, a: char, b: char) -> bool {
+        self.pairs.contains(&(a, b))
+    }
+
+    // pairs returns every pair once, smallest character first, sorted.
+    pub fn pairs(&self) -> Vec<(char, char)> {
+        let mut pairs: Vec<(char, char)> =
+            self.pairs.iter().filter(|(a, b)| a <= b).cloned().collect();
+        pairs.sort_unstable();
+        pairs
+    }
+}
+
+// SimilarChars is how SimilarCharTable is serialized, every pair once.
+#[derive(Serialize, Deserialize)]
+struct SimilarChars {
+    pairs: Vec<(char, char)>,
+    credit: f64,
+}
+
+impl From<SimilarCharTable> for SimilarChars {
+    fn from(table: SimilarCharTable) -> SimilarChars {
+        SimilarChars {
+            pairs: table.pairs(),
+            credit: table.credit,
+        }
+    }
+}
+
+impl From<SimilarChars> for SimilarCharTable {
+    fn from(chars: SimilarChars) -> SimilarCharTable {
+        SimilarCharTable::new(chars.pairs, chars.credit)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::SimilarCharTable;
+
+    #[test]
+    fn test_census_is_symmetric() {
+        let table = SimilarCharTable::census();
+        assert!(table.is_similar('I', 'L'));
+        assert!(table.is_similar('L', 'I'));
+    }
+
+    #[test]
+    fn test_census_not_similar() {
+        let table = SimilarCharTable::census();
+        assert!(!table.is_similar('A', 'B'));
+        assert!(!table.is_similar('A', 'A'));
+    }
+
+    #[test]
+    fn test_custom_table() {
+        let table = SimilarCharTable::new(vec![('M', 'N')], 0.5);
+        assert!(table.is_similar('N', 'M'));
+        assert!(!table.is_similar('O', 'Q'));
+        assert_eq!(table.credit(), 0.5);
+    }
+
+    #[test]
+    fn test_credit_is_clamped() {
+        assert_eq!(SimilarCharTable::new(vec![('M', 'N')], 1.5).credit(), 1.0);
+        assert_eq!(SimilarCharTable::new(vec![('M', 'N')], -0.5).credit(), 0.0);
+        let mut table = SimilarCharTable::census();
+        table.set_credit(f64::NAN);
+        assert_eq!(table.credit(), 0.0);
+    }
+
+    #[test]
+    fn test_pairs() {
+        let table = SimilarCharTable::new(vec![('N', 'M'), ('I', 'L'), ('L', 'I')], 0.5);
+        assert_eq!(table.pairs(), vec![('I', 'L'), ('M', 'N')]);
+        assert_eq!(SimilarCharTable::census().pairs().len(), 39);
+    }
+}
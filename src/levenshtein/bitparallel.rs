@@ -0,0 +1,274 @@
+// Bit-parallel Levenshtein distance (Myers 1999, Hyyrö 2003).
+// A column of the dynamic programming matrix is encoded as vertical deltas,
+// one bit per character of the pattern, and updated for every character of
+// the text in a constant number of word operations.
+
+const WORD_BITS: usize = 64;
+const ASCII: usize = 128;
+
+// PatternMasks holds, for every character of the pattern, the positions it
+// occurs at. ASCII characters are looked up in a table, other characters
+// in a short list.
+struct PatternMasks {
+    words: usize,
+    ascii: Vec<u64>,
+    extended: Vec<(char, Vec<u64>)>,
+}
+
+impl PatternMasks {
+    fn new(pattern: &[char]) -> PatternMasks {
+        let words = pattern.len().div_ceil(WORD_BITS);
+        let mut masks = PatternMasks {
+            words,
+            ascii: vec![0; ASCII * words],
+            extended: Vec::new(),
+        };
+        for (i, c) in pattern.iter().enumerate() {
+            let (word, bit) = (i / WORD_BITS, 1 << (i % WORD_BITS));
+            if (*c as usize) < ASCII {
+                masks.ascii[*c as usize * words + word] |= bit;
+                continue;
+            }
+            match masks.extended.iter_mut().find(|(other, _)| other == c) {
+                Some((_, mask)) => mask[word] |= bit,
+                None => {
+                    let mut mask = vec![0; words];
+                    mask[word] |= bit;
+                    masks.extended.push((*c, mask));
+                }
+            }
+        }
+        masks
+    }
+
+    fn get(&self, c: char, word: usize) -> u64 {
+        if (c as usize) < ASCII {
+            return self.ascii[c as usize * self.words + word];
+        }
+        self.extended
+            .iter()
+            .find(|(other, _)| *other == c)
+            .map_or(0, |(_, mask)| mask[word])
+    }
+}
+
+// levenshtein_distance returns the Levenshtein distance between chars_1 and
+// chars_2. The shorter string is used as pattern. Patterns of at most 64
+// characters are handled without allocation.
+pub fn levenshtein_distance(chars_1: &[char], chars_2: &[char]) -> usize {
+    let (pattern, text) = if chars_1.len() <= chars_2.len() {
+        (chars_1, chars_2)
+    } else {
+        (chars_2, chars_1)
+    };
+    if pattern.is_empty() {
+        return text.len();
+    }
+    if pattern.len() <= WORD_BITS {
+        distance_64(pattern, text)
+    } else {
+        distance_blocked(pattern, text)
+    }
+}
+
+// LevenshteinPattern keeps the masks of a pattern so that its distance to
+// many texts can be computed without rebuilding them.
+pub(crate) struct LevenshteinPattern {
+    len: usize,
+    masks: PatternMasks,
+}
+
+impl LevenshteinPattern {
+    pub(crate) fn new(pattern: &[char]) -> LevenshteinPattern {
+        LevenshteinPattern {
+            len: pattern.len(),
+            masks: PatternMasks::new(pattern),
+        }
+    }
+
+    // distance returns the Levenshtein distance between the pattern and text.
+    pub(crate) fn distance(&self, text: &[char]) -> usize {
+        if self.len == 0 {
+            return text.len();
+        }
+        if self.len <= WORD_BITS {
+            word_distance(self.len, text, |c| self.masks.get(c, 0))
+        } else {
+            blocked_distance(self.len, &self.masks, text)
+        }
+    }
+}
+
+fn distance_64(pattern: &[char], text: &[char]) -> usize {
+    // Masks of ASCII characters are kept on the stack, other characters of
+    // the pattern in a fixed size list.
+    let mut ascii = [0u64; ASCII];
+    let mut extended = [('\0', 0u64); WORD_BITS];
+    let mut extended_len = 0;
+    for (i, c) in pattern.iter().enumerate() {
+        if (*c as usize) < ASCII {
+            ascii[*c as usize] |= 1 << i;
+            continue;
+        }
+        match extended[..extended_len]
+            .iter_mut()
+            .find(|(other, _)| other == c)
+        {
+            Some((_, mask)) => *mask |= 1 << i,
+            None => {
+                extended[extended_len] = (*c, 1 << i);
+                extended_len += 1;
+            }
+        }
+    }
+
+    word_distance(pattern.len(), text, |c| {
+        if (c as usize) < ASCII {
+            ascii[c as usize]
+        } else {
+            extended[..extended_len]
+                .iter()
+                .find(|(other, _)| *other == c)
+                .map_or(0, |(_, mask)| *mask)
+        }
+    })
+}
+
+// word_distance runs the single word algorithm for a pattern of len
+// characters, eq returns the positions a character occurs at.
+fn word_distance<F: Fn(char) -> u64>(len: usize, text: &[char], eq: F) -> usize {
+    let last = 1u64 << (len - 1);
+    let mut vp: u64 = if len == WORD_BITS { !0 } else { (1 << len) - 1 };
+    let mut vn: u64 = 0;
+    let mut distance = len;
+    for c in text.iter() {
+        let eq = eq(*c);
+        let x = eq | vn;
+        let d0 = ((vp.wrapping_add(x & vp)) ^ vp) | x;
+        let mut hp = vn | !(d0 | vp);
+        let mut hn = vp & d0;
+        if hp & last != 0 {
+            distance += 1;
+        } else if hn & last != 0 {
+            distance -= 1;
+        }
+        hp = (hp << 1) | 1;
+        hn <<= 1;
+        vp = hn | !(d0 | hp);
+        vn = hp & d0;
+    }
+    distance
+}
+
+fn distance_blocked(pattern: &[char], text: &[char]) -> usize {
+    blocked_distance(pattern.len(), &PatternMasks::new(pattern), text)
+}
+
+fn blocked_distance(len: usize, masks: &PatternMasks, text: &[char]) -> usize {
+    let words = masks.words;
+    let last = 1u64 << ((len - 1) % WORD_BITS);
+    let mut vp = vec![!0u64; words];
+    let mut vn = vec![0u64; words];
+    let mut distance = len;
+    for c in text.iter() {
+        // Horizontal deltas entering the first word, the first row of the
+        // matrix always increases by one.
+        let mut hp_carry: u64 = 1;
+        let mut hn_carry: u64 = 0;
+        for word in 0..words {
+            let eq = masks.get(*c, word);
+            let x = eq | hn_carry;
+            let d0 = ((x & vp[word]).wrapping_add(vp[word]) ^ vp[word]) | x | vn[word];
+            let mut hp = vn[word] | !(d0 | vp[word]);
+            let mut hn = d0 & vp[word];
+
+            let (hp_in, hn_in) = (hp_carry, hn_carry);
+            if word < words - 1 {
+                hp_carry = hp >> 63;
+                hn_carry = hn >> 63;
+            } else {
+                hp_carry = (hp & last != 0) as u64;
+                hn_carry = (hn & last != 0) as u64;
+            }
+            hp = (hp << 1) | hp_in;
+            hn = (hn << 1) | hn_in;
+            vp[word] = hn | !(d0 | hp);
+            vn[word] = hp & d0;
+        }
+        distance = distance + hp_carry as usize - hn_carry as usize;
+    }
+    distance
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::super::shared::compute::test;
+    use super::super::compute;
+
+    fn random_names(count: usize, max_length: usize) -> Vec<Vec<char>> {
+        let alphabet = ['A', 'B', 'C', 'D', 'É', 'Ø'];
+        test::random_names(7, &alphabet, count, max_length)
+            .iter()
+            .map(|name| name.chars().collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_levenshtein_distance() {
+        let kitten: Vec<char> = "KITTEN".chars().collect();
+        let sitting: Vec<char> = "SITTING".chars().collect();
+        assert_eq!(super::levenshtein_distance(&kitten, &sitting), 3);
+        assert_eq!(super::levenshtein_distance(&[], &sitting), 7);
+    }
+
+    #[test]
+    fn test_matches_scalar_short_names() {
+        let names = random_names(60, 12);
+        for s1 in names.iter() {
+            for s2 in names.iter() {
+                assert_eq!(
+                    super::levenshtein_distance(s1, s2),
+                    compute::levenshtein_distance_scalar(s1, s2)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_matches_scalar_long_names() {
+        let names = random_names(30, 200);
+        for s1 in names.iter() {
+            for s2 in names.iter() {
+                assert_eq!(
+                    super::levenshtein_distance(s1, s2),
+                    compute::levenshtein_distance_scalar(s1, s2)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_pattern_matches_scalar() {
+        let mut names = random_names(30, 12);
+        names.extend(random_names(10, 150));
+        for s1 in names.iter() {
+            let pattern = super::LevenshteinPattern::new(s1);
+            for s2 in names.iter() {
+                assert_eq!(
+                    pattern.distance(s2),
+                    compute::levenshtein_distance_scalar(s1, s2)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_pattern_of_64_characters() {
+        let s1: Vec<char> = "AB".repeat(32).chars().collect();
+        let s2: Vec<char> = "BA".repeat(33).chars().collect();
+        assert_eq!(
+            super::levenshtein_distance(&s1, &s2),
+            compute::levenshtein_distance_scalar(&s1, &s2)
+        );
+    }
+}
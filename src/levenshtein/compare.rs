@@ -0,0 +1,178 @@
+use super::super::prelude::*;
+use super::bitparallel::LevenshteinPattern;
+use super::compute;
+use std::sync::RwLock;
+
+// LevenshteinMatcher measures the edit distance between two names,
+// normalized by the length of the longer name. Between 0.0 and 1.0.
+// Score is 1 - d / max(len1, len2).
+// Basic preprocessing:
+//   - Replaces non-alphanumeric with whitespace
+//   - Convert to uppercase.
+pub struct LevenshteinMatcher {
+    name: String,
+    weight: RwLock<f64>,
+}
+
+impl LevenshteinMatcher {
+    pub fn new(weight: Option<f64>) -> LevenshteinMatcher {
+        let weight = weight.unwrap_or(1.0);
+        let locked_weight = RwLock::new(weight);
+        LevenshteinMatcher {
+            name: "Levenshtein".to_owned(),
+            weight: locked_weight,
+        }
+    }
+
+    pub fn from_spec(spec: &MatcherSpec) -> Result<LevenshteinMatcher, SpecError> {
+        spec.check_params(&[])?;
+        Ok(LevenshteinMatcher::new(spec.weight))
+    }
+}
+
+impl Default for LevenshteinMatcher {
+    fn default() -> LevenshteinMatcher {
+        LevenshteinMatcher::new(None)
+    }
+}
+
+impl Named for LevenshteinMatcher {
+    fn get_name(&self) -> &str {
+        &self.name[..]
+    }
+}
+
+impl Clean for LevenshteinMatcher {}
+
+impl Weighted for LevenshteinMatcher {
+    fn get_weight(&self) -> f64 {
+        let weight = self.weight.read().unwrap();
+        *weight
+    }
+
+    fn set_weight(&mut self, weight: f64) {
+        let mut weight_ptr = self.weight.write().unwrap();
+        *weight_ptr = weight;
+    }
+}
+
+impl Matcher for LevenshteinMatcher {
+    fn get_score(&self, s1: &str, s2: &str) -> f64 {
+        let s1 = self.clean(s1);
+        let s2 = self.clean(s2);
+        compute::levenshtein_score(&s1[..], &s2[..])
+    }
+
+    // At least the difference in length must be edited.
+    fn get_upper_bound(&self, s1: &str, s2: &str) -> f64 {
+        let len1 = self.clean(s1).chars().count();
+        let len2 = self.clean(s2).chars().count();
+        length_bound(len1, len2)
+    }
+
+    fn get_prepared_score(&self, p1: &PreparedName, p2: &PreparedName) -> f64 {
+        compute::levenshtein_score_chars(p1.chars(), p2.chars())
+    }
+
+    fn get_prepared_upper_bound(&self, p1: &PreparedName, p2: &PreparedName) -> f64 {
+        length_bound(p1.chars().len(), p2.chars().len())
+    }
+
+    fn prepare_query(&self, query: &str) -> Box<dyn QueryScorer + '_> {
+        let query = PreparedName::new(query);
+        let pattern = LevenshteinPattern::new(query.chars());
+        Box::new(LevenshteinQuery {
+            matcher: self,
+            query,
+            pattern,
+        })
+    }
+}
+
+// LevenshteinQuery keeps the bit masks of the cleaned query.
+struct LevenshteinQuery<'a> {
+    matcher: &'a LevenshteinMatcher,
+    query: PreparedName,
+    pattern: LevenshteinPattern,
+}
+
+impl<'a> QueryScorer for LevenshteinQuery<'a> {
+    fn score(&self, candidate: &PreparedName) -> f64 {
+        let len1 = self.query.chars().len();
+        let len2 = candidate.chars().len();
+        if len1 == 0 || len2 == 0 {
+            return 0.0;
+        }
+        let distance = self.pattern.distance(candidate.chars());
+        1.0 - distance as f64 / len1.max(len2) as f64
+    }
+
+    fn match_result(&self, candidate: &PreparedName) -> MatchResult {
+        let score = self.score(candidate);
+        MatchResult::new(self.matcher.get_name(), self.matcher.get_weight(), score)
+    }
+
+    fn upper_bound(&self, candidate: &PreparedName) -> f64 {
+        length_bound(self.query.chars().len(), candidate.chars().len())
+    }
+}
+
+fn length_bound(len1: usize, len2: usize) -> f64 {
+    if len1 == 0 || len2 == 0 {
+        return 0.0;
+    }
+    len1.min(len2) as f64 / len1.max(len2) as f64
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::super::prelude::*;
+    use super::LevenshteinMatcher;
+
+    #[test]
+    fn test_case_sensitive_match() {
+        let matcher = LevenshteinMatcher::default();
+        let name1 = "john doe";
+        let name2 = "JOHN DOE";
+        assert_eq!(matcher.get_score(name1, name2), 1.0);
+    }
+
+    #[test]
+    fn test_typo() {
+        let matcher = LevenshteinMatcher::default();
+        let name1 = "Jon Doe";
+        let name2 = "John Doe";
+        assert_eq!(matcher.get_score(name1, name2), 0.875);
+    }
+
+    #[test]
+    fn test_upper_bound() {
+        let matcher = LevenshteinMatcher::default();
+        let name1 = "Jon Doe";
+        let name2 = "John Doe";
+        assert_eq!(matcher.get_upper_bound(name1, name2), 0.875);
+    }
+
+    #[test]
+    fn test_prepared_query() {
+        let matcher = LevenshteinMatcher::default();
+        let long_name = "Maria del Carmen de los Angeles Guadalupe Fernandez de la Torre";
+        let query = matcher.prepare_query(long_name);
+        for name in [
+            "Maria Fernandez",
+            "",
+            "Maria del Carmen Fernandez de la Torre",
+        ] {
+            let candidate = PreparedName::new(name);
+            assert_eq!(query.score(&candidate), matcher.get_score(long_name, name));
+        }
+    }
+
+    #[test]
+    fn test_half_weight() {
+        let matcher = LevenshteinMatcher::new(Some(0.5));
+        let name1 = "JOHN DOE";
+        let name2 = "JOHN DOE";
+        assert_eq!(matcher.get_weighted_score(name1, name2), 0.5);
+    }
+}
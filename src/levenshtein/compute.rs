@@ -0,0 +1,172 @@
+use super::bitparallel;
+use std::cmp;
+use std::collections::HashMap;
+
+// levenshtein_distance returns the minimum number of insertions, deletions
+// and substitutions required to turn s1 into s2.
+// Only two rows of the dynamic programming matrix are kept in memory.
+pub fn levenshtein_distance(s1: &str, s2: &str) -> usize {
+    let chars_1: Vec<char> = s1.chars().collect();
+    let chars_2: Vec<char> = s2.chars().collect();
+    levenshtein_distance_chars(&chars_1, &chars_2)
+}
+
+// levenshtein_distance_chars is levenshtein_distance over characters
+// already collected, computed by the bit-parallel kernel.
+pub fn levenshtein_distance_chars(chars_1: &[char], chars_2: &[char]) -> usize {
+    bitparallel::levenshtein_distance(chars_1, chars_2)
+}
+
+// levenshtein_distance_scalar is levenshtein_distance_chars computed row by
+// row. Kept as a reference for the bit-parallel kernel.
+pub fn levenshtein_distance_scalar(chars_1: &[char], chars_2: &[char]) -> usize {
+    let mut prev_row: Vec<usize> = (0..=chars_2.len()).collect();
+    let mut curr_row = vec![0; chars_2.len() + 1];
+    for (i, char_i) in chars_1.iter().enumerate() {
+        curr_row[0] = i + 1;
+        for (j, char_j) in chars_2.iter().enumerate() {
+            let substitution = prev_row[j] + (char_i != char_j) as usize;
+            let deletion = prev_row[j + 1] + 1;
+            let insertion = curr_row[j] + 1;
+            curr_row[j + 1] = cmp::min(substitution, cmp::min(deletion, insertion));
+        }
+        std::mem::swap(&mut prev_row, &mut curr_row);
+    }
+    prev_row[chars_2.len()]
+}
+
+// damerau_levenshtein_distance returns the minimum number of insertions,
+// deletions, substitutions and transpositions of adjacent characters
+// required to turn s1 into s2 (Lowrance-Wagner).
+// Unlike the restricted variant, a substring may be edited more than once,
+// which makes it a metric.
+pub fn damerau_levenshtein_distance(s1: &str, s2: &str) -> usize {
+    let chars_1: Vec<char> = s1.chars().collect();
+    let chars_2: Vec<char> = s2.chars().collect();
+    let length_1 = chars_1.len();
+    let length_2 = chars_2.len();
+    let infinity = length_1 + length_2;
+
+    // Matrix is offset by one row and one column holding infinity.
+    let width = length_2 + 2;
+    let mut matrix = vec![0; (length_1 + 2) * width];
+    matrix[0] = infinity;
+    for i in 0..=length_1 {
+        matrix[(i + 1) * width] = infinity;
+        matrix[(i + 1) * width + 1] = i;
+    }
+    for j in 0..=length_2 {
+        matrix[j + 1] = infinity;
+        matrix[width + j + 1] = j;
+    }
+
+    // Last row in which every character of s1 was seen.
+    let mut last_row: HashMap<char, usize> = HashMap::new();
+    for i in 1..=length_1 {
+        // Last column of the current row where characters matched.
+        let mut last_match_column = 0;
+        for j in 1..=length_2 {
+            let k = *last_row.get(&chars_2[j - 1]).unwrap_or(&0);
+            let l = last_match_column;
+            let cost = if chars_1[i - 1] == chars_2[j - 1] {
+                last_match_column = j;
+                0
+            } else {
+                1
+            };
+            let substitution = matrix[i * width + j] + cost;
+            let insertion = matrix[(i + 1) * width + j] + 1;
+            let deletion = matrix[i * width + j + 1] + 1;
+            let transposition = matrix[k * width + l] + (i - k - 1) + 1 + (j - l - 1);
+            matrix[(i + 1) * width + j + 1] = cmp::min(
+                cmp::min(substitution, insertion),
+                cmp::min(deletion, transposition),
+            );
+        }
+        last_row.insert(chars_1[i - 1], i);
+    }
+    matrix[(length_1 + 1) * width + length_2 + 1]
+}
+
+// normalized_levenshtein_distance returns the Levenshtein distance normalized
+// as 2d / (len1 + len2 + d) (Yujian & Bo), which remains a metric.
+// Range between 0.0 and 1.0.
+pub fn normalized_levenshtein_distance(s1: &str, s2: &str) -> f64 {
+    let distance = levenshtein_distance(s1, s2) as f64;
+    let total = (s1.chars().count() + s2.chars().count()) as f64 + distance;
+    if total == 0.0 {
+        return 0.0;
+    }
+    2.0 * distance / total
+}
+
+// levenshtein_score returns the similarity between string s1 and string s2
+// defined as 1 - d / max(len1, len2).
+// Range between 0.0 and 1.0.
+pub fn levenshtein_score(s1: &str, s2: &str) -> f64 {
+    let chars_1: Vec<char> = s1.chars().collect();
+    let chars_2: Vec<char> = s2.chars().collect();
+    levenshtein_score_chars(&chars_1, &chars_2)
+}
+
+// levenshtein_score_chars is levenshtein_score over characters
+// already collected.
+pub fn levenshtein_score_chars(chars_1: &[char], chars_2: &[char]) -> f64 {
+    if chars_1.is_empty() || chars_2.is_empty() {
+        return 0.0;
+    }
+    let distance = levenshtein_distance_chars(chars_1, chars_2) as f64;
+    1.0 - distance / cmp::max(chars_1.len(), chars_2.len()) as f64
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn test_levenshtein_kitten_sitting() {
+        assert_eq!(super::levenshtein_distance("KITTEN", "SITTING"), 3);
+    }
+
+    #[test]
+    fn test_levenshtein_empty_string() {
+        assert_eq!(super::levenshtein_distance("", "JOHN"), 4);
+        assert_eq!(super::levenshtein_distance("JOHN", ""), 4);
+        assert_eq!(super::levenshtein_distance("", ""), 0);
+    }
+
+    #[test]
+    fn test_levenshtein_transposition() {
+        assert_eq!(super::levenshtein_distance("JOHN", "JHON"), 2);
+    }
+
+    #[test]
+    fn test_damerau_levenshtein_transposition() {
+        assert_eq!(super::damerau_levenshtein_distance("JOHN", "JHON"), 1);
+    }
+
+    #[test]
+    fn test_damerau_levenshtein_unrestricted() {
+        // Restricted edit distance would be 3.
+        assert_eq!(super::damerau_levenshtein_distance("CA", "ABC"), 2);
+    }
+
+    #[test]
+    fn test_damerau_levenshtein_empty_string() {
+        assert_eq!(super::damerau_levenshtein_distance("", "JOHN"), 4);
+        assert_eq!(super::damerau_levenshtein_distance("", ""), 0);
+    }
+
+    #[test]
+    fn test_normalized_levenshtein() {
+        // 2 * 3 / (6 + 7 + 3)
+        let distance = super::normalized_levenshtein_distance("KITTEN", "SITTING");
+        assert!((distance - 0.375).abs() < 0.001);
+        assert_eq!(super::normalized_levenshtein_distance("", ""), 0.0);
+    }
+
+    #[test]
+    fn test_levenshtein_score() {
+        let score = super::levenshtein_score("JOHN", "JON");
+        assert_eq!(score, 0.75);
+        assert_eq!(super::levenshtein_score("", "JON"), 0.0);
+    }
+}
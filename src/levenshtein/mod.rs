@@ -0,0 +1,3 @@
+mod bitparallel;
+pub mod compare;
+pub mod compute;
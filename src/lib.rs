@@ -1,6 +1,21 @@
+pub mod batch;
+pub mod blocking;
+pub mod dedupe;
 pub mod ensemble;
+pub mod index;
+pub mod input;
 pub mod jaccard;
 pub mod jaro;
+pub mod levenshtein;
+pub mod linkage;
+pub mod matrix;
 pub mod prelude;
+pub mod registry;
+#[cfg(feature = "server")]
+pub mod server;
 pub mod shared;
+pub mod smith_waterman;
 pub mod soundex;
+pub mod spec;
+pub mod stream;
+pub mod token;
@@ -0,0 +1,529 @@
+use super::blocking::keys::{self, BlockingKey};
+use super::ensemble::Ensemble;
+use super::prelude::*;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+// Assignment describes how conflicting links are resolved so that every
+// record is linked to at most one counterpart.
+//   - Greedy: Links are accepted best first when both records are free.
+//   - Optimal: Hungarian assignment maximising the total score of links.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum Assignment {
+    Greedy,
+    Optimal,
+}
+
+// Side of the linkage a record belongs to.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+// Link is a pair of records, one from each list, with their aggregated score.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Link {
+    pub left: usize,
+    pub right: usize,
+    pub score: f64,
+}
+
+// Tie reports a record whose best candidates score within tie_tolerance of
+// each other. candidates are ids on the other side, sorted.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Tie {
+    pub side: Side,
+    pub id: usize,
+    pub score: f64,
+    pub candidates: Vec<usize>,
+}
+
+// Linkage is the outcome of linking two lists.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Linkage {
+    pub links: Vec<Link>,
+    pub unmatched_left: Vec<usize>,
+    pub unmatched_right: Vec<usize>,
+    pub ties: Vec<Tie>,
+}
+
+// Linker links records of a left list to records of a right list.
+// Candidate pairs share a blocking key, every pair is compared when
+// blocking is empty. Pairs scoring at least threshold are assigned
+// one-to-one.
+pub struct Linker {
+    pub blocking: Vec<BlockingKey>,
+    pub threshold: f64,
+    pub assignment: Assignment,
+    pub tie_tolerance: f64,
+}
+
+impl Linker {
+    pub fn new(threshold: f64) -> Linker {
+        Linker {
+            blocking: vec![BlockingKey::Soundex, BlockingKey::SortedTokenPrefix(4)],
+            threshold,
+            assignment: Assignment::Greedy,
+            tie_tolerance: 0.0,
+        }
+    }
+
+    // candidate_pairs returns every (left, right) pair sharing a blocking
+    // key, each pair once, sorted.
+    pub fn candidate_pairs(&self, left: &[&str], right: &[&str]) -> Vec<(usize, usize)> {
+        if self.blocking.is_empty() {
+            return (0..left.len())
+                .flat_map(|l| (0..right.len()).map(move |r| (l, r)))
+                .collect();
+        }
+        let right_keys: Vec<Vec<String>> = right
+            .par_iter()
+            .map(|name| keys::blocking_keys(&self.blocking, &self.clean(name)[..]))
+            .collect();
+        let mut blocks: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (id, keys) in right_keys.iter().enumerate() {
+            for key in keys.iter() {
+                blocks.entry(&key[..]).or_default().push(id);
+            }
+        }
+        left.par_iter()
+            .enumerate()
+            .flat_map_iter(|(l, name)| {
+                let mut ids: HashSet<usize> = HashSet::new();
+                for key in keys::blocking_keys(&self.blocking, &self.clean(name)[..]) {
+                    if let Some(block) = blocks.get(&key[..]) {
+                        ids.extend(block.iter());
+                    }
+                }
+                let mut ids: Vec<usize> = ids.into_iter().collect();
+                ids.sort_unstable();
+                ids.into_iter().map(move |r| (l, r))
+            })
+            .collect()
+    }
+
+    // score_pairs scores candidate pairs in parallel and keeps those scoring
+    // at least threshold.
+    pub fn score_pairs(
+        &self,
+        ensemble: &Ensemble,
+        left: &[&str],
+        right: &[&str],
+        pairs: &[(usize, usize)],
+    ) -> Vec<Link> {
+        pairs
+            .par_iter()
+            .filter_map(|(l, r)| {
+                ensemble
+                    .get_aggregated_score_above(left[*l], right[*r], self.threshold)
+                    .map(|score| Link {
+                        left: *l,
+                        right: *r,
+                        score,
+                    })
+            })
+            .collect()
+    }
+
+    // link scores candidate pairs between left and right and assigns them
+    // one-to-one.
+    pub fn link(&self, ensemble: &Ensemble, left: &[&str], right: &[&str]) -> Linkage {
+        let pairs = self.candidate_pairs(left, right);
+        self.link_candidates(ensemble, left, right, &pairs)
+    }
+
+    // link_candidates links left and right using the given candidate pairs
+    // instead of blocking.
+    pub fn link_candidates(
+        &self,
+        ensemble: &Ensemble,
+        left: &[&str],
+        right: &[&str],
+        pairs: &[(usize, usize)],
+    ) -> Linkage {
+        let links = self.score_pairs(ensemble, left, right, pairs);
+        self.link_scored(left.len(), right.len(), links)
+    }
+
+    // link_scored assigns already scored links one-to-one.
+    // left_len and right_len are the sizes of the lists.
+    pub fn link_scored(&self, left_len: usize, right_len: usize, mut links: Vec<Link>) -> Linkage {
+        links.retain(|link| link.score >= self.threshold);
+        links.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then((a.left, a.right).cmp(&(b.left, b.right)))
+        });
+        let ties = self.find_ties(&links);
+        let mut assigned = match self.assignment {
+            Assignment::Greedy => greedy_assignment(&links),
+            Assignment::Optimal => optimal_assignment(&links),
+        };
+        assigned.sort_by_key(|link| (link.left, link.right));
+
+        let mut left_matched = vec![false; left_len];
+        let mut right_matched = vec![false; right_len];
+        for link in assigned.iter() {
+            left_matched[link.left] = true;
+            right_matched[link.right] = true;
+        }
+        Linkage {
+            links: assigned,
+            unmatched_left: unmatched(&left_matched),
+            unmatched_right: unmatched(&right_matched),
+            ties,
+        }
+    }
+
+    // find_ties reports records with several best candidates.
+    // links must be sorted best first.
+    fn find_ties(&self, links: &[Link]) -> Vec<Tie> {
+        let mut ties = Vec::new();
+        for side in [Side::Left, Side::Right] {
+            let mut candidates: HashMap<usize, Vec<&Link>> = HashMap::new();
+            for link in links.iter() {
+                let id = match side {
+                    Side::Left => link.left,
+                    Side::Right => link.right,
+                };
+                candidates.entry(id).or_default().push(link);
+            }
+            for (id, candidates) in candidates.into_iter() {
+                let best = candidates[0].score;
+                let mut tied: Vec<usize> = candidates
+                    .iter()
+                    .filter(|link| best - link.score <= self.tie_tolerance)
+                    .map(|link| match side {
+                        Side::Left => link.right,
+                        Side::Right => link.left,
+                    })
+                    .collect();
+                if tied.len() > 1 {
+                    tied.sort_unstable();
+                    ties.push(Tie {
+                        side,
+                        id,
+                        score: best,
+                        candidates: tied,
+                    });
+                }
+            }
+        }
+        ties.sort_by_key(|tie| (tie.side == Side::Right, tie.id));
+        ties
+    }
+}
+
+impl Clean for Linker {}
+
+fn unmatched(matched: &[bool]) -> Vec<usize> {
+    matched
+        .iter()
+        .enumerate()
+        .filter(|(_, matched)| !**matched)
+        .map(|(id, _)| id)
+        .collect()
+}
+
+// greedy_assignment accepts links best first when both records are free.
+// links must be sorted best first.
+fn greedy_assignment(links: &[Link]) -> Vec<Link> {
+    let mut left_used: HashSet<usize> = HashSet::new();
+    let mut right_used: HashSet<usize> = HashSet::new();
+    let mut assigned = Vec::new();
+    for link in links.iter() {
+        if !left_used.contains(&link.left) && !right_used.contains(&link.right) {
+            left_used.insert(link.left);
+            right_used.insert(link.right);
+            assigned.push(link.clone());
+        }
+    }
+    assigned
+}
+
+// optimal_assignment maximises the total score of assigned links.
+// The bipartite graph is split in connected components, each solved
+// independently with the Hungarian algorithm.
+fn optimal_assignment(links: &[Link]) -> Vec<Link> {
+    let mut adjacency: HashMap<(Side, usize), Vec<usize>> = HashMap::new();
+    for (i, link) in links.iter().enumerate() {
+        adjacency
+            .entry((Side::Left, link.left))
+            .or_default()
+            .push(i);
+        adjacency
+            .entry((Side::Right, link.right))
+            .or_default()
+            .push(i);
+    }
+
+    // Group links by connected component.
+    let mut visited = vec![false; links.len()];
+    let mut components: Vec<Vec<&Link>> = Vec::new();
+    for start in 0..links.len() {
+        if visited[start] {
+            continue;
+        }
+        visited[start] = true;
+        let mut stack = vec![start];
+        let mut component = Vec::new();
+        while let Some(i) = stack.pop() {
+            let link = &links[i];
+            component.push(link);
+            for key in [(Side::Left, link.left), (Side::Right, link.right)] {
+                for next in adjacency[&key].iter() {
+                    if !visited[*next] {
+                        visited[*next] = true;
+                        stack.push(*next);
+                    }
+                }
+            }
+        }
+        components.push(component);
+    }
+
+    components
+        .par_iter()
+        .flat_map_iter(|component| solve_component(component))
+        .collect()
+}
+
+fn solve_component(links: &[&Link]) -> Vec<Link> {
+    if links.len() == 1 {
+        return vec![links[0].clone()];
+    }
+    let mut rows: Vec<usize> = links.iter().map(|link| link.left).collect();
+    let mut cols: Vec<usize> = links.iter().map(|link| link.right).collect();
+    rows.sort_unstable();
+    rows.dedup();
+    cols.sort_unstable();
+    cols.dedup();
+    // The Hungarian algorithm needs no more rows than columns.
+    let transposed = rows.len() > cols.len();
+    if transposed {
+        std::mem::swap(&mut rows, &mut cols);
+    }
+
+    // Costs are negated scores, missing links cost nothing.
+    let mut scores = vec![vec![None; cols.len()]; rows.len()];
+    for link in links.iter() {
+        let (row, col) = if transposed {
+            (link.right, link.left)
+        } else {
+            (link.left, link.right)
+        };
+        let i = rows.binary_search(&row).unwrap();
+        let j = cols.binary_search(&col).unwrap();
+        scores[i][j] = Some(link.score);
+    }
+    let costs: Vec<Vec<f64>> = scores
+        .iter()
+        .map(|row| row.iter().map(|score| -score.unwrap_or(0.0)).collect())
+        .collect();
+
+    hungarian(&costs)
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, j)| {
+            scores[i][j].map(|score| {
+                let (left, right) = if transposed {
+                    (cols[j], rows[i])
+                } else {
+                    (rows[i], cols[j])
+                };
+                Link { left, right, score }
+            })
+        })
+        .collect()
+}
+
+// hungarian returns the column assigned to every row minimising the total
+// cost. costs must have no more rows than columns.
+fn hungarian(costs: &[Vec<f64>]) -> Vec<usize> {
+    let n = costs.len();
+    let m = costs[0].len();
+    // Potentials and matching are 1-indexed, index 0 is a sentinel.
+    let mut u = vec![0.0; n + 1];
+    let mut v = vec![0.0; m + 1];
+    let mut matched_row = vec![0; m + 1];
+    let mut way = vec![0; m + 1];
+    for i in 1..=n {
+        matched_row[0] = i;
+        let mut j0 = 0;
+        let mut min_v = vec![f64::INFINITY; m + 1];
+        let mut used = vec![false; m + 1];
+        loop {
+            used[j0] = true;
+            let i0 = matched_row[j0];
+            let mut delta = f64::INFINITY;
+            let mut j1 = 0;
+            for j in 1..=m {
+                if !used[j] {
+                    let current = costs[i0 - 1][j - 1] - u[i0] - v[j];
+                    if current < min_v[j] {
+                        min_v[j] = current;
+                        way[j] = j0;
+                    }
+                    if min_v[j] < delta {
+                        delta = min_v[j];
+                        j1 = j;
+                    }
+                }
+            }
+            for j in 0..=m {
+                if used[j] {
+                    u[matched_row[j]] += delta;
+                    v[j] -= delta;
+                } else {
+                    min_v[j] -= delta;
+                }
+            }
+            j0 = j1;
+            if matched_row[j0] == 0 {
+                break;
+            }
+        }
+        loop {
+            let j1 = way[j0];
+            matched_row[j0] = matched_row[j1];
+            j0 = j1;
+            if j0 == 0 {
+                break;
+            }
+        }
+    }
+
+    let mut assignment = vec![0; n];
+    for j in 1..=m {
+        if matched_row[j] != 0 {
+            assignment[matched_row[j] - 1] = j - 1;
+        }
+    }
+    assignment
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::ensemble::Ensemble;
+    use super::super::jaro::compare::JaroWinklerMatcher;
+    use super::super::prelude::*;
+    use super::{hungarian, Assignment, Link, Linker, Side};
+
+    fn build_ensemble() -> Ensemble {
+        let matchers: Vec<Box<dyn Matcher>> = vec![Box::new(JaroWinklerMatcher::default())];
+        Ensemble::new(matchers)
+    }
+
+    fn link(left: usize, right: usize, score: f64) -> Link {
+        Link { left, right, score }
+    }
+
+    // Greedy takes (0, 0) first and leaves left 1 unmatched,
+    // optimal links both records.
+    fn conflicting_links() -> Vec<Link> {
+        vec![link(0, 0, 0.95), link(0, 1, 0.9), link(1, 0, 0.92)]
+    }
+
+    #[test]
+    fn test_candidate_pairs() {
+        let linker = Linker::new(0.9);
+        let left = vec!["John Doe", "Mary Smith"];
+        let right = vec!["Peter Parker", "Jon Doe", "Mary Smyth"];
+        assert_eq!(linker.candidate_pairs(&left, &right), vec![(0, 1), (1, 2)]);
+    }
+
+    #[test]
+    fn test_candidate_pairs_without_blocking() {
+        let mut linker = Linker::new(0.9);
+        linker.blocking = Vec::new();
+        let left = vec!["John Doe", "Mary Smith"];
+        let right = vec!["Peter Parker", "Jon Doe"];
+        assert_eq!(
+            linker.candidate_pairs(&left, &right),
+            vec![(0, 0), (0, 1), (1, 0), (1, 1)]
+        );
+    }
+
+    #[test]
+    fn test_link() {
+        let linker = Linker::new(0.9);
+        let left = vec!["John Doe", "Mary Smith", "Bruce Wayne"];
+        let right = vec!["Peter Parker", "Jon Doe", "Mary Smyth"];
+        let linkage = linker.link(&build_ensemble(), &left, &right);
+        let pairs: Vec<(usize, usize)> = linkage
+            .links
+            .iter()
+            .map(|link| (link.left, link.right))
+            .collect();
+        assert_eq!(pairs, vec![(0, 1), (1, 2)]);
+        assert_eq!(linkage.unmatched_left, vec![2]);
+        assert_eq!(linkage.unmatched_right, vec![0]);
+        assert!(linkage.ties.is_empty());
+    }
+
+    #[test]
+    fn test_greedy_assignment() {
+        let linker = Linker::new(0.9);
+        let linkage = linker.link_scored(2, 2, conflicting_links());
+        assert_eq!(linkage.links, vec![link(0, 0, 0.95)]);
+        assert_eq!(linkage.unmatched_left, vec![1]);
+        assert_eq!(linkage.unmatched_right, vec![1]);
+    }
+
+    #[test]
+    fn test_optimal_assignment() {
+        let mut linker = Linker::new(0.9);
+        linker.assignment = Assignment::Optimal;
+        let linkage = linker.link_scored(2, 2, conflicting_links());
+        assert_eq!(linkage.links, vec![link(0, 1, 0.9), link(1, 0, 0.92)]);
+        assert!(linkage.unmatched_left.is_empty());
+        assert!(linkage.unmatched_right.is_empty());
+    }
+
+    #[test]
+    fn test_optimal_assignment_more_left_records() {
+        let mut linker = Linker::new(0.5);
+        linker.assignment = Assignment::Optimal;
+        let links = vec![link(0, 0, 0.9), link(1, 0, 0.8), link(2, 0, 0.95)];
+        let linkage = linker.link_scored(3, 1, links);
+        assert_eq!(linkage.links, vec![link(2, 0, 0.95)]);
+        assert_eq!(linkage.unmatched_left, vec![0, 1]);
+    }
+
+    #[test]
+    fn test_threshold() {
+        let linker = Linker::new(0.93);
+        let linkage = linker.link_scored(2, 2, conflicting_links());
+        assert_eq!(linkage.links, vec![link(0, 0, 0.95)]);
+    }
+
+    #[test]
+    fn test_ties() {
+        let mut linker = Linker::new(0.9);
+        let links = vec![link(0, 0, 0.95), link(0, 1, 0.95), link(1, 1, 0.91)];
+        let linkage = linker.link_scored(2, 2, links.clone());
+        assert_eq!(linkage.ties.len(), 1);
+        assert_eq!(linkage.ties[0].side, Side::Left);
+        assert_eq!(linkage.ties[0].id, 0);
+        assert_eq!(linkage.ties[0].candidates, vec![0, 1]);
+
+        // Right 1 is tied between left 0 and left 1 within 0.05.
+        linker.tie_tolerance = 0.05;
+        let linkage = linker.link_scored(2, 2, links);
+        assert_eq!(linkage.ties.len(), 2);
+        assert_eq!(linkage.ties[1].side, Side::Right);
+        assert_eq!(linkage.ties[1].candidates, vec![0, 1]);
+    }
+
+    #[test]
+    fn test_hungarian() {
+        let costs = vec![
+            vec![4.0, 1.0, 3.0],
+            vec![2.0, 0.0, 5.0],
+            vec![3.0, 2.0, 2.0],
+        ];
+        assert_eq!(hungarian(&costs), vec![1, 0, 2]);
+    }
+}
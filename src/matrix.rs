@@ -0,0 +1,386 @@
+use super::ensemble::Ensemble;
+use super::prelude::*;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+// MatrixScorer is implemented by what similarity matrices are computed from.
+//   - Matcher: Score of the matcher, not weighted.
+//   - Ensemble: Aggregated score of its matchers.
+pub trait MatrixScorer: Sync {
+    // prepare_row returns a function scoring the row name against
+    // column names.
+    fn prepare_row<'a>(&'a self, name: &str) -> Box<dyn Fn(&PreparedName) -> f64 + 'a>;
+}
+
+impl<M: Matcher + ?Sized> MatrixScorer for M {
+    fn prepare_row<'a>(&'a self, name: &str) -> Box<dyn Fn(&PreparedName) -> f64 + 'a> {
+        let scorer = self.prepare_query(name);
+        Box::new(move |candidate| scorer.score(candidate))
+    }
+}
+
+impl MatrixScorer for Ensemble {
+    fn prepare_row<'a>(&'a self, name: &str) -> Box<dyn Fn(&PreparedName) -> f64 + 'a> {
+        let query = self.prepare_query(name);
+        Box::new(move |candidate| query.get_aggregated_score(candidate))
+    }
+}
+
+// DenseMatrix holds every score between rows and columns, row-major.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DenseMatrix {
+    pub rows: usize,
+    pub cols: usize,
+    pub scores: Vec<f64>,
+}
+
+impl DenseMatrix {
+    pub fn get(&self, row: usize, col: usize) -> f64 {
+        self.scores[row * self.cols + col]
+    }
+
+    pub fn row(&self, row: usize) -> &[f64] {
+        &self.scores[row * self.cols..(row + 1) * self.cols]
+    }
+}
+
+// CooMatrix holds scores as (row, col, score) triplets, in coordinate form.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CooMatrix {
+    pub rows: usize,
+    pub cols: usize,
+    pub row_ids: Vec<usize>,
+    pub col_ids: Vec<usize>,
+    pub scores: Vec<f64>,
+}
+
+impl CooMatrix {
+    pub fn len(&self) -> usize {
+        self.scores.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+
+    // to_csr sorts triplets by row then column. Duplicated coordinates
+    // are kept.
+    pub fn to_csr(&self) -> CsrMatrix {
+        let mut order: Vec<usize> = (0..self.len()).collect();
+        order.sort_by_key(|i| (self.row_ids[*i], self.col_ids[*i]));
+        let mut row_offsets = vec![0; self.rows + 1];
+        for row in self.row_ids.iter() {
+            row_offsets[row + 1] += 1;
+        }
+        for row in 0..self.rows {
+            row_offsets[row + 1] += row_offsets[row];
+        }
+        CsrMatrix {
+            rows: self.rows,
+            cols: self.cols,
+            row_offsets,
+            col_ids: order.iter().map(|i| self.col_ids[*i]).collect(),
+            scores: order.iter().map(|i| self.scores[*i]).collect(),
+        }
+    }
+}
+
+// CsrMatrix holds scores in compressed sparse row form. Columns of row i
+// are col_ids[row_offsets[i]..row_offsets[i + 1]], sorted.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CsrMatrix {
+    pub rows: usize,
+    pub cols: usize,
+    pub row_offsets: Vec<usize>,
+    pub col_ids: Vec<usize>,
+    pub scores: Vec<f64>,
+}
+
+impl CsrMatrix {
+    pub fn len(&self) -> usize {
+        self.scores.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+
+    // row returns the columns stored for row and their scores.
+    pub fn row(&self, row: usize) -> (&[usize], &[f64]) {
+        let range = self.row_offsets[row]..self.row_offsets[row + 1];
+        (&self.col_ids[range.clone()], &self.scores[range])
+    }
+
+    // get returns the score stored at row and col, None when it was not
+    // stored.
+    pub fn get(&self, row: usize, col: usize) -> Option<f64> {
+        let (col_ids, scores) = self.row(row);
+        let position = col_ids.binary_search(&col).ok()?;
+        Some(scores[position])
+    }
+
+    pub fn to_coo(&self) -> CooMatrix {
+        let mut row_ids = Vec::with_capacity(self.len());
+        for row in 0..self.rows {
+            let count = self.row_offsets[row + 1] - self.row_offsets[row];
+            row_ids.extend(std::iter::repeat_n(row, count));
+        }
+        CooMatrix {
+            rows: self.rows,
+            cols: self.cols,
+            row_ids,
+            col_ids: self.col_ids.clone(),
+            scores: self.scores.clone(),
+        }
+    }
+}
+
+// SimilarityMatrix computes the scores between every row name and every
+// column name. Rows are split in blocks of tile_rows names scored in
+// parallel, and each block is scored against tile_cols columns at a time
+// so that prepared columns stay in cache while every row of the block
+// visits them. Row names are prepared once per block, column names once.
+pub struct SimilarityMatrix {
+    pub tile_rows: usize,
+    pub tile_cols: usize,
+}
+
+impl Default for SimilarityMatrix {
+    fn default() -> SimilarityMatrix {
+        SimilarityMatrix::new()
+    }
+}
+
+impl SimilarityMatrix {
+    pub fn new() -> SimilarityMatrix {
+        SimilarityMatrix {
+            tile_rows: 16,
+            tile_cols: 256,
+        }
+    }
+
+    // dense returns the rows × cols matrix of scores.
+    pub fn dense<S: MatrixScorer + ?Sized>(
+        &self,
+        scorer: &S,
+        rows: &[&str],
+        cols: &[&str],
+    ) -> DenseMatrix {
+        let columns = prepare_columns(cols);
+        let mut scores = vec![0.0; rows.len() * cols.len()];
+        if !cols.is_empty() {
+            self.score_rows(scorer, rows, &columns, &mut scores);
+        }
+        DenseMatrix {
+            rows: rows.len(),
+            cols: cols.len(),
+            scores,
+        }
+    }
+
+    // for_each_row calls f with every row index and its scores, in order.
+    // Rows are computed in parallel batches, only one batch is held in
+    // memory at a time.
+    pub fn for_each_row<S, F>(&self, scorer: &S, rows: &[&str], cols: &[&str], mut f: F)
+    where
+        S: MatrixScorer + ?Sized,
+        F: FnMut(usize, &[f64]),
+    {
+        let columns = prepare_columns(cols);
+        let batch_rows = self.tile_rows.max(1) * rayon::current_num_threads();
+        let mut scores = vec![0.0; batch_rows * cols.len()];
+        for (batch, batch_names) in rows.chunks(batch_rows).enumerate() {
+            let scores = &mut scores[..batch_names.len() * cols.len()];
+            if !cols.is_empty() {
+                self.score_rows(scorer, batch_names, &columns, scores);
+            }
+            for i in 0..batch_names.len() {
+                f(
+                    batch * batch_rows + i,
+                    &scores[i * cols.len()..(i + 1) * cols.len()],
+                );
+            }
+        }
+    }
+
+    // sparse returns the scores that are at least threshold, the full
+    // matrix is never held in memory.
+    pub fn sparse<S: MatrixScorer + ?Sized>(
+        &self,
+        scorer: &S,
+        rows: &[&str],
+        cols: &[&str],
+        threshold: f64,
+    ) -> CsrMatrix {
+        let columns = prepare_columns(cols);
+        let tile_rows = self.tile_rows.max(1);
+        let blocks: Vec<Vec<Vec<(usize, f64)>>> = rows
+            .par_chunks(tile_rows)
+            .map(|names| {
+                let mut entries = vec![Vec::new(); names.len()];
+                self.visit_block(scorer, names, &columns, |row, col, score| {
+                    if score >= threshold {
+                        entries[row].push((col, score));
+                    }
+                });
+                entries
+            })
+            .collect();
+
+        let mut row_offsets = vec![0];
+        let mut col_ids = Vec::new();
+        let mut scores = Vec::new();
+        for entries in blocks.into_iter().flatten() {
+            for (col, score) in entries {
+                col_ids.push(col);
+                scores.push(score);
+            }
+            row_offsets.push(col_ids.len());
+        }
+        CsrMatrix {
+            rows: rows.len(),
+            cols: cols.len(),
+            row_offsets,
+            col_ids,
+            scores,
+        }
+    }
+
+    // score_rows writes the scores of names, row-major, into scores.
+    fn score_rows<S: MatrixScorer + ?Sized>(
+        &self,
+        scorer: &S,
+        names: &[&str],
+        columns: &[PreparedName],
+        scores: &mut [f64],
+    ) {
+        let tile_rows = self.tile_rows.max(1);
+        scores
+            .par_chunks_mut(tile_rows * columns.len())
+            .zip(names.par_chunks(tile_rows))
+            .for_each(|(block_scores, block_names)| {
+                self.visit_block(scorer, block_names, columns, |row, col, score| {
+                    block_scores[row * columns.len() + col] = score;
+                });
+            });
+    }
+
+    // visit_block scores a block of row names against every column, one
+    // tile of columns at a time. Within a row, columns are visited in order.
+    fn visit_block<S, F>(&self, scorer: &S, names: &[&str], columns: &[PreparedName], mut f: F)
+    where
+        S: MatrixScorer + ?Sized,
+        F: FnMut(usize, usize, f64),
+    {
+        let row_scorers: Vec<_> = names.iter().map(|name| scorer.prepare_row(name)).collect();
+        let tile_cols = self.tile_cols.max(1);
+        for tile_start in (0..columns.len()).step_by(tile_cols) {
+            let tile = &columns[tile_start..(tile_start + tile_cols).min(columns.len())];
+            for (row, row_scorer) in row_scorers.iter().enumerate() {
+                for (offset, column) in tile.iter().enumerate() {
+                    f(row, tile_start + offset, row_scorer(column));
+                }
+            }
+        }
+    }
+}
+
+fn prepare_columns(cols: &[&str]) -> Vec<PreparedName> {
+    cols.par_iter()
+        .map(|name| PreparedName::new(name))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::ensemble::Ensemble;
+    use super::super::jaro::compare::JaroWinklerMatcher;
+    use super::super::prelude::*;
+    use super::super::soundex::compare::SoundexMatcher;
+    use super::SimilarityMatrix;
+
+    const ROWS: [&str; 5] = ["John Doe", "Jane Doe", "Mary Smith", "Peter Parker", ""];
+    const COLS: [&str; 4] = ["Jon Doe", "Mary Smyth", "Doe John", "Pete Parker"];
+
+    // Small tiles so that every test crosses block and tile boundaries.
+    fn small_tiles() -> SimilarityMatrix {
+        let mut matrix = SimilarityMatrix::new();
+        matrix.tile_rows = 2;
+        matrix.tile_cols = 3;
+        matrix
+    }
+
+    #[test]
+    fn test_dense_matcher() {
+        let matcher = JaroWinklerMatcher::default();
+        let matrix = small_tiles().dense(&matcher, &ROWS, &COLS);
+        assert_eq!((matrix.rows, matrix.cols), (5, 4));
+        for (i, row) in ROWS.iter().enumerate() {
+            for (j, col) in COLS.iter().enumerate() {
+                assert_eq!(matrix.get(i, j), matcher.get_score(row, col));
+            }
+        }
+    }
+
+    #[test]
+    fn test_dense_ensemble() {
+        let matchers: Vec<Box<dyn Matcher>> = vec![
+            Box::new(JaroWinklerMatcher::default()),
+            Box::new(SoundexMatcher::default()),
+        ];
+        let mut ensemble = Ensemble::new(matchers);
+        ensemble.set_equal_weight();
+        let matrix = small_tiles().dense(&ensemble, &ROWS, &COLS);
+        for (i, row) in ROWS.iter().enumerate() {
+            for (j, col) in COLS.iter().enumerate() {
+                assert_eq!(matrix.get(i, j), ensemble.get_aggregated_score(row, col));
+            }
+        }
+    }
+
+    #[test]
+    fn test_for_each_row() {
+        let matcher = JaroWinklerMatcher::default();
+        let matrix = small_tiles();
+        let dense = matrix.dense(&matcher, &ROWS, &COLS);
+        let mut visited = Vec::new();
+        matrix.for_each_row(&matcher, &ROWS, &COLS, |row, scores| {
+            assert_eq!(scores, dense.row(row));
+            visited.push(row);
+        });
+        assert_eq!(visited, vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_sparse() {
+        let matcher = JaroWinklerMatcher::default();
+        let matrix = small_tiles();
+        let dense = matrix.dense(&matcher, &ROWS, &COLS);
+        let sparse = matrix.sparse(&matcher, &ROWS, &COLS, 0.8);
+        let expected = dense.scores.iter().filter(|score| **score >= 0.8).count();
+        assert_eq!(sparse.len(), expected);
+        for i in 0..ROWS.len() {
+            for j in 0..COLS.len() {
+                let score = dense.get(i, j);
+                let stored = if score >= 0.8 { Some(score) } else { None };
+                assert_eq!(sparse.get(i, j), stored);
+            }
+        }
+        assert_eq!(sparse.to_coo().to_csr(), sparse);
+    }
+
+    #[test]
+    fn test_empty_columns() {
+        let matcher = JaroWinklerMatcher::default();
+        let matrix = small_tiles();
+        assert!(matrix.dense(&matcher, &ROWS, &[]).scores.is_empty());
+        let sparse = matrix.sparse(&matcher, &ROWS, &[], 0.5);
+        assert_eq!(sparse.row_offsets, vec![0; 6]);
+        let mut rows = 0;
+        matrix.for_each_row(&matcher, &ROWS, &[], |_, scores| {
+            assert!(scores.is_empty());
+            rows += 1;
+        });
+        assert_eq!(rows, 5);
+    }
+}
@@ -1,14 +1,22 @@
+pub use super::shared::prepared::PreparedName;
+pub use super::spec::{MatcherSpec, ParamValue, SpecError};
 use serde::{Deserialize, Serialize};
 use std::marker::{Send, Sync};
 
 // MatcherRule represents the matching result
 // in greated detail
+// ordering is only reported by matchers that search over name orderings,
+// it holds the reordered name that produced the best score.
+// Build results with new, as more fields may be added.
 #[derive(Serialize, Deserialize, Debug)]
+#[non_exhaustive]
 pub struct MatchResult {
     pub algorithm: String,
     pub weight: f64,
     pub absolute_score: f64,
     pub weighted_score: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ordering: Option<String>,
 }
 
 impl MatchResult {
@@ -18,8 +26,14 @@ impl MatchResult {
             weight,
             absolute_score: score,
             weighted_score: score * weight,
+            ordering: None,
         }
     }
+
+    pub fn with_ordering(mut self, ordering: String) -> MatchResult {
+        self.ordering = Some(ordering);
+        self
+    }
 }
 
 // Clean trait handles string preprocessing before comparison can take place.
@@ -42,6 +56,23 @@ pub trait Weighted: Send + Sync {
     fn set_weight(&mut self, weight: f64);
 }
 
+// Tokenization describes how token based matchers split names into tokens.
+//   - Whitespace: Tokens are separated by whitespace.
+//   - Segmented: Adjacent tokens may be merged and long tokens may be split
+//     against the other name's tokens, eg "MARY ANN" vs "MARYANN".
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum Tokenization {
+    Whitespace,
+    Segmented,
+}
+
+// Tokenized trait exposes the tokenization mode of token based matchers
+pub trait Tokenized {
+    fn get_tokenization(&self) -> Tokenization;
+
+    fn set_tokenization(&mut self, tokenization: Tokenization);
+}
+
 // Named trait exposes name attribute of concrete type
 pub trait Named {
     fn get_name(&self) -> &str;
@@ -63,4 +94,78 @@ pub trait Matcher: Clean + Weighted + Named {
         let score = self.get_score(s1, s2);
         MatchResult::new(self.get_name(), self.get_weight(), score)
     }
+
+    // get_upper_bound returns a cheap upper bound of get_score(s1, s2),
+    // used to skip matchers that can no longer change the outcome.
+    // Defaults to 1.0, matchers override it when a tighter bound is cheap.
+    fn get_upper_bound(&self, _s1: &str, _s2: &str) -> f64 {
+        1.0
+    }
+
+    // get_prepared_score returns the same score as get_score, computed from
+    // prepared names. Matchers override it to reuse the cleaned and derived
+    // forms cached by PreparedName.
+    fn get_prepared_score(&self, p1: &PreparedName, p2: &PreparedName) -> f64 {
+        self.get_score(p1.raw(), p2.raw())
+    }
+
+    fn get_prepared_match_result(&self, p1: &PreparedName, p2: &PreparedName) -> MatchResult {
+        let score = self.get_prepared_score(p1, p2);
+        MatchResult::new(self.get_name(), self.get_weight(), score)
+    }
+
+    fn get_prepared_upper_bound(&self, p1: &PreparedName, p2: &PreparedName) -> f64 {
+        self.get_upper_bound(p1.raw(), p2.raw())
+    }
+
+    // get_spec describes the matcher, its weight and parameters, so that
+    // MatcherSpec::build returns an equivalent matcher.
+    // Defaults to the name and weight, matchers with parameters override it.
+    fn get_spec(&self) -> MatcherSpec {
+        MatcherSpec::new(self.get_name()).with_weight(self.get_weight())
+    }
+
+    // prepare_query returns a scorer comparing a fixed query against many
+    // candidates. Matchers override it to compute state derived from the
+    // query once, eg the bit masks of Jaro-Winkler.
+    // Defaults to scoring prepared names.
+    fn prepare_query(&self, query: &str) -> Box<dyn QueryScorer + '_> {
+        Box::new(PreparedQueryScorer {
+            matcher: self,
+            query: PreparedName::new(query),
+        })
+    }
+}
+
+// QueryScorer compares a fixed query against candidates, returning the same
+// scores, match results and upper bounds as the Matcher it was prepared by.
+pub trait QueryScorer: Send + Sync {
+    fn score(&self, candidate: &PreparedName) -> f64;
+
+    fn match_result(&self, candidate: &PreparedName) -> MatchResult;
+
+    fn upper_bound(&self, candidate: &PreparedName) -> f64;
+}
+
+// PreparedQueryScorer is the default QueryScorer, it keeps the prepared
+// query so that its cleaned and derived forms are computed once.
+pub struct PreparedQueryScorer<'a, M: Matcher + ?Sized> {
+    pub matcher: &'a M,
+    pub query: PreparedName,
+}
+
+impl<'a, M: Matcher + ?Sized> QueryScorer for PreparedQueryScorer<'a, M> {
+    fn score(&self, candidate: &PreparedName) -> f64 {
+        self.matcher.get_prepared_score(&self.query, candidate)
+    }
+
+    fn match_result(&self, candidate: &PreparedName) -> MatchResult {
+        self.matcher
+            .get_prepared_match_result(&self.query, candidate)
+    }
+
+    fn upper_bound(&self, candidate: &PreparedName) -> f64 {
+        self.matcher
+            .get_prepared_upper_bound(&self.query, candidate)
+    }
 }
@@ -0,0 +1,328 @@
+use super::ensemble::Ensemble;
+use super::jaccard::compare::JaccardMatcher;
+use super::jaro::compare::JaroWinklerMatcher;
+use super::levenshtein::compare::LevenshteinMatcher;
+use super::prelude::*;
+use super::smith_waterman::compare::SmithWatermanMatcher;
+use super::soundex::compare::{SoundexJaccardMatcher, SoundexMatcher};
+use super::spec::{normalize, EnsembleSpec, Params};
+use super::token::compare::{PartialMatcher, TokenSetMatcher, TokenSortMatcher};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+type MatcherFactory = dyn Fn(&MatcherSpec) -> Result<Box<dyn Matcher>, SpecError> + Send + Sync;
+
+type WrapperFactory =
+    dyn Fn(&MatcherSpec, Box<dyn Matcher>) -> Result<Box<dyn Matcher>, SpecError> + Send + Sync;
+
+// Factory builds a matcher from its spec, wrappers also take the matcher
+// they wrap.
+enum Factory {
+    Matcher(Box<MatcherFactory>),
+    Wrapper(Box<WrapperFactory>),
+}
+
+// MatcherRegistry constructs matchers by name at runtime.
+// Built-in matchers are registered under the names returned by get_name,
+// eg "Jaro-Winkler", and token wrappers under "Token-Sort", "Token-Set"
+// and "Partial". Names are case insensitive and punctuation is ignored,
+// so jaro-winkler, JaroWinkler and Jaro-Winkler are the same matcher.
+// Factories read their parameters from the params of the MatcherSpec they
+// are given, the weight of the spec is applied to the matcher they return.
+pub struct MatcherRegistry {
+    factories: HashMap<String, (String, Factory)>,
+}
+
+impl Default for MatcherRegistry {
+    fn default() -> MatcherRegistry {
+        MatcherRegistry::new()
+    }
+}
+
+impl MatcherRegistry {
+    // new returns a registry holding every built-in matcher.
+    pub fn new() -> MatcherRegistry {
+        let mut registry = MatcherRegistry::empty();
+        registry.register("Jaro-Winkler", |spec| {
+            Ok(Box::new(JaroWinklerMatcher::from_spec(spec)?))
+        });
+        registry.register("Jaccard", |spec| {
+            Ok(Box::new(JaccardMatcher::from_spec(spec)?))
+        });
+        registry.register("Soundex", |spec| {
+            Ok(Box::new(SoundexMatcher::from_spec(spec)?))
+        });
+        registry.register("Soundex-Jaccard", |spec| {
+            Ok(Box::new(SoundexJaccardMatcher::from_spec(spec)?))
+        });
+        registry.register("Smith-Waterman", |spec| {
+            Ok(Box::new(SmithWatermanMatcher::from_spec(spec)?))
+        });
+        registry.register("Levenshtein", |spec| {
+            Ok(Box::new(LevenshteinMatcher::from_spec(spec)?))
+        });
+        registry.register_wrapper("Token-Sort", |spec, matcher| {
+            Ok(Box::new(TokenSortMatcher::from_spec(spec, matcher)?))
+        });
+        registry.register_wrapper("Token-Set", |spec, matcher| {
+            Ok(Box::new(TokenSetMatcher::from_spec(spec, matcher)?))
+        });
+        registry.register_wrapper("Partial", |spec, matcher| {
+            Ok(Box::new(PartialMatcher::from_spec(spec, matcher)?))
+        });
+        registry
+    }
+
+    // empty returns a registry without any matcher.
+    pub fn empty() -> MatcherRegistry {
+        MatcherRegistry {
+            factories: HashMap::new(),
+        }
+    }
+
+    // builtin returns a shared registry holding every built-in matcher,
+    // used by MatcherSpec::build and Ensemble::from_spec.
+    pub fn builtin() -> &'static MatcherRegistry {
+        static BUILTIN: OnceLock<MatcherRegistry> = OnceLock::new();
+        BUILTIN.get_or_init(MatcherRegistry::new)
+    }
+
+    // register adds a matcher under name, replacing any matcher
+    // registered under the same name.
+    pub fn register<F>(&mut self, name: &str, factory: F)
+    where
+        F: Fn(&MatcherSpec) -> Result<Box<dyn Matcher>, SpecError> + Send + Sync + 'static,
+    {
+        self.insert(name, Factory::Matcher(Box::new(factory)));
+    }
+
+    // register_wrapper adds a matcher wrapping another matcher, such as
+    // Token-Sort, under name.
+    pub fn register_wrapper<F>(&mut self, name: &str, factory: F)
+    where
+        F: Fn(&MatcherSpec, Box<dyn Matcher>) -> Result<Box<dyn Matcher>, SpecError>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.insert(name, Factory::Wrapper(Box::new(factory)));
+    }
+
+    fn insert(&mut self, name: &str, factory: Factory) {
+        self.factories
+            .insert(normalize(name), (name.to_owned(), factory));
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.factories.contains_key(&normalize(name))
+    }
+
+    // names returns the registered names, sorted.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.factories.values().map(|(name, _)| &name[..]).collect();
+        names.sort_unstable();
+        names
+    }
+
+    // get returns the matcher registered under name, built with params.
+    // Wrappers take the wrapped matcher in parentheses,
+    // eg "Token-Sort(Jaro-Winkler)".
+    pub fn get(&self, name: &str, params: &Params) -> Result<Box<dyn Matcher>, SpecError> {
+        let mut spec = MatcherSpec::new(name);
+        spec.params = params.clone();
+        self.build(&spec)
+    }
+
+    // build returns the matcher described by spec, see MatcherSpec.
+    pub fn build(&self, spec: &MatcherSpec) -> Result<Box<dyn Matcher>, SpecError> {
+        let (kind, wrapped) = split_kind(spec);
+        let (_, factory) = self
+            .factories
+            .get(&normalize(kind))
+            .ok_or_else(|| SpecError::UnknownMatcher(spec.kind.to_owned()))?;
+        let mut matcher = match (factory, wrapped) {
+            (Factory::Matcher(factory), None) => factory(spec)?,
+            (Factory::Wrapper(factory), Some(wrapped)) => factory(spec, self.build(&wrapped)?)?,
+            (Factory::Wrapper(_), None) => {
+                return Err(SpecError::MissingMatcher(spec.kind.to_owned()))
+            }
+            (Factory::Matcher(_), Some(_)) => {
+                return Err(spec.invalid_param("matcher", "nothing to wrap"))
+            }
+        };
+        if let Some(weight) = spec.weight {
+            matcher.set_weight(weight);
+        }
+        Ok(matcher)
+    }
+
+    // build_ensemble is Ensemble::from_spec with the matchers of the
+    // registry.
+    pub fn build_ensemble(&self, spec: &EnsembleSpec) -> Result<Ensemble, SpecError> {
+        let matchers = spec
+            .matchers
+            .iter()
+            .map(|matcher| self.build(matcher))
+            .collect::<Result<Vec<Box<dyn Matcher>>, SpecError>>()?;
+        let mut ensemble = Ensemble::new(matchers);
+        ensemble.aggregation = spec.aggregation;
+        Ok(ensemble)
+    }
+}
+
+// split_kind splits "Token-Sort(Jaro-Winkler)" into the wrapper name and the
+// spec of the wrapped matcher, which is otherwise given in spec.matcher.
+fn split_kind(spec: &MatcherSpec) -> (&str, Option<MatcherSpec>) {
+    let kind = spec.kind.trim();
+    match kind.find('(') {
+        Some(open) if kind.ends_with(')') && spec.matcher.is_none() => {
+            let wrapped = MatcherSpec::new(&kind[open + 1..kind.len() - 1]);
+            (kind[..open].trim(), Some(wrapped))
+        }
+        _ => (kind, spec.matcher.as_deref().cloned()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::ensemble::Ensemble;
+    use super::super::levenshtein::compare::LevenshteinMatcher;
+    use super::super::prelude::*;
+    use super::super::spec::{EnsembleSpec, Params};
+    use super::MatcherRegistry;
+
+    const BUILTIN: [&str; 6] = [
+        "Jaro-Winkler",
+        "Jaccard",
+        "Soundex",
+        "Soundex-Jaccard",
+        "Smith-Waterman",
+        "Levenshtein",
+    ];
+
+    // ScaledMatcher scales the Levenshtein score by a factor, it stands for
+    // a matcher defined outside of the library.
+    struct ScaledMatcher {
+        matcher: LevenshteinMatcher,
+        factor: f64,
+    }
+
+    impl Named for ScaledMatcher {
+        fn get_name(&self) -> &str {
+            "Scaled-Levenshtein"
+        }
+    }
+
+    impl Clean for ScaledMatcher {}
+
+    impl Weighted for ScaledMatcher {
+        fn get_weight(&self) -> f64 {
+            self.matcher.get_weight()
+        }
+
+        fn set_weight(&mut self, weight: f64) {
+            self.matcher.set_weight(weight);
+        }
+    }
+
+    impl Matcher for ScaledMatcher {
+        fn get_score(&self, s1: &str, s2: &str) -> f64 {
+            self.factor * self.matcher.get_score(s1, s2)
+        }
+    }
+
+    fn scaled_registry() -> MatcherRegistry {
+        let mut registry = MatcherRegistry::new();
+        registry.register("Scaled-Levenshtein", |spec| {
+            spec.check_params(&["factor"])?;
+            let factor = spec.f64_param("factor")?.unwrap_or(1.0);
+            if !(0.0..=1.0).contains(&factor) {
+                return Err(spec.invalid_param("factor", "expected a number between 0 and 1"));
+            }
+            Ok(Box::new(ScaledMatcher {
+                matcher: LevenshteinMatcher::default(),
+                factor,
+            }))
+        });
+        registry
+    }
+
+    #[test]
+    fn test_builtin_names() {
+        let registry = MatcherRegistry::new();
+        for name in BUILTIN.iter() {
+            let matcher = registry.get(name, &Params::new()).unwrap();
+            assert_eq!(matcher.get_name(), *name);
+            assert!(registry.contains(&name.to_lowercase()));
+        }
+        let matcher = registry
+            .get("token-set(Soundex-Jaccard)", &Params::new())
+            .unwrap();
+        assert_eq!(matcher.get_name(), "Token-Set(Soundex-Jaccard)");
+        assert_eq!(registry.names().len(), 9);
+    }
+
+    #[test]
+    fn test_get_with_params() {
+        let registry = MatcherRegistry::new();
+        let mut params = Params::new();
+        params.insert(
+            "tokenization".to_owned(),
+            ParamValue::Text("Segmented".to_owned()),
+        );
+        let matcher = registry.get("Jaccard", &params).unwrap();
+        assert_eq!(matcher.get_score("Mary Ann", "Maryann"), 1.0);
+        assert!(registry.get("Soundex", &params).is_err());
+    }
+
+    #[test]
+    fn test_register_custom() {
+        let registry = scaled_registry();
+        let mut params = Params::new();
+        params.insert("factor".to_owned(), ParamValue::Float(0.5));
+        let matcher = registry.get("scaled-levenshtein", &params).unwrap();
+        assert_eq!(matcher.get_score("John Doe", "John Doe"), 0.5);
+
+        let spec = MatcherSpec::new("Token-Sort")
+            .with_weight(0.25)
+            .with_matcher(MatcherSpec::new("Scaled-Levenshtein"));
+        let matcher = registry.build(&spec).unwrap();
+        assert_eq!(matcher.get_name(), "Token-Sort(Scaled-Levenshtein)");
+        assert_eq!(matcher.get_weight(), 0.25);
+        assert_eq!(matcher.get_score("Doe John", "John Doe"), 1.0);
+    }
+
+    #[test]
+    fn test_errors() {
+        let registry = scaled_registry();
+        let err = registry.get("Metaphone", &Params::new()).err().unwrap();
+        assert_eq!(err.to_string(), "unknown matcher Metaphone");
+        let mut params = Params::new();
+        params.insert("factor".to_owned(), ParamValue::Float(2.0));
+        let err = registry.get("Scaled-Levenshtein", &params).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "invalid parameter factor of Scaled-Levenshtein: expected a number between 0 and 1"
+        );
+        let err = registry.get("Partial", &Params::new()).err().unwrap();
+        assert!(matches!(err, SpecError::MissingMatcher(_)));
+        assert!(MatcherRegistry::empty()
+            .get("Jaro-Winkler", &Params::new())
+            .is_err());
+    }
+
+    #[test]
+    fn test_build_ensemble() {
+        let registry = scaled_registry();
+        let spec = EnsembleSpec {
+            matchers: vec![
+                MatcherSpec::new("Jaro-Winkler").with_weight(0.5),
+                MatcherSpec::new("Scaled-Levenshtein").with_weight(0.5),
+            ],
+            aggregation: Default::default(),
+        };
+        let ensemble = registry.build_ensemble(&spec).unwrap();
+        assert_eq!(ensemble.get_aggregated_score("John Doe", "John Doe"), 1.0);
+        assert!(Ensemble::from_spec(&spec).is_err());
+    }
+}
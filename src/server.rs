@@ -0,0 +1,441 @@
+use super::ensemble::{Ensemble, EnsembleResult};
+use super::spec::{EnsembleSpec, SpecError};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::io::{self, Read};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::Duration;
+use tiny_http::{Header, Request, Response};
+
+// ScoreRequest is the body of /score, and every pair of /score/batch.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ScoreRequest {
+    pub name1: String,
+    pub name2: String,
+}
+
+// BatchScoreRequest is the body of /score/batch.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BatchScoreRequest {
+    pub pairs: Vec<ScoreRequest>,
+}
+
+// SearchRequest is the body of /search. The query is searched among names,
+// or among the names the service was started with when names is missing.
+//   - k: Maximum number of results, 10 by default.
+//   - min_score: Only results scoring at least min_score are returned.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SearchRequest {
+    pub query: String,
+    #[serde(default)]
+    pub names: Option<Vec<String>>,
+    #[serde(default = "default_k")]
+    pub k: usize,
+    #[serde(default)]
+    pub min_score: f64,
+}
+
+fn default_k() -> usize {
+    10
+}
+
+#[derive(Serialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+// MatchService answers matching requests with the Ensemble described by a
+// spec file. The file is read again by reload, requests being answered
+// meanwhile keep the previous Ensemble.
+pub struct MatchService {
+    spec_path: PathBuf,
+    ensemble: RwLock<Arc<Ensemble>>,
+    names: Vec<String>,
+}
+
+impl MatchService {
+    // load builds the Ensemble from a JSON, TOML or YAML spec file,
+    // see EnsembleSpec::load.
+    pub fn load<P: AsRef<Path>>(spec_path: P) -> Result<MatchService, SpecError> {
+        let spec_path = spec_path.as_ref().to_path_buf();
+        let ensemble = Ensemble::from_spec(&EnsembleSpec::load(&spec_path)?)?;
+        Ok(MatchService {
+            spec_path,
+            ensemble: RwLock::new(Arc::new(ensemble)),
+            names: Vec::new(),
+        })
+    }
+
+    // with_names sets the names searched by /search requests without names.
+    pub fn with_names(mut self, names: Vec<String>) -> MatchService {
+        self.names = names;
+        self
+    }
+
+    pub fn ensemble(&self) -> Arc<Ensemble> {
+        Arc::clone(&self.ensemble.read().unwrap())
+    }
+
+    // reload reads the spec file again. The current Ensemble is kept when
+    // the file is invalid.
+    pub fn reload(&self) -> Result<(), SpecError> {
+        let ensemble = Ensemble::from_spec(&EnsembleSpec::load(&self.spec_path)?)?;
+        *self.ensemble.write().unwrap() = Arc::new(ensemble);
+        Ok(())
+    }
+
+    // handle answers a request, returning the status code and JSON body.
+    //   - POST /score: ScoreRequest -> EnsembleResult
+    //   - POST /score/batch: BatchScoreRequest -> [EnsembleResult]
+    //   - POST /search: SearchRequest -> [EnsembleResult], best first
+    // Errors are returned as {"error": "..."}.
+    pub fn handle(&self, method: &str, path: &str, body: &str) -> (u16, String) {
+        let path = path.split('?').next().unwrap_or("");
+        let routed = match path {
+            "/score" | "/score/batch" | "/search" if method != "POST" => {
+                Err((405, format!("{} {} is not allowed, use POST", method, path)))
+            }
+            "/score" => parse(body).map(|request| self.score(request)),
+            "/score/batch" => parse(body).map(|request| self.score_batch(request)),
+            "/search" => parse(body).and_then(|request| self.search(request)),
+            _ => Err((404, format!("{} not found", path))),
+        };
+        match routed {
+            Ok(body) => (200, body),
+            Err((status, error)) => (status, to_json(&ErrorResponse { error })),
+        }
+    }
+
+    fn score(&self, request: ScoreRequest) -> String {
+        let result = self
+            .ensemble()
+            .get_ensemble_result(&request.name1[..], &request.name2[..]);
+        to_json(&result)
+    }
+
+    fn score_batch(&self, request: BatchScoreRequest) -> String {
+        let ensemble = self.ensemble();
+        let results: Vec<EnsembleResult> = request
+            .pairs
+            .par_iter()
+            .map(|pair| ensemble.get_ensemble_result(&pair.name1[..], &pair.name2[..]))
+            .collect();
+        to_json(&results)
+    }
+
+    fn search(&self, request: SearchRequest) -> Result<String, (u16, String)> {
+        let names: Vec<&str> = match &request.names {
+            Some(names) => names.iter().map(|name| &name[..]).collect(),
+            None if !self.names.is_empty() => self.names.iter().map(|name| &name[..]).collect(),
+            None => return Err((400, "names is missing".to_owned())),
+        };
+        let results = self.ensemble().get_top_k_above(
+            &request.query[..],
+            names,
+            request.k,
+            request.min_score,
+        );
+        Ok(to_json(&results))
+    }
+}
+
+fn parse<'a, T: Deserialize<'a>>(body: &'a str) -> Result<T, (u16, String)> {
+    serde_json::from_str(body).map_err(|err| (400, format!("invalid request: {}", err)))
+}
+
+fn to_json<T: Serialize>(value: &T) -> String {
+    serde_json::to_string(value).unwrap()
+}
+
+// DEFAULT_MAX_BODY_BYTES is the default limit of request bodies, 1 MiB.
+pub const DEFAULT_MAX_BODY_BYTES: usize = 1 << 20;
+
+// Server serves a MatchService over HTTP.
+//   - max_body_bytes: Requests with a larger body are answered with 413
+//     without being read, DEFAULT_MAX_BODY_BYTES by default.
+pub struct Server {
+    http: tiny_http::Server,
+    service: Arc<MatchService>,
+    stopped: AtomicBool,
+    pub max_body_bytes: usize,
+}
+
+impl Server {
+    // bind listens on addr, eg "127.0.0.1:8080". Port 0 picks a free port,
+    // see local_addr.
+    pub fn bind(addr: &str, service: Arc<MatchService>) -> io::Result<Server> {
+        let http = tiny_http::Server::http(addr).map_err(io::Error::other)?;
+        Ok(Server {
+            http,
+            service,
+            stopped: AtomicBool::new(false),
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+        })
+    }
+
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.http.server_addr().to_ip()
+    }
+
+    pub fn service(&self) -> &MatchService {
+        &self.service
+    }
+
+    // run answers requests on workers threads until stop is called.
+    pub fn run(&self, workers: usize) {
+        thread::scope(|scope| {
+            for _ in 0..workers.max(1) {
+                scope.spawn(|| self.serve());
+            }
+        });
+    }
+
+    // stop makes run return once the requests being answered are done.
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::SeqCst);
+    }
+
+    fn serve(&self) {
+        while !self.stopped.load(Ordering::SeqCst) {
+            match self.http.recv_timeout(Duration::from_millis(100)) {
+                Ok(Some(request)) => self.respond(request),
+                Ok(None) => continue,
+                Err(_) => return,
+            }
+        }
+    }
+
+    fn respond(&self, mut request: Request) {
+        let (status, body) = match self.read_body(&mut request) {
+            Ok(body) => {
+                let method = request.method().as_str().to_owned();
+                self.service.handle(&method, request.url(), &body)
+            }
+            Err((status, error)) => (status, to_json(&ErrorResponse { error })),
+        };
+        let content_type: Header = "Content-Type: application/json".parse().unwrap();
+        let response = Response::from_string(body)
+            .with_status_code(status)
+            .with_header(content_type);
+        // The client may be gone, there is no one left to report to.
+        let _ = request.respond(response);
+    }
+
+    // read_body reads the body of request, up to max_body_bytes. The length
+    // is unknown before reading chunked bodies, so the read is limited too.
+    fn read_body(&self, request: &mut Request) -> Result<String, (u16, String)> {
+        let too_large = || {
+            let error = format!("request body exceeds {} bytes", self.max_body_bytes);
+            (413, error)
+        };
+        if request.body_length().unwrap_or(0) > self.max_body_bytes {
+            return Err(too_large());
+        }
+        let mut body = String::new();
+        request
+            .as_reader()
+            .take(self.max_body_bytes as u64 + 1)
+            .read_to_string(&mut body)
+            .map_err(|err| (400, format!("invalid body: {}", err)))?;
+        if body.len() > self.max_body_bytes {
+            return Err(too_large());
+        }
+        Ok(body)
+    }
+}
+
+// reload_on_sighup reloads service every time the process receives SIGHUP,
+// from a background thread. Reload errors are passed to on_error.
+#[cfg(unix)]
+pub fn reload_on_sighup<F>(service: Arc<MatchService>, on_error: F) -> io::Result<()>
+where
+    F: Fn(SpecError) + Send + 'static,
+{
+    let mut signals = signal_hook::iterator::Signals::new([signal_hook::consts::SIGHUP])?;
+    thread::spawn(move || {
+        for _ in signals.forever() {
+            if let Err(err) = service.reload() {
+                on_error(err);
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::ensemble::EnsembleResult;
+    use super::{MatchService, Server};
+    use std::io::{Read, Write};
+    use std::net::{SocketAddr, TcpStream};
+    use std::path::PathBuf;
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    const SPEC: &str = r#"{
+        "matchers": [
+            {"type": "Jaro-Winkler", "weight": 0.5},
+            {"type": "Soundex", "weight": 0.5}
+        ]
+    }"#;
+
+    // spec_file writes a spec to a file only used by the calling test.
+    fn spec_file(test: &str, content: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "name_match_server_{}_{}.json",
+            test,
+            std::process::id()
+        ));
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    // post sends a request to the server and returns its status and body.
+    fn post(addr: SocketAddr, path: &str, body: &str) -> (u16, String) {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "POST {} HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            path,
+            body.len(),
+            body
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let status = response[9..12].parse().unwrap();
+        let body = response.split("\r\n\r\n").nth(1).unwrap().to_owned();
+        (status, body)
+    }
+
+    #[test]
+    fn test_server() {
+        let path = spec_file("server", SPEC);
+        let service = MatchService::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let names = vec!["Jon Doe".to_owned(), "Mary Smith".to_owned()];
+        let service = Arc::new(service.with_names(names));
+        let server = Arc::new(Server::bind("127.0.0.1:0", service).unwrap());
+        let addr = server.local_addr().unwrap();
+        let running = Arc::clone(&server);
+        let handle = thread::spawn(move || running.run(2));
+
+        let (status, body) = post(
+            addr,
+            "/score",
+            r#"{"name1": "John Doe", "name2": "John Doe"}"#,
+        );
+        assert_eq!(status, 200);
+        let result: EnsembleResult = serde_json::from_str(&body).unwrap();
+        assert_eq!(result.score, 1.0);
+        assert_eq!(result.results.len(), 2);
+
+        let (status, body) = post(
+            addr,
+            "/score/batch",
+            r#"{"pairs": [{"name1": "John Doe", "name2": "Jon Doe"},
+                          {"name1": "John Doe", "name2": "Mary Smith"}]}"#,
+        );
+        assert_eq!(status, 200);
+        let results: Vec<EnsembleResult> = serde_json::from_str(&body).unwrap();
+        assert_eq!(results[1].name2, "Mary Smith");
+        assert!(results[0].score > results[1].score);
+
+        let (status, body) = post(addr, "/search", r#"{"query": "John Doe", "k": 1}"#);
+        assert_eq!(status, 200);
+        let results: Vec<EnsembleResult> = serde_json::from_str(&body).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].name2, "Jon Doe");
+
+        let (status, body) = post(addr, "/score", r#"{"name1": "John Doe"}"#);
+        assert_eq!(status, 400);
+        assert!(body.contains("name2"));
+        assert_eq!(post(addr, "/unknown", "{}").0, 404);
+
+        server.stop();
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_body_limit() {
+        let path = spec_file("body_limit", SPEC);
+        let service = Arc::new(MatchService::load(&path).unwrap());
+        std::fs::remove_file(&path).unwrap();
+        let mut server = Server::bind("127.0.0.1:0", service).unwrap();
+        server.max_body_bytes = 64;
+        let server = Arc::new(server);
+        let addr = server.local_addr().unwrap();
+        let running = Arc::clone(&server);
+        let handle = thread::spawn(move || running.run(1));
+
+        let body = r#"{"name1": "John Doe", "name2": "Jon Doe"}"#;
+        assert_eq!(post(addr, "/score", body).0, 200);
+        let body = format!(r#"{{"name1": "{}", "name2": "Jon Doe"}}"#, "J".repeat(64));
+        let (status, body) = post(addr, "/score", &body);
+        assert_eq!(status, 413);
+        assert!(body.contains("64 bytes"));
+
+        server.stop();
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_handle() {
+        let path = spec_file("handle", SPEC);
+        let service = MatchService::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let search =
+            r#"{"query": "John Doe", "names": ["Mary Smith", "Jon Doe"], "min_score": 0.9}"#;
+        let (status, body) = service.handle("POST", "/search", search);
+        assert_eq!(status, 200);
+        let results: Vec<EnsembleResult> = serde_json::from_str(&body).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].name2, "Jon Doe");
+        assert_eq!(service.handle("GET", "/score", "").0, 405);
+        assert_eq!(
+            service
+                .handle("POST", "/search", r#"{"query": "John Doe"}"#)
+                .0,
+            400
+        );
+    }
+
+    #[test]
+    fn test_reload() {
+        let path = spec_file("reload", SPEC);
+        let service = MatchService::load(&path).unwrap();
+        assert_eq!(service.ensemble().matchers.len(), 2);
+
+        std::fs::write(&path, r#"{"matchers": [{"type": "Metaphone"}]}"#).unwrap();
+        assert!(service.reload().is_err());
+        assert_eq!(service.ensemble().matchers.len(), 2);
+
+        std::fs::write(&path, r#"{"matchers": [{"type": "Levenshtein"}]}"#).unwrap();
+        service.reload().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(service.ensemble().matchers[0].get_name(), "Levenshtein");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_reload_on_sighup() {
+        let path = spec_file("sighup", SPEC);
+        let service = Arc::new(MatchService::load(&path).unwrap());
+        super::reload_on_sighup(Arc::clone(&service), |err| panic!("{}", err)).unwrap();
+
+        std::fs::write(&path, r#"{"matchers": [{"type": "Levenshtein"}]}"#).unwrap();
+        signal_hook::low_level::raise(signal_hook::consts::SIGHUP).unwrap();
+        let start = Instant::now();
+        while service.ensemble().matchers.len() != 1 {
+            assert!(start.elapsed() < Duration::from_secs(10));
+            thread::sleep(Duration::from_millis(10));
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+}
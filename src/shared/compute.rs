@@ -9,10 +9,57 @@ pub fn jaccard_index(s1: &HashSet<String>, s2: &HashSet<String>) -> f64 {
     overlaps / union
 }
 
+// qgrams returns the overlapping substrings of length q of every token.
+// Tokens shorter than q are kept whole.
+// Eg:
+//   ("JOHN DOE", 3) -> ["JOH", "OHN", "DOE"]
+pub fn qgrams(s1: &str, q: usize) -> Vec<String> {
+    let mut grams = Vec::new();
+    for token in s1.split_whitespace() {
+        let chars: Vec<char> = token.chars().collect();
+        if q == 0 || chars.len() <= q {
+            grams.push(token.to_owned());
+            continue;
+        }
+        for window in chars.windows(q) {
+            grams.push(window.iter().collect());
+        }
+    }
+    grams
+}
+
 #[cfg(test)]
-mod test {
+pub(crate) mod test {
     use super::jaccard_index;
+    use super::qgrams;
     use std::collections::HashSet;
+
+    // random_names returns count pseudo random names of up to max_length
+    // characters of alphabet, the same names for the same seed. Small
+    // alphabets make matches and transpositions frequent.
+    pub(crate) fn random_names(
+        seed: u64,
+        alphabet: &[char],
+        count: usize,
+        max_length: usize,
+    ) -> Vec<String> {
+        let mut state = seed;
+        let mut next = || {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (state >> 33) as usize
+        };
+        (0..count)
+            .map(|_| {
+                let length = next() % (max_length + 1);
+                (0..length)
+                    .map(|_| alphabet[next() % alphabet.len()])
+                    .collect()
+            })
+            .collect()
+    }
+
     fn build_map_from_word_vec(words: Vec<&str>) -> HashSet<String> {
         let mut hash_map: HashSet<String> = HashSet::new();
         for word in words {
@@ -45,4 +92,16 @@ mod test {
         let coef = jaccard_index(&name1, &name2);
         assert_eq!(coef, 0.);
     }
+
+    #[test]
+    fn test_qgrams() {
+        let grams = qgrams("JOHN DOE", 3);
+        assert_eq!(grams, vec!["JOH", "OHN", "DOE"]);
+    }
+
+    #[test]
+    fn test_qgrams_short_token() {
+        let grams = qgrams("AL JO", 3);
+        assert_eq!(grams, vec!["AL", "JO"]);
+    }
 }
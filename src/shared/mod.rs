@@ -1 +1,3 @@
 pub mod compute;
+pub mod prepared;
+pub mod tokenize;
@@ -0,0 +1,235 @@
+use super::super::prelude::Clean;
+use super::super::soundex::encode;
+use super::compute;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, OnceLock};
+
+// PreparedName holds a name along with representations derived from it,
+// so that comparing a name many times does not recompute them.
+// The cleaned form is computed upfront, other forms are computed lazily
+// on first use and cached.
+//   - chars: Characters of the cleaned name.
+//   - tokens: Set of whitespace separated tokens of the cleaned name.
+//   - soundex: Soundex code of the cleaned name.
+//   - soundex_tokens: Set of Soundex codes of every token.
+//   - qgrams: Set of q-grams of every token, cached per q.
+// Names are cleaned with the default Clean implementation.
+pub struct PreparedName {
+    raw: String,
+    cleaned: String,
+    chars: OnceLock<Vec<char>>,
+    tokens: OnceLock<HashSet<String>>,
+    soundex: OnceLock<String>,
+    soundex_tokens: OnceLock<HashSet<String>>,
+    qgrams: Mutex<HashMap<usize, Arc<HashSet<String>>>>,
+}
+
+struct DefaultClean;
+
+impl Clean for DefaultClean {}
+
+impl PreparedName {
+    pub fn new(raw: &str) -> PreparedName {
+        let cleaned = DefaultClean.clean(raw);
+        PreparedName::from_cleaned(raw, cleaned)
+    }
+
+    // from_cleaned prepares a name whose cleaned form is already known,
+    // eg read from a saved index.
+    pub fn from_cleaned(raw: &str, cleaned: String) -> PreparedName {
+        PreparedName {
+            raw: raw.to_owned(),
+            cleaned,
+            chars: OnceLock::new(),
+            tokens: OnceLock::new(),
+            soundex: OnceLock::new(),
+            soundex_tokens: OnceLock::new(),
+            qgrams: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // with_soundex_tokens seeds the Soundex codes of every token,
+    // separated by whitespace, instead of encoding tokens again.
+    pub fn with_soundex_tokens(self, codes: &str) -> PreparedName {
+        let codes: HashSet<String> = codes
+            .split_whitespace()
+            .map(|code| code.to_owned())
+            .collect();
+        let _ = self.soundex_tokens.set(codes);
+        self
+    }
+
+    pub fn raw(&self) -> &str {
+        &self.raw[..]
+    }
+
+    pub fn cleaned(&self) -> &str {
+        &self.cleaned[..]
+    }
+
+    pub fn chars(&self) -> &[char] {
+        self.chars.get_or_init(|| self.cleaned.chars().collect())
+    }
+
+    pub fn tokens(&self) -> &HashSet<String> {
+        self.tokens.get_or_init(|| {
+            self.cleaned
+                .split_whitespace()
+                .map(|token| token.to_owned())
+                .collect()
+        })
+    }
+
+    pub fn soundex(&self) -> &str {
+        self.soundex
+            .get_or_init(|| encode::apply_soundex(&self.cleaned[..]))
+    }
+
+    pub fn soundex_tokens(&self) -> &HashSet<String> {
+        self.soundex_tokens.get_or_init(|| {
+            self.cleaned
+                .split_whitespace()
+                .map(encode::apply_soundex)
+                .collect()
+        })
+    }
+
+    pub fn qgrams(&self, q: usize) -> Arc<HashSet<String>> {
+        let mut qgrams = self.qgrams.lock().unwrap();
+        qgrams
+            .entry(q)
+            .or_insert_with(|| {
+                Arc::new(compute::qgrams(&self.cleaned[..], q).into_iter().collect())
+            })
+            .clone()
+    }
+}
+
+impl From<&str> for PreparedName {
+    fn from(raw: &str) -> PreparedName {
+        PreparedName::new(raw)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::super::jaccard::compare::JaccardMatcher;
+    use super::super::super::jaro::compare::JaroWinklerMatcher;
+    use super::super::super::jaro::config::JaroWinklerConfigOptions;
+    use super::super::super::levenshtein::compare::LevenshteinMatcher;
+    use super::super::super::prelude::*;
+    use super::super::super::smith_waterman::compare::SmithWatermanMatcher;
+    use super::super::super::soundex::compare::{SoundexJaccardMatcher, SoundexMatcher};
+    use super::super::super::token::compare::TokenSortMatcher;
+    use super::PreparedName;
+
+    #[test]
+    fn test_prepared_name() {
+        let name = PreparedName::new("Doe, John");
+        assert_eq!(name.raw(), "Doe, John");
+        assert_eq!(name.cleaned(), "DOE  JOHN");
+        assert_eq!(name.chars().len(), 9);
+        assert_eq!(name.tokens().len(), 2);
+        assert!(name.tokens().contains("JOHN"));
+        assert_eq!(name.soundex(), "D250");
+        assert!(name.soundex_tokens().contains("J500"));
+        assert_eq!(name.qgrams(3).len(), 3);
+        assert_eq!(name.qgrams(2).len(), 5);
+    }
+
+    #[test]
+    fn test_with_soundex_tokens() {
+        let name = PreparedName::from_cleaned("Doe, John", "DOE  JOHN".to_owned())
+            .with_soundex_tokens("D000 J500");
+        assert_eq!(name.soundex_tokens().len(), 2);
+        assert!(name.soundex_tokens().contains("D000"));
+    }
+
+    fn all_matchers() -> Vec<Box<dyn Matcher>> {
+        let mut config = JaroWinklerConfigOptions::default();
+        config.max_permutation_tokens = Some(3);
+        let mut segmented_jaccard = JaccardMatcher::default();
+        segmented_jaccard.set_tokenization(Tokenization::Segmented);
+        vec![
+            Box::new(JaroWinklerMatcher::default()),
+            Box::new(JaroWinklerMatcher::new(Some(config), None)),
+            Box::new(JaccardMatcher::default()),
+            Box::new(segmented_jaccard),
+            Box::new(SoundexMatcher::default()),
+            Box::new(SoundexJaccardMatcher::default()),
+            Box::new(LevenshteinMatcher::default()),
+            Box::new(SmithWatermanMatcher::default()),
+            Box::new(TokenSortMatcher::new(
+                Box::new(JaroWinklerMatcher::default()),
+                None,
+            )),
+        ]
+    }
+
+    #[test]
+    fn test_prepared_scores_match_raw_scores() {
+        let matchers = all_matchers();
+        let pairs = [
+            ("John Doe", "Doe, Jon"),
+            ("Mary Ann Smith", "Maryann Smyth"),
+            ("", "Peter Parker"),
+        ];
+        for matcher in matchers.iter() {
+            for (name1, name2) in pairs.iter() {
+                let prepared1 = PreparedName::new(name1);
+                let prepared2 = PreparedName::new(name2);
+                assert_eq!(
+                    matcher.get_prepared_score(&prepared1, &prepared2),
+                    matcher.get_score(name1, name2)
+                );
+                assert_eq!(
+                    matcher.get_prepared_upper_bound(&prepared1, &prepared2),
+                    matcher.get_upper_bound(name1, name2)
+                );
+                let result = matcher.get_prepared_match_result(&prepared1, &prepared2);
+                assert_eq!(
+                    result.ordering,
+                    matcher.get_match_result(name1, name2).ordering
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_query_scores_match_raw_scores() {
+        let long_name = "Maria del Carmen de los Angeles Guadalupe Fernandez de la Torre y Alvarez";
+        let queries = ["John Doe", "", "Zoë Ølsen", long_name];
+        let candidates = [
+            "Doe, Jon",
+            "Peter Parker",
+            "",
+            "Zoe Olsen",
+            "Maria del Carmen Fernandez de la Torre",
+            long_name,
+        ];
+        for matcher in all_matchers().iter() {
+            for query in queries.iter() {
+                let scorer = matcher.prepare_query(query);
+                for candidate in candidates.iter() {
+                    let prepared = PreparedName::new(candidate);
+                    // Some matchers score two empty names as NaN.
+                    assert_eq!(
+                        scorer.score(&prepared).to_bits(),
+                        matcher.get_score(query, candidate).to_bits()
+                    );
+                    assert_eq!(
+                        scorer.upper_bound(&prepared),
+                        matcher.get_upper_bound(query, candidate)
+                    );
+                    let result = scorer.match_result(&prepared);
+                    let expected = matcher.get_match_result(query, candidate);
+                    assert_eq!(
+                        result.weighted_score.to_bits(),
+                        expected.weighted_score.to_bits()
+                    );
+                    assert_eq!(result.ordering, expected.ordering);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,214 @@
+use std::collections::HashSet;
+
+// Longest run of adjacent tokens that may be merged into a single token.
+const MAX_MERGED_TOKENS: usize = 3;
+// Shortest part a token can be split into.
+const MIN_SPLIT_LENGTH: usize = 2;
+
+// tokenize splits a name by whitespace.
+pub fn tokenize(name: &str) -> Vec<String> {
+    name.split_whitespace()
+        .map(|token| token.to_owned())
+        .collect()
+}
+
+// merge_adjacent merges runs of adjacent tokens whose concatenation
+// is a token of the other name.
+// Eg:
+//   ["MARY", "ANN", "DOE"] against ["MARYANN", "DOE"] -> ["MARYANN", "DOE"]
+fn merge_adjacent(tokens: &[String], other: &HashSet<&str>) -> Vec<String> {
+    let mut merged = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        let mut run = 1;
+        for n in (2..=MAX_MERGED_TOKENS).rev() {
+            if i + n <= tokens.len() && other.contains(&tokens[i..i + n].concat()[..]) {
+                run = n;
+                break;
+            }
+        }
+        merged.push(tokens[i..i + run].concat());
+        i += run;
+    }
+    merged
+}
+
+// split_token returns the best split of a token into two parts
+// against the other name's tokens. Splits where both parts are tokens of the
+// other name are preferred over splits where only one part is.
+// Eg:
+//   "MARYANN" against ["ANN", "SMITH"] -> Some(("MARY", "ANN"))
+fn split_token(token: &str, other: &HashSet<&str>) -> Option<(String, String)> {
+    let chars: Vec<char> = token.chars().collect();
+    if chars.len() < 2 * MIN_SPLIT_LENGTH {
+        return None;
+    }
+
+    let mut best: Option<(usize, (usize, usize))> = None;
+    for k in MIN_SPLIT_LENGTH..=(chars.len() - MIN_SPLIT_LENGTH) {
+        let head: String = chars[..k].iter().collect();
+        let tail: String = chars[k..].iter().collect();
+        let head_length = if other.contains(&head[..]) { k } else { 0 };
+        let tail_length = if other.contains(&tail[..]) {
+            chars.len() - k
+        } else {
+            0
+        };
+        let matched_parts = (head_length > 0) as usize + (tail_length > 0) as usize;
+        if matched_parts == 0 {
+            continue;
+        }
+        // Ties are broken by the longest matching part.
+        let rank = (matched_parts, head_length.max(tail_length));
+        if best.is_none_or(|(_, best_rank)| rank > best_rank) {
+            best = Some((k, rank));
+        }
+    }
+
+    best.map(|(k, _)| (chars[..k].iter().collect(), chars[k..].iter().collect()))
+}
+
+// split_long splits every token that contains a token of the other name.
+// Eg:
+//   ["MARYANN", "SMITH"] against ["ANN", "SMITH"] -> ["MARY", "ANN", "SMITH"]
+fn split_long(tokens: &[String], other: &HashSet<&str>) -> Vec<String> {
+    let mut split = Vec::new();
+    for token in tokens.iter() {
+        if other.contains(&token[..]) {
+            split.push(token.to_owned());
+            continue;
+        }
+        match split_token(token, other) {
+            Some((head, tail)) => {
+                split.push(head);
+                split.push(tail);
+            }
+            None => split.push(token.to_owned()),
+        }
+    }
+    split
+}
+
+// segmentations returns the candidate segmentations of tokens against the
+// other name's tokens: as given, with adjacent tokens merged, and with long
+// tokens split. Duplicates are removed, the given segmentation comes first.
+pub fn segmentations(tokens: &[String], other: &[String]) -> Vec<Vec<String>> {
+    let other: HashSet<&str> = other.iter().map(|token| &token[..]).collect();
+    let mut candidates = vec![tokens.to_vec()];
+    for candidate in [merge_adjacent(tokens, &other), split_long(tokens, &other)] {
+        if !candidates.contains(&candidate) {
+            candidates.push(candidate);
+        }
+    }
+    candidates
+}
+
+// best_segmentation tries every pair of candidate segmentations of both
+// names and returns the one with the highest score along with the score.
+// Ties are resolved in favour of the given segmentation.
+// Eg:
+// ["MARY", "ANN"] vs ["MARYANN"] with Jaccard Index
+// -> (1.0, ["MARYANN"], ["MARYANN"])
+pub fn best_segmentation<F>(
+    tokens_1: &[String],
+    tokens_2: &[String],
+    score: F,
+) -> (f64, Vec<String>, Vec<String>)
+where
+    F: Fn(&[String], &[String]) -> f64,
+{
+    let candidates_1 = segmentations(tokens_1, tokens_2);
+    let candidates_2 = segmentations(tokens_2, tokens_1);
+
+    let mut best = (f64::NEG_INFINITY, tokens_1.to_vec(), tokens_2.to_vec());
+    for candidate_1 in candidates_1.iter() {
+        for candidate_2 in candidates_2.iter() {
+            let candidate_score = score(candidate_1, candidate_2);
+            if candidate_score > best.0 {
+                best = (candidate_score, candidate_1.clone(), candidate_2.clone());
+            }
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::compute::jaccard_index;
+    use std::collections::HashSet;
+
+    fn tokens(words: Vec<&str>) -> Vec<String> {
+        words.into_iter().map(|word| word.to_owned()).collect()
+    }
+
+    fn jaccard(s1: &[String], s2: &[String]) -> f64 {
+        let set_1: HashSet<String> = s1.iter().cloned().collect();
+        let set_2: HashSet<String> = s2.iter().cloned().collect();
+        jaccard_index(&set_1, &set_2)
+    }
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(
+            super::tokenize("  MARY   ANN "),
+            tokens(vec!["MARY", "ANN"])
+        );
+    }
+
+    #[test]
+    fn test_segmentations_merge() {
+        let candidates = super::segmentations(
+            &tokens(vec!["MC", "DONALD", "JOHN"]),
+            &tokens(vec!["MCDONALD", "JOHN"]),
+        );
+        assert!(candidates.contains(&tokens(vec!["MCDONALD", "JOHN"])));
+    }
+
+    #[test]
+    fn test_segmentations_split() {
+        let candidates = super::segmentations(
+            &tokens(vec!["MARYANN", "SMITH"]),
+            &tokens(vec!["ANN", "SMITH"]),
+        );
+        assert!(candidates.contains(&tokens(vec!["MARY", "ANN", "SMITH"])));
+    }
+
+    #[test]
+    fn test_segmentations_given_first() {
+        let candidates =
+            super::segmentations(&tokens(vec!["JOHN", "DOE"]), &tokens(vec!["JOHN", "DOE"]));
+        assert_eq!(candidates, vec![tokens(vec!["JOHN", "DOE"])]);
+    }
+
+    #[test]
+    fn test_best_segmentation_merge() {
+        let (score, segmented_1, segmented_2) = super::best_segmentation(
+            &tokens(vec!["MARY", "ANN"]),
+            &tokens(vec!["MARYANN"]),
+            jaccard,
+        );
+        assert_eq!(score, 1.0);
+        assert_eq!(segmented_1, segmented_2);
+    }
+
+    #[test]
+    fn test_best_segmentation_split() {
+        let (score, _, _) = super::best_segmentation(
+            &tokens(vec!["MARYANN", "SMITH"]),
+            &tokens(vec!["ANN", "SMITH"]),
+            jaccard,
+        );
+        assert!((score - 0.666).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_best_segmentation_no_change() {
+        let (score, segmented_1, _) = super::best_segmentation(
+            &tokens(vec!["JOHN", "DOE"]),
+            &tokens(vec!["JANE", "DOE"]),
+            jaccard,
+        );
+        assert!((score - 0.333).abs() < 0.01);
+        assert_eq!(segmented_1, tokens(vec!["JOHN", "DOE"]));
+    }
+}
@@ -0,0 +1,157 @@
+use super::super::prelude::*;
+use super::compute;
+use super::config;
+use std::sync::RwLock;
+
+// SmithWatermanMatcher finds the best matching region between two names
+// using Smith-Waterman local alignment with affine gaps.
+// Useful when a name is embedded in noise, eg:
+// "PAYMENT TO JOHN A DOE REF" vs "John Doe"
+// The alignment score is normalized by the self-alignment score
+// of the shorter name. Between 0.0 and 1.0.
+// Basic preprocessing:
+//   - Replaces non-alphanumeric with whitespace
+//   - Convert to uppercase.
+pub struct SmithWatermanMatcher {
+    name: String,
+    config: config::SmithWatermanConfigOptions,
+    weight: RwLock<f64>,
+}
+
+impl SmithWatermanMatcher {
+    pub fn new(
+        user_config: Option<config::SmithWatermanConfigOptions>,
+        weight: Option<f64>,
+    ) -> SmithWatermanMatcher {
+        let configuration = user_config.unwrap_or_default();
+        let weight = weight.unwrap_or(1.0);
+        let locked_weight = RwLock::new(weight);
+        SmithWatermanMatcher {
+            name: "Smith-Waterman".to_owned(),
+            config: configuration,
+            weight: locked_weight,
+        }
+    }
+
+    // from_spec builds the matcher described by spec, the parameters of
+    // SmithWatermanConfigOptions missing from spec keep their default.
+    pub fn from_spec(spec: &MatcherSpec) -> Result<SmithWatermanMatcher, SpecError> {
+        spec.check_params(&["match_score", "mismatch_score", "gap_open", "gap_extend"])?;
+        let mut config = config::SmithWatermanConfigOptions::default();
+        if let Some(score) = spec.f64_param("match_score")? {
+            if score <= 0.0 {
+                return Err(spec.invalid_param("match_score", "must be positive"));
+            }
+            config.match_score = score;
+        }
+        if let Some(score) = spec.f64_param("mismatch_score")? {
+            config.mismatch_score = score;
+        }
+        if let Some(penalty) = spec.f64_param("gap_open")? {
+            config.gap_open = penalty;
+        }
+        if let Some(penalty) = spec.f64_param("gap_extend")? {
+            config.gap_extend = penalty;
+        }
+        Ok(SmithWatermanMatcher::new(Some(config), spec.weight))
+    }
+}
+
+impl Default for SmithWatermanMatcher {
+    fn default() -> SmithWatermanMatcher {
+        SmithWatermanMatcher::new(None, None)
+    }
+}
+
+impl Named for SmithWatermanMatcher {
+    fn get_name(&self) -> &str {
+        &self.name[..]
+    }
+}
+
+impl Clean for SmithWatermanMatcher {}
+
+impl Weighted for SmithWatermanMatcher {
+    fn get_weight(&self) -> f64 {
+        let weight = self.weight.read().unwrap();
+        *weight
+    }
+
+    fn set_weight(&mut self, weight: f64) {
+        let mut weight_ptr = self.weight.write().unwrap();
+        *weight_ptr = weight;
+    }
+}
+
+impl Matcher for SmithWatermanMatcher {
+    fn get_score(&self, s1: &str, s2: &str) -> f64 {
+        let s1 = self.clean(s1);
+        let s2 = self.clean(s2);
+        compute::smith_waterman_score(&s1[..], &s2[..], &self.config)
+    }
+
+    fn get_prepared_score(&self, p1: &PreparedName, p2: &PreparedName) -> f64 {
+        compute::smith_waterman_score(p1.cleaned(), p2.cleaned(), &self.config)
+    }
+
+    fn get_spec(&self) -> MatcherSpec {
+        MatcherSpec::new(self.get_name())
+            .with_weight(self.get_weight())
+            .with_param("match_score", ParamValue::Float(self.config.match_score))
+            .with_param(
+                "mismatch_score",
+                ParamValue::Float(self.config.mismatch_score),
+            )
+            .with_param("gap_open", ParamValue::Float(self.config.gap_open))
+            .with_param("gap_extend", ParamValue::Float(self.config.gap_extend))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::super::prelude::*;
+    use super::super::config;
+    use super::SmithWatermanMatcher;
+
+    #[test]
+    fn test_case_sensitive_match() {
+        let matcher = SmithWatermanMatcher::default();
+        let name1 = "john doe";
+        let name2 = "JOHN DOE";
+        assert_eq!(matcher.get_score(name1, name2), 1.0);
+    }
+
+    #[test]
+    fn test_embedded_name_match() {
+        let matcher = SmithWatermanMatcher::default();
+        let name1 = "PAYMENT TO JOHN A DOE REF 123";
+        let name2 = "John Doe";
+        assert!(matcher.get_score(name1, name2) > 0.85);
+    }
+
+    #[test]
+    fn test_unrelated_names() {
+        let matcher = SmithWatermanMatcher::default();
+        let name1 = "PAYMENT TO JOHN A DOE REF 123";
+        let name2 = "Mary Smith";
+        assert!(matcher.get_score(name1, name2) < 0.5);
+    }
+
+    #[test]
+    fn test_custom_config() {
+        // Expensive gaps are no longer worth bridging the middle initial.
+        let sw_config = config::SmithWatermanConfigOptions::new(1.0, -1.0, -5.0, -1.0);
+        let matcher = SmithWatermanMatcher::new(Some(sw_config), None);
+        let name1 = "JOHN A DOE";
+        let name2 = "JOHN DOE";
+        assert_eq!(matcher.get_score(name1, name2), 5.0 / 8.0);
+    }
+
+    #[test]
+    fn test_half_weight() {
+        let matcher = SmithWatermanMatcher::new(None, Some(0.5));
+        let name1 = "JOHN DOE";
+        let name2 = "JOHN DOE";
+        assert_eq!(matcher.get_weighted_score(name1, name2), 0.5);
+    }
+}
@@ -0,0 +1,125 @@
+use super::config;
+
+// alignment_score returns the score of the best local alignment between
+// string s1 and string s2 using the Smith-Waterman algorithm with affine
+// gaps (Gotoh). A gap of length k costs gap_open + (k - 1) * gap_extend.
+//
+// Only two rows of the dynamic programming matrices are kept in memory.
+//   - h: Best alignment ending at (i, j).
+//   - e: Best alignment ending at (i, j) with a gap in s1.
+//   - f: Best alignment ending at (i, j) with a gap in s2.
+pub fn alignment_score(s1: &str, s2: &str, config: &config::SmithWatermanConfigOptions) -> f64 {
+    let chars_1: Vec<char> = s1.chars().collect();
+    let chars_2: Vec<char> = s2.chars().collect();
+    let length_2 = chars_2.len();
+
+    let mut prev_h = vec![0.0; length_2 + 1];
+    let mut prev_f = vec![f64::NEG_INFINITY; length_2 + 1];
+    let mut curr_h = vec![0.0; length_2 + 1];
+    let mut curr_f = vec![f64::NEG_INFINITY; length_2 + 1];
+    let mut best = 0.0;
+
+    for char_i in chars_1.iter() {
+        let mut e = f64::NEG_INFINITY;
+        curr_h[0] = 0.0;
+        for j in 1..=length_2 {
+            let substitution = if *char_i == chars_2[j - 1] {
+                config.match_score
+            } else {
+                config.mismatch_score
+            };
+            e = f64::max(curr_h[j - 1] + config.gap_open, e + config.gap_extend);
+            curr_f[j] = f64::max(prev_h[j] + config.gap_open, prev_f[j] + config.gap_extend);
+            let diagonal = prev_h[j - 1] + substitution;
+            let h = f64::max(0.0, f64::max(diagonal, f64::max(e, curr_f[j])));
+            curr_h[j] = h;
+            if h > best {
+                best = h;
+            }
+        }
+        std::mem::swap(&mut prev_h, &mut curr_h);
+        std::mem::swap(&mut prev_f, &mut curr_f);
+    }
+    best
+}
+
+// smith_waterman_score returns the best local alignment score normalized by
+// the self-alignment score of the shorter string, which is the highest
+// score any local alignment could reach.
+// Between 0.0 and 1.0
+pub fn smith_waterman_score(
+    s1: &str,
+    s2: &str,
+    config: &config::SmithWatermanConfigOptions,
+) -> f64 {
+    let len1 = s1.chars().count();
+    let len2 = s2.chars().count();
+    if len1 == 0 || len2 == 0 {
+        return 0.0;
+    }
+
+    let max_score = len1.min(len2) as f64 * config.match_score;
+    if max_score <= 0.0 {
+        return 0.0;
+    }
+    let score = alignment_score(s1, s2, config) / max_score;
+    score.clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::config::SmithWatermanConfigOptions;
+
+    #[test]
+    fn test_alignment_score_exact_match() {
+        let sw_config = SmithWatermanConfigOptions::default();
+        let score = super::alignment_score("JOHN", "JOHN", &sw_config);
+        assert_eq!(score, 8.0);
+    }
+
+    #[test]
+    fn test_alignment_score_no_match() {
+        let sw_config = SmithWatermanConfigOptions::default();
+        let score = super::alignment_score("ABC", "XYZ", &sw_config);
+        assert_eq!(score, 0.0);
+    }
+
+    #[test]
+    fn test_alignment_score_affine_gap() {
+        // "JOHN" + gap of 2 + " DOE" => 8 - 1.5 - 0.5 + 8
+        let sw_config = SmithWatermanConfigOptions::default();
+        let score = super::alignment_score("JOHN A DOE", "JOHN DOE", &sw_config);
+        assert_eq!(score, 14.0);
+    }
+
+    #[test]
+    fn test_alignment_score_is_symmetric() {
+        let sw_config = SmithWatermanConfigOptions::default();
+        let score1 = super::alignment_score("PAYMENT TO JOHN A DOE", "JOHN DOE", &sw_config);
+        let score2 = super::alignment_score("JOHN DOE", "PAYMENT TO JOHN A DOE", &sw_config);
+        assert_eq!(score1, score2);
+    }
+
+    #[test]
+    fn test_smith_waterman_empty_string() {
+        let sw_config = SmithWatermanConfigOptions::default();
+        let score = super::smith_waterman_score("", "JOHN", &sw_config);
+        assert_eq!(score, 0.0);
+    }
+
+    #[test]
+    fn test_smith_waterman_embedded_name() {
+        let sw_config = SmithWatermanConfigOptions::default();
+        let score = super::smith_waterman_score("PAYMENT TO JOHN DOE REF", "JOHN DOE", &sw_config);
+        assert_eq!(score, 1.0);
+    }
+
+    #[test]
+    fn test_smith_waterman_embedded_name_with_middle_initial() {
+        let sw_config = SmithWatermanConfigOptions::default();
+        let score =
+            super::smith_waterman_score("PAYMENT TO JOHN A DOE REF", "JOHN DOE", &sw_config);
+        let expected = 0.875;
+        assert!((score - expected).abs() < 0.001);
+    }
+}
@@ -0,0 +1,45 @@
+use serde::{Deserialize, Serialize};
+
+// SmithWatermanConfigOptions holds the scoring scheme used by the
+// local alignment.
+//   - match_score: Reward for aligning two identical characters. Must be positive.
+//   - mismatch_score: Penalty for aligning two different characters.
+//   - gap_open: Penalty for opening a gap.
+//   - gap_extend: Penalty for extending an existing gap by one character.
+// Penalties are expressed as negative numbers.
+// Missing fields are deserialized to their default.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct SmithWatermanConfigOptions {
+    pub match_score: f64,
+    pub mismatch_score: f64,
+    pub gap_open: f64,
+    pub gap_extend: f64,
+}
+
+impl Default for SmithWatermanConfigOptions {
+    fn default() -> SmithWatermanConfigOptions {
+        SmithWatermanConfigOptions {
+            match_score: 2.0,
+            mismatch_score: -1.0,
+            gap_open: -1.5,
+            gap_extend: -0.5,
+        }
+    }
+}
+
+impl SmithWatermanConfigOptions {
+    pub fn new(
+        match_score: f64,
+        mismatch_score: f64,
+        gap_open: f64,
+        gap_extend: f64,
+    ) -> SmithWatermanConfigOptions {
+        SmithWatermanConfigOptions {
+            match_score,
+            mismatch_score,
+            gap_open,
+            gap_extend,
+        }
+    }
+}
@@ -0,0 +1,3 @@
+pub mod compare;
+mod compute;
+pub mod config;
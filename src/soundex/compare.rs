@@ -1,5 +1,7 @@
 use super::super::prelude::*;
 use super::super::shared::compute;
+use super::super::shared::tokenize;
+use super::super::spec::with_tokenization;
 use super::encode;
 use std::collections::HashSet;
 use std::sync::RwLock;
@@ -29,6 +31,11 @@ impl SoundexMatcher {
             weight: locked_weight,
         }
     }
+
+    pub fn from_spec(spec: &MatcherSpec) -> Result<SoundexMatcher, SpecError> {
+        spec.check_params(&[])?;
+        Ok(SoundexMatcher::new(spec.weight))
+    }
 }
 
 impl Named for SoundexMatcher {
@@ -63,6 +70,14 @@ impl Matcher for SoundexMatcher {
             0.0
         }
     }
+
+    fn get_prepared_score(&self, p1: &PreparedName, p2: &PreparedName) -> f64 {
+        if p1.soundex() == p2.soundex() {
+            1.0
+        } else {
+            0.0
+        }
+    }
 }
 
 // SoundexJaccardMatcher is an improvement over SoundexMatcher, handles token
@@ -77,6 +92,7 @@ impl Matcher for SoundexMatcher {
 pub struct SoundexJaccardMatcher {
     name: String,
     weight: f64,
+    tokenization: Tokenization,
 }
 
 impl SoundexJaccardMatcher {
@@ -85,6 +101,7 @@ impl SoundexJaccardMatcher {
         SoundexJaccardMatcher {
             name: "Soundex-Jaccard".to_owned(),
             weight,
+            tokenization: Tokenization::Whitespace,
         }
     }
 
@@ -92,6 +109,7 @@ impl SoundexJaccardMatcher {
         SoundexJaccardMatcher {
             name: "Soundex-Jaccard".to_owned(),
             weight: 1.0,
+            tokenization: Tokenization::Whitespace,
         }
     }
 
@@ -100,7 +118,7 @@ impl SoundexJaccardMatcher {
     // Eg:
     //   "James" -> ["J520"]
     //   "James Bond" -> ["J520", "B530"]
-    fn as_tokenized_set(&self, name: &str) -> HashSet<String> {
+    pub fn as_tokenized_set(&self, name: &str) -> HashSet<String> {
         let mut soundex_set: HashSet<String> = HashSet::new();
         for token in name.split_whitespace() {
             let soundex_code = encode::apply_soundex(token);
@@ -108,6 +126,24 @@ impl SoundexJaccardMatcher {
         }
         soundex_set
     }
+
+    fn as_soundex_set(&self, tokens: &[String]) -> HashSet<String> {
+        tokens
+            .iter()
+            .map(|token| encode::apply_soundex(&token[..]))
+            .collect()
+    }
+
+    // from_spec builds the matcher described by spec, tokenization is
+    // Whitespace unless given.
+    pub fn from_spec(spec: &MatcherSpec) -> Result<SoundexJaccardMatcher, SpecError> {
+        spec.check_params(&["tokenization"])?;
+        let mut matcher = SoundexJaccardMatcher::new(spec.weight);
+        if let Some(tokenization) = spec.tokenization_param("tokenization")? {
+            matcher.set_tokenization(tokenization);
+        }
+        Ok(matcher)
+    }
 }
 
 impl Named for SoundexJaccardMatcher {
@@ -118,6 +154,16 @@ impl Named for SoundexJaccardMatcher {
 
 impl Clean for SoundexJaccardMatcher {}
 
+impl Tokenized for SoundexJaccardMatcher {
+    fn get_tokenization(&self) -> Tokenization {
+        self.tokenization
+    }
+
+    fn set_tokenization(&mut self, tokenization: Tokenization) {
+        self.tokenization = tokenization;
+    }
+}
+
 impl Weighted for SoundexJaccardMatcher {
     fn get_weight(&self) -> f64 {
         self.weight
@@ -128,14 +174,40 @@ impl Weighted for SoundexJaccardMatcher {
     }
 }
 
+impl SoundexJaccardMatcher {
+    fn segmented_score(&self, cleaned_s1: &str, cleaned_s2: &str) -> f64 {
+        let tokens_1 = tokenize::tokenize(cleaned_s1);
+        let tokens_2 = tokenize::tokenize(cleaned_s2);
+        let (score, _, _) = tokenize::best_segmentation(&tokens_1, &tokens_2, |t1, t2| {
+            compute::jaccard_index(&self.as_soundex_set(t1), &self.as_soundex_set(t2))
+        });
+        score
+    }
+}
+
 impl Matcher for SoundexJaccardMatcher {
     fn get_score(&self, s1: &str, s2: &str) -> f64 {
         let cleaned_s1 = self.clean(s1);
-        let name_1_soundex_set = self.as_tokenized_set(&cleaned_s1[..]);
         let cleaned_s2 = self.clean(s2);
+        if self.tokenization == Tokenization::Segmented {
+            return self.segmented_score(&cleaned_s1[..], &cleaned_s2[..]);
+        }
+        let name_1_soundex_set = self.as_tokenized_set(&cleaned_s1[..]);
         let name_2_soundex_set = self.as_tokenized_set(&cleaned_s2[..]);
         compute::jaccard_index(&name_1_soundex_set, &name_2_soundex_set)
     }
+
+    fn get_prepared_score(&self, p1: &PreparedName, p2: &PreparedName) -> f64 {
+        if self.tokenization == Tokenization::Segmented {
+            return self.segmented_score(p1.cleaned(), p2.cleaned());
+        }
+        compute::jaccard_index(p1.soundex_tokens(), p2.soundex_tokens())
+    }
+
+    fn get_spec(&self) -> MatcherSpec {
+        let spec = MatcherSpec::new(self.get_name()).with_weight(self.get_weight());
+        with_tokenization(spec, self.tokenization)
+    }
 }
 
 #[cfg(test)]
@@ -232,4 +304,13 @@ mod test {
         let score = matcher.get_weighted_score(name1, name2);
         assert_eq!(score, 0.5);
     }
+
+    #[test]
+    fn test_soundex_jaccard_segmented_split_name() {
+        let mut matcher = SoundexJaccardMatcher::default();
+        matcher.set_tokenization(Tokenization::Segmented);
+        let name1 = "Ronald McDonald";
+        let name2 = "Ronald Mc Donald";
+        assert_eq!(matcher.get_score(name1, name2), 1.0);
+    }
 }
@@ -1,2 +1,2 @@
 pub mod compare;
-mod encode;
+pub mod encode;
@@ -0,0 +1,524 @@
+use super::ensemble::Aggregation;
+use super::prelude::*;
+use super::registry::MatcherRegistry;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+// ParamValue is the value of a matcher parameter, as written in a config
+// file. Integers are accepted where floats are expected.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum ParamValue {
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    Text(String),
+    List(Vec<ParamValue>),
+}
+
+pub type Params = BTreeMap<String, ParamValue>;
+
+// MatcherSpec describes a matcher by its type name, as registered in
+// MatcherRegistry, along with its weight and parameters.
+// Wrappers such as Token-Sort describe the wrapped matcher in matcher.
+// The wrapped matcher may also be given in the type name,
+// eg "Token-Sort(Jaro-Winkler)".
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MatcherSpec {
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weight: Option<f64>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub params: Params,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub matcher: Option<Box<MatcherSpec>>,
+}
+
+impl MatcherSpec {
+    pub fn new(kind: &str) -> MatcherSpec {
+        MatcherSpec {
+            kind: kind.to_owned(),
+            weight: None,
+            params: Params::new(),
+            matcher: None,
+        }
+    }
+
+    pub fn with_weight(mut self, weight: f64) -> MatcherSpec {
+        self.weight = Some(weight);
+        self
+    }
+
+    pub fn with_param(mut self, name: &str, value: ParamValue) -> MatcherSpec {
+        self.params.insert(name.to_owned(), value);
+        self
+    }
+
+    pub fn with_matcher(mut self, matcher: MatcherSpec) -> MatcherSpec {
+        self.matcher = Some(Box::new(matcher));
+        self
+    }
+
+    // build returns the matcher described by the spec, using the built-in
+    // matchers of MatcherRegistry.
+    pub fn build(&self) -> Result<Box<dyn Matcher>, SpecError> {
+        MatcherRegistry::builtin().build(self)
+    }
+
+    // check_params returns an error for parameters not in names.
+    pub fn check_params(&self, names: &[&str]) -> Result<(), SpecError> {
+        match self
+            .params
+            .keys()
+            .find(|name| !names.contains(&name.as_str()))
+        {
+            Some(name) => Err(self.invalid_param(name, "unknown parameter")),
+            None => Ok(()),
+        }
+    }
+
+    pub fn invalid_param(&self, name: &str, reason: &str) -> SpecError {
+        SpecError::InvalidParam {
+            matcher: self.kind.to_owned(),
+            param: name.to_owned(),
+            reason: reason.to_owned(),
+        }
+    }
+
+    pub fn f64_param(&self, name: &str) -> Result<Option<f64>, SpecError> {
+        match self.params.get(name) {
+            None => Ok(None),
+            Some(ParamValue::Float(value)) => Ok(Some(*value)),
+            Some(ParamValue::Integer(value)) => Ok(Some(*value as f64)),
+            Some(_) => Err(self.invalid_param(name, "expected a number")),
+        }
+    }
+
+    pub fn usize_param(&self, name: &str) -> Result<Option<usize>, SpecError> {
+        match self.params.get(name) {
+            None => Ok(None),
+            Some(ParamValue::Integer(value)) if *value >= 0 => Ok(Some(*value as usize)),
+            Some(_) => Err(self.invalid_param(name, "expected a non-negative integer")),
+        }
+    }
+
+    pub fn str_param(&self, name: &str) -> Result<Option<&str>, SpecError> {
+        match self.params.get(name) {
+            None => Ok(None),
+            Some(ParamValue::Text(value)) => Ok(Some(&value[..])),
+            Some(_) => Err(self.invalid_param(name, "expected a string")),
+        }
+    }
+
+    pub fn list_param(&self, name: &str) -> Result<Option<&[ParamValue]>, SpecError> {
+        match self.params.get(name) {
+            None => Ok(None),
+            Some(ParamValue::List(values)) => Ok(Some(&values[..])),
+            Some(_) => Err(self.invalid_param(name, "expected a list")),
+        }
+    }
+
+    // tokenization_param reads a Tokenization, "Whitespace" or "Segmented".
+    pub fn tokenization_param(&self, name: &str) -> Result<Option<Tokenization>, SpecError> {
+        match self.str_param(name)?.map(normalize).as_deref() {
+            None => Ok(None),
+            Some("whitespace") => Ok(Some(Tokenization::Whitespace)),
+            Some("segmented") => Ok(Some(Tokenization::Segmented)),
+            Some(_) => Err(self.invalid_param(name, "expected Whitespace or Segmented")),
+        }
+    }
+}
+
+// with_tokenization adds the tokenization parameter when it is not the
+// default one.
+pub(crate) fn with_tokenization(spec: MatcherSpec, tokenization: Tokenization) -> MatcherSpec {
+    match tokenization {
+        Tokenization::Whitespace => spec,
+        Tokenization::Segmented => {
+            spec.with_param("tokenization", ParamValue::Text("Segmented".to_owned()))
+        }
+    }
+}
+
+// normalize ignores case and punctuation of type names, so that
+// jaro-winkler, JaroWinkler and Jaro-Winkler are the same type.
+pub(crate) fn normalize(kind: &str) -> String {
+    kind.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
+
+// EnsembleSpec describes an Ensemble, so that it can be kept in a config
+// file. See Ensemble::from_spec and Ensemble::to_spec.
+// Cleaning of names is fixed and shared by all matchers, so it is not
+// described.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct EnsembleSpec {
+    pub matchers: Vec<MatcherSpec>,
+    #[serde(default)]
+    pub aggregation: Aggregation,
+}
+
+impl EnsembleSpec {
+    #[cfg(feature = "json")]
+    pub fn from_json(content: &str) -> Result<EnsembleSpec, SpecError> {
+        serde_json::from_str(content).map_err(|err| SpecError::Format(err.to_string()))
+    }
+
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> Result<String, SpecError> {
+        serde_json::to_string_pretty(self).map_err(|err| SpecError::Format(err.to_string()))
+    }
+
+    #[cfg(feature = "toml")]
+    pub fn from_toml(content: &str) -> Result<EnsembleSpec, SpecError> {
+        toml::from_str(content).map_err(|err| SpecError::Format(err.to_string()))
+    }
+
+    #[cfg(feature = "toml")]
+    pub fn to_toml(&self) -> Result<String, SpecError> {
+        toml::to_string_pretty(self).map_err(|err| SpecError::Format(err.to_string()))
+    }
+
+    #[cfg(feature = "yaml")]
+    pub fn from_yaml(content: &str) -> Result<EnsembleSpec, SpecError> {
+        serde_norway::from_str(content).map_err(|err| SpecError::Format(err.to_string()))
+    }
+
+    #[cfg(feature = "yaml")]
+    pub fn to_yaml(&self) -> Result<String, SpecError> {
+        serde_norway::to_string(self).map_err(|err| SpecError::Format(err.to_string()))
+    }
+
+    // parse reads a spec in format, "json", "toml", "yaml" or "yml".
+    // Formats are enabled by the features of the same name.
+    #[cfg_attr(
+        not(any(feature = "json", feature = "toml", feature = "yaml")),
+        allow(unused_variables)
+    )]
+    pub fn parse(content: &str, format: &str) -> Result<EnsembleSpec, SpecError> {
+        match &format.to_lowercase()[..] {
+            #[cfg(feature = "json")]
+            "json" => EnsembleSpec::from_json(content),
+            #[cfg(feature = "toml")]
+            "toml" => EnsembleSpec::from_toml(content),
+            #[cfg(feature = "yaml")]
+            "yaml" | "yml" => EnsembleSpec::from_yaml(content),
+            _ => Err(SpecError::UnsupportedFormat(format.to_owned())),
+        }
+    }
+
+    // format writes the spec in format, see parse.
+    pub fn format(&self, format: &str) -> Result<String, SpecError> {
+        match &format.to_lowercase()[..] {
+            #[cfg(feature = "json")]
+            "json" => self.to_json(),
+            #[cfg(feature = "toml")]
+            "toml" => self.to_toml(),
+            #[cfg(feature = "yaml")]
+            "yaml" | "yml" => self.to_yaml(),
+            _ => Err(SpecError::UnsupportedFormat(format.to_owned())),
+        }
+    }
+
+    // load reads a spec from a file, in the format given by its extension.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<EnsembleSpec, SpecError> {
+        let content = std::fs::read_to_string(path.as_ref())?;
+        EnsembleSpec::parse(&content, extension(path.as_ref()))
+    }
+
+    // save writes the spec to a file, in the format given by its extension.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), SpecError> {
+        let content = self.format(extension(path.as_ref()))?;
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+}
+
+fn extension(path: &Path) -> &str {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or("")
+}
+
+// SpecError is returned when a spec can not be read or describes an
+// invalid matcher.
+#[derive(Debug)]
+pub enum SpecError {
+    Io(io::Error),
+    Format(String),
+    UnsupportedFormat(String),
+    UnknownMatcher(String),
+    MissingMatcher(String),
+    InvalidParam {
+        matcher: String,
+        param: String,
+        reason: String,
+    },
+}
+
+impl fmt::Display for SpecError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SpecError::Io(err) => write!(f, "io error: {}", err),
+            SpecError::Format(reason) => write!(f, "invalid spec: {}", reason),
+            SpecError::UnsupportedFormat(extension) => {
+                write!(f, "unsupported spec format \"{}\"", extension)
+            }
+            SpecError::UnknownMatcher(kind) => write!(f, "unknown matcher {}", kind),
+            SpecError::MissingMatcher(kind) => write!(f, "{} needs a wrapped matcher", kind),
+            SpecError::InvalidParam {
+                matcher,
+                param,
+                reason,
+            } => write!(f, "invalid parameter {} of {}: {}", param, matcher, reason),
+        }
+    }
+}
+
+impl std::error::Error for SpecError {}
+
+impl From<io::Error> for SpecError {
+    fn from(err: io::Error) -> SpecError {
+        SpecError::Io(err)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::ensemble::{Aggregation, Ensemble};
+    use super::super::jaro::compare::JaroWinklerMatcher;
+    use super::super::jaro::config::JaroWinklerConfigOptions;
+    use super::super::jaro::similar::SimilarCharTable;
+    use super::super::prelude::*;
+    use super::super::smith_waterman::compare::SmithWatermanMatcher;
+    use super::super::smith_waterman::config::SmithWatermanConfigOptions;
+    use super::super::soundex::compare::SoundexJaccardMatcher;
+    use super::super::token::compare::{PartialMatcher, TokenSortMatcher};
+    use super::{EnsembleSpec, MatcherSpec, ParamValue, SpecError};
+
+    const NAMES: [(&str, &str); 4] = [
+        ("John Doe", "Jon Doe"),
+        ("Doe John", "John Doe"),
+        ("Mary Ann Smith", "Maryann Smith"),
+        ("Peter Parker", "Pete Parkr"),
+    ];
+
+    fn build_ensemble() -> Ensemble {
+        let mut config = JaroWinklerConfigOptions::new(0.6, 3, 0.15);
+        config.max_permutation_tokens = Some(3);
+        config.similar_chars = Some(SimilarCharTable::new(vec![('N', 'M')], 0.4));
+        let mut soundex_jaccard = SoundexJaccardMatcher::new(Some(0.5));
+        soundex_jaccard.set_tokenization(Tokenization::Segmented);
+        let smith_waterman = SmithWatermanMatcher::new(
+            Some(SmithWatermanConfigOptions::new(3.0, -2.0, -2.0, -1.0)),
+            None,
+        );
+        let matchers: Vec<Box<dyn Matcher>> = vec![
+            Box::new(JaroWinklerMatcher::new(Some(config), Some(2.0))),
+            Box::new(soundex_jaccard),
+            Box::new(smith_waterman),
+            Box::new(TokenSortMatcher::new(
+                Box::new(PartialMatcher::new(
+                    Box::new(JaroWinklerMatcher::default()),
+                    None,
+                )),
+                Some(0.25),
+            )),
+        ];
+        let mut ensemble = Ensemble::new(matchers);
+        ensemble.aggregation = Aggregation::WeightedMean;
+        ensemble
+    }
+
+    fn assert_same_scores(ensemble: &Ensemble, other: &Ensemble) {
+        for (name1, name2) in NAMES.iter() {
+            let expected = ensemble.get_ensemble_result(name1, name2);
+            let result = other.get_ensemble_result(name1, name2);
+            assert_eq!(result.score, expected.score);
+            for (result, expected) in result.results.iter().zip(expected.results.iter()) {
+                assert_eq!(result.algorithm, expected.algorithm);
+                assert_eq!(result.weighted_score, expected.weighted_score);
+            }
+        }
+    }
+
+    #[test]
+    fn test_spec_round_trip() {
+        let ensemble = build_ensemble();
+        let spec = ensemble.to_spec();
+        let rebuilt = Ensemble::from_spec(&spec).unwrap();
+        assert_eq!(rebuilt.aggregation, Aggregation::WeightedMean);
+        assert_eq!(rebuilt.to_spec(), spec);
+        assert_same_scores(&ensemble, &rebuilt);
+    }
+
+    #[test]
+    #[cfg(all(feature = "json", feature = "toml", feature = "yaml"))]
+    fn test_spec_formats_round_trip() {
+        let spec = build_ensemble().to_spec();
+        assert_eq!(
+            EnsembleSpec::from_json(&spec.to_json().unwrap()).unwrap(),
+            spec
+        );
+        assert_eq!(
+            EnsembleSpec::from_toml(&spec.to_toml().unwrap()).unwrap(),
+            spec
+        );
+        assert_eq!(
+            EnsembleSpec::from_yaml(&spec.to_yaml().unwrap()).unwrap(),
+            spec
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn test_spec_from_json() {
+        let spec = EnsembleSpec::from_json(
+            r#"{
+                "matchers": [
+                    {"type": "jaro-winkler", "weight": 0.5, "params": {"scaling_factor": 0.2}},
+                    {"type": "Token-Set(Soundex)", "weight": 0.5}
+                ]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(spec.aggregation, Aggregation::WeightedSum);
+        let ensemble = Ensemble::from_spec(&spec).unwrap();
+        assert_eq!(ensemble.matchers[0].get_name(), "Jaro-Winkler");
+        assert_eq!(ensemble.matchers[1].get_name(), "Token-Set(Soundex)");
+        let mut config = JaroWinklerConfigOptions::default();
+        config.scaling_factor = 0.2;
+        let expected = JaroWinklerMatcher::new(Some(config), None);
+        assert_eq!(
+            ensemble.matchers[0].get_score("Martha", "Marhta"),
+            expected.get_score("Martha", "Marhta")
+        );
+        assert!(matches!(
+            EnsembleSpec::from_json("{\"matchers\": 1}"),
+            Err(SpecError::Format(_))
+        ));
+    }
+
+    #[test]
+    #[cfg(all(feature = "toml", feature = "yaml"))]
+    fn test_spec_from_toml_and_yaml() {
+        let toml = r#"
+            aggregation = "Max"
+
+            [[matchers]]
+            type = "Levenshtein"
+
+            [[matchers]]
+            type = "Partial"
+            weight = 2
+            matcher = { type = "Jaccard", params = { tokenization = "Segmented" } }
+        "#;
+        let yaml = r#"
+            aggregation: Max
+            matchers:
+              - type: Levenshtein
+              - type: Partial
+                weight: 2
+                matcher:
+                  type: Jaccard
+                  params:
+                    tokenization: Segmented
+        "#;
+        let spec = EnsembleSpec::from_toml(toml).unwrap();
+        assert_eq!(EnsembleSpec::from_yaml(yaml).unwrap(), spec);
+        let ensemble = Ensemble::from_spec(&spec).unwrap();
+        assert_eq!(ensemble.aggregation, Aggregation::Max);
+        assert_eq!(ensemble.matchers[1].get_name(), "Partial(Jaccard)");
+        assert_eq!(ensemble.matchers[1].get_weight(), 2.0);
+    }
+
+    #[test]
+    fn test_spec_errors() {
+        let build = |spec: MatcherSpec| spec.build().err().unwrap();
+        assert!(matches!(
+            build(MatcherSpec::new("Metaphone")),
+            SpecError::UnknownMatcher(_)
+        ));
+        assert!(matches!(
+            build(MatcherSpec::new("Token-Sort")),
+            SpecError::MissingMatcher(_)
+        ));
+        let err =
+            build(MatcherSpec::new("Jaro-Winkler").with_param("scaling", ParamValue::Float(0.2)));
+        assert_eq!(
+            err.to_string(),
+            "invalid parameter scaling of Jaro-Winkler: unknown parameter"
+        );
+        let err = build(
+            MatcherSpec::new("Jaro-Winkler")
+                .with_param("similarity_threshold", ParamValue::Text("high".to_owned())),
+        );
+        assert!(matches!(err, SpecError::InvalidParam { .. }));
+        let err = build(
+            MatcherSpec::new("Jaro-Winkler")
+                .with_param("scaling_factor", ParamValue::Float(0.3))
+                .with_param("max_prefix_length", ParamValue::Integer(4)),
+        );
+        assert!(matches!(err, SpecError::InvalidParam { .. }));
+        let err = build(
+            MatcherSpec::new("Jaro-Winkler")
+                .with_param("max_prefix_length", ParamValue::Integer(1 << 40)),
+        );
+        assert!(matches!(err, SpecError::InvalidParam { .. }));
+        let err = build(
+            MatcherSpec::new("Jaro-Winkler")
+                .with_param("similar_chars", ParamValue::Text("census".to_owned()))
+                .with_param("similar_credit", ParamValue::Float(1.5)),
+        );
+        assert!(matches!(err, SpecError::InvalidParam { .. }));
+        let err = build(
+            MatcherSpec::new("Jaccard")
+                .with_param("tokenization", ParamValue::Text("Trigrams".to_owned())),
+        );
+        assert!(matches!(err, SpecError::InvalidParam { .. }));
+        let err = build(MatcherSpec::new("Soundex").with_matcher(MatcherSpec::new("Jaccard")));
+        assert!(matches!(err, SpecError::InvalidParam { .. }));
+        assert!(matches!(
+            EnsembleSpec::parse("", "ini"),
+            Err(SpecError::UnsupportedFormat(_))
+        ));
+    }
+
+    #[test]
+    fn test_similar_chars_census() {
+        let spec = MatcherSpec::new("Jaro-Winkler")
+            .with_param("similar_chars", ParamValue::Text("census".to_owned()));
+        let matcher = spec.build().unwrap();
+        let mut config = JaroWinklerConfigOptions::default();
+        config.similar_chars = Some(SimilarCharTable::census());
+        let expected = JaroWinklerMatcher::new(Some(config), None);
+        assert_eq!(
+            matcher.get_score("Dwayne", "Duane"),
+            expected.get_score("Dwayne", "Duane")
+        );
+    }
+
+    #[test]
+    #[cfg(all(feature = "json", feature = "toml", feature = "yaml"))]
+    fn test_save_and_load() {
+        let spec = build_ensemble().to_spec();
+        let dir = std::env::temp_dir();
+        for extension in ["json", "toml", "yaml"] {
+            let path = dir.join(format!(
+                "name_match_spec_{}.{}",
+                std::process::id(),
+                extension
+            ));
+            spec.save(&path).unwrap();
+            let loaded = EnsembleSpec::load(&path);
+            std::fs::remove_file(&path).unwrap();
+            assert_eq!(loaded.unwrap(), spec);
+        }
+    }
+}
@@ -0,0 +1,311 @@
+use super::ensemble::{Ensemble, EnsembleQuery, EnsembleResult};
+use super::prelude::*;
+use rayon::iter::ParallelBridge;
+use rayon::prelude::*;
+use std::collections::VecDeque;
+use std::io::{self, BufRead};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+// Streamer compares a query against names read lazily from an iterator or
+// a reader, without holding every name or result in memory.
+// Names are consumed in chunks of chunk_size, each chunk scored in parallel.
+//   - preserve_order: Results are yielded in input order. Otherwise names
+//     are read by the workers while others are being scored, instead of
+//     the whole chunk being read first. Results of a chunk are still
+//     yielded once the whole chunk is scored, in no particular order.
+//   - min_score: Only results scoring at least min_score are yielded.
+pub struct Streamer {
+    pub chunk_size: usize,
+    pub preserve_order: bool,
+    pub min_score: Option<f64>,
+}
+
+impl Default for Streamer {
+    fn default() -> Streamer {
+        Streamer::new()
+    }
+}
+
+impl Streamer {
+    pub fn new() -> Streamer {
+        Streamer {
+            chunk_size: 4096,
+            preserve_order: true,
+            min_score: None,
+        }
+    }
+
+    // results returns the EnsembleResult between query_name and every name,
+    // along with the position of the name in the input.
+    pub fn results<'a, I>(
+        &self,
+        ensemble: &'a Ensemble,
+        query_name: &str,
+        names: I,
+    ) -> ResultStream<'a, I::IntoIter>
+    where
+        I: IntoIterator,
+    {
+        ResultStream {
+            query: ensemble.prepare_query(query_name),
+            source: names.into_iter(),
+            chunk_size: self.chunk_size.max(1),
+            preserve_order: self.preserve_order,
+            min_score: self.min_score,
+            next_index: 0,
+            buffer: VecDeque::new(),
+            exhausted: false,
+        }
+    }
+
+    // lines is results over a reader holding one name per line.
+    // Reading stops at the first error, which is yielded after the results
+    // of the lines read before it.
+    pub fn lines<'a, R: BufRead>(
+        &self,
+        ensemble: &'a Ensemble,
+        query_name: &str,
+        reader: R,
+    ) -> LineStream<'a, R> {
+        let error = Arc::new(Mutex::new(None));
+        let names = ReaderNames {
+            lines: reader.lines(),
+            error: Arc::clone(&error),
+            failed: false,
+        };
+        LineStream {
+            results: self.results(ensemble, query_name, names),
+            error,
+        }
+    }
+}
+
+// ResultStream yields (position, EnsembleResult) for every name of source.
+pub struct ResultStream<'a, I> {
+    query: EnsembleQuery<'a>,
+    source: I,
+    chunk_size: usize,
+    preserve_order: bool,
+    min_score: Option<f64>,
+    next_index: usize,
+    buffer: VecDeque<(usize, EnsembleResult)>,
+    exhausted: bool,
+}
+
+impl<'a, I> ResultStream<'a, I>
+where
+    I: Iterator + Send,
+    I::Item: AsRef<str> + Send,
+{
+    // fill scores the next chunk of names into the buffer.
+    fn fill(&mut self) {
+        let offset = self.next_index;
+        let query = &self.query;
+        let min_score = self.min_score;
+        let score = |(i, name): (usize, I::Item)| {
+            let name = PreparedName::new(name.as_ref());
+            let result = match min_score {
+                Some(min_score) => query.get_ensemble_result_above(&name, min_score)?,
+                None => query.get_ensemble_result(&name),
+            };
+            Some((offset + i, result))
+        };
+
+        let source = self.source.by_ref().take(self.chunk_size).enumerate();
+        let read = AtomicUsize::new(0);
+        let results: Vec<(usize, EnsembleResult)> = if self.preserve_order {
+            let names: Vec<(usize, I::Item)> = source.collect();
+            read.store(names.len(), Ordering::Relaxed);
+            names.into_par_iter().filter_map(score).collect()
+        } else {
+            source
+                .par_bridge()
+                .filter_map(|item| {
+                    read.fetch_add(1, Ordering::Relaxed);
+                    score(item)
+                })
+                .collect()
+        };
+        let read = read.into_inner();
+        self.next_index += read;
+        self.exhausted = read < self.chunk_size;
+        self.buffer.extend(results);
+    }
+}
+
+impl<'a, I> Iterator for ResultStream<'a, I>
+where
+    I: Iterator + Send,
+    I::Item: AsRef<str> + Send,
+{
+    type Item = (usize, EnsembleResult);
+
+    fn next(&mut self) -> Option<(usize, EnsembleResult)> {
+        loop {
+            if let Some(result) = self.buffer.pop_front() {
+                return Some(result);
+            }
+            if self.exhausted {
+                return None;
+            }
+            self.fill();
+        }
+    }
+}
+
+// ReaderNames yields the lines of a reader until the first error, which is
+// kept for LineStream.
+struct ReaderNames<R> {
+    lines: io::Lines<R>,
+    error: Arc<Mutex<Option<io::Error>>>,
+    failed: bool,
+}
+
+impl<R: BufRead> Iterator for ReaderNames<R> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        if self.failed {
+            return None;
+        }
+        match self.lines.next()? {
+            Ok(line) => Some(line),
+            Err(error) => {
+                self.failed = true;
+                *self.error.lock().unwrap() = Some(error);
+                None
+            }
+        }
+    }
+}
+
+// LineStream yields (line number, EnsembleResult) for every line of a
+// reader, line numbers starting at 0.
+pub struct LineStream<'a, R> {
+    results: ResultStream<'a, ReaderNames<R>>,
+    error: Arc<Mutex<Option<io::Error>>>,
+}
+
+impl<'a, R: BufRead + Send> Iterator for LineStream<'a, R> {
+    type Item = io::Result<(usize, EnsembleResult)>;
+
+    fn next(&mut self) -> Option<io::Result<(usize, EnsembleResult)>> {
+        if let Some(result) = self.results.next() {
+            return Some(Ok(result));
+        }
+        self.error.lock().unwrap().take().map(Err)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::ensemble::Ensemble;
+    use super::super::jaro::compare::JaroWinklerMatcher;
+    use super::super::prelude::*;
+    use super::super::soundex::compare::SoundexMatcher;
+    use super::Streamer;
+    use std::io::{self, Cursor, Read};
+
+    const NAMES: [&str; 7] = [
+        "Jon Doe",
+        "Mary Smith",
+        "John Doe",
+        "Jane Doe",
+        "Peter Parker",
+        "Doe John",
+        "Johnny Doe",
+    ];
+
+    fn build_ensemble() -> Ensemble {
+        let matchers: Vec<Box<dyn Matcher>> = vec![
+            Box::new(JaroWinklerMatcher::default()),
+            Box::new(SoundexMatcher::default()),
+        ];
+        let mut ensemble = Ensemble::new(matchers);
+        ensemble.set_equal_weight();
+        ensemble
+    }
+
+    // Small chunks so that every test crosses chunk boundaries.
+    fn small_chunks() -> Streamer {
+        let mut streamer = Streamer::new();
+        streamer.chunk_size = 3;
+        streamer
+    }
+
+    #[test]
+    fn test_results_preserve_order() {
+        let ensemble = build_ensemble();
+        let expected = ensemble.get_ensemble_result_arr("John Doe", NAMES.to_vec());
+        let results: Vec<_> = small_chunks()
+            .results(&ensemble, "John Doe", NAMES.iter())
+            .collect();
+        assert_eq!(results.len(), NAMES.len());
+        for (i, (index, result)) in results.iter().enumerate() {
+            assert_eq!(*index, i);
+            assert_eq!(result.name2, expected[i].name2);
+            assert_eq!(result.score, expected[i].score);
+        }
+    }
+
+    #[test]
+    fn test_results_unordered() {
+        let ensemble = build_ensemble();
+        let mut streamer = small_chunks();
+        streamer.preserve_order = false;
+        let names = NAMES.iter().map(|name| name.to_string());
+        let mut results: Vec<_> = streamer.results(&ensemble, "John Doe", names).collect();
+        results.sort_by_key(|(index, _)| *index);
+        assert_eq!(results.len(), NAMES.len());
+        for (i, (index, result)) in results.iter().enumerate() {
+            assert_eq!(*index, i);
+            assert_eq!(result.name2, NAMES[i]);
+        }
+    }
+
+    #[test]
+    fn test_results_min_score() {
+        let ensemble = build_ensemble();
+        let mut streamer = small_chunks();
+        streamer.min_score = Some(0.9);
+        let indices: Vec<usize> = streamer
+            .results(&ensemble, "John Doe", NAMES.iter())
+            .map(|(index, _)| index)
+            .collect();
+        let expected: Vec<usize> = (0..NAMES.len())
+            .filter(|i| ensemble.get_aggregated_score("John Doe", NAMES[*i]) >= 0.9)
+            .collect();
+        assert!(expected.len() < NAMES.len());
+        assert_eq!(indices, expected);
+    }
+
+    #[test]
+    fn test_lines() {
+        let ensemble = build_ensemble();
+        let reader = Cursor::new(NAMES.join("\n"));
+        let results: Vec<_> = small_chunks()
+            .lines(&ensemble, "John Doe", reader)
+            .collect::<io::Result<_>>()
+            .unwrap();
+        assert_eq!(results.len(), NAMES.len());
+        assert_eq!(results[2].1.name2, "John Doe");
+        assert_eq!(results[2].1.score, 1.0);
+    }
+
+    #[test]
+    fn test_lines_error() {
+        let ensemble = build_ensemble();
+        let mut bytes = b"Jon Doe\nJohn Doe\n".to_vec();
+        bytes.extend_from_slice(&[0xff, 0xfe, b'\n']);
+        bytes.extend_from_slice(b"Jane Doe\n");
+        let reader = io::BufReader::new(Cursor::new(bytes).take(1 << 20));
+        let results: Vec<_> = small_chunks()
+            .lines(&ensemble, "John Doe", reader)
+            .collect();
+        assert_eq!(results.len(), 3);
+        assert!(results[0].is_ok());
+        assert!(results[1].is_ok());
+        assert!(results[2].is_err());
+    }
+}
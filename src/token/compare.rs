@@ -0,0 +1,383 @@
+use super::super::prelude::*;
+use super::super::shared::tokenize;
+use super::super::spec::with_tokenization;
+use super::compute;
+use std::sync::RwLock;
+
+// TokenSortMatcher wraps any Matcher and sorts the tokens of both names
+// alphabetically before comparing them, so that word order no longer matters.
+// Eg:
+// "Doe John" vs "John Doe"
+// -> "DOE JOHN" vs "DOE JOHN"
+pub struct TokenSortMatcher {
+    name: String,
+    matcher: Box<dyn Matcher>,
+    weight: RwLock<f64>,
+    tokenization: Tokenization,
+}
+
+impl TokenSortMatcher {
+    pub fn new(matcher: Box<dyn Matcher>, weight: Option<f64>) -> TokenSortMatcher {
+        let weight = weight.unwrap_or(1.0);
+        let locked_weight = RwLock::new(weight);
+        TokenSortMatcher {
+            name: format!("Token-Sort({})", matcher.get_name()),
+            matcher,
+            weight: locked_weight,
+            tokenization: Tokenization::Whitespace,
+        }
+    }
+
+    fn score_tokens(&self, tokens_1: &[String], tokens_2: &[String]) -> f64 {
+        let sorted_s1 = compute::sort_tokens(&tokens_1.join(" ")[..]);
+        let sorted_s2 = compute::sort_tokens(&tokens_2.join(" ")[..]);
+        self.matcher.get_score(&sorted_s1[..], &sorted_s2[..])
+    }
+
+    // from_spec builds the matcher described by spec around the matcher
+    // wrapped by spec, tokenization is Whitespace unless given.
+    pub fn from_spec(
+        spec: &MatcherSpec,
+        matcher: Box<dyn Matcher>,
+    ) -> Result<TokenSortMatcher, SpecError> {
+        spec.check_params(&["tokenization"])?;
+        let mut wrapper = TokenSortMatcher::new(matcher, spec.weight);
+        if let Some(tokenization) = spec.tokenization_param("tokenization")? {
+            wrapper.set_tokenization(tokenization);
+        }
+        Ok(wrapper)
+    }
+}
+
+impl Named for TokenSortMatcher {
+    fn get_name(&self) -> &str {
+        &self.name[..]
+    }
+}
+
+impl Clean for TokenSortMatcher {}
+
+impl Tokenized for TokenSortMatcher {
+    fn get_tokenization(&self) -> Tokenization {
+        self.tokenization
+    }
+
+    fn set_tokenization(&mut self, tokenization: Tokenization) {
+        self.tokenization = tokenization;
+    }
+}
+
+impl Weighted for TokenSortMatcher {
+    fn get_weight(&self) -> f64 {
+        let weight = self.weight.read().unwrap();
+        *weight
+    }
+
+    fn set_weight(&mut self, weight: f64) {
+        let mut weight_ptr = self.weight.write().unwrap();
+        *weight_ptr = weight;
+    }
+}
+
+impl Matcher for TokenSortMatcher {
+    fn get_score(&self, s1: &str, s2: &str) -> f64 {
+        let tokens_1 = tokenize::tokenize(&self.clean(s1)[..]);
+        let tokens_2 = tokenize::tokenize(&self.clean(s2)[..]);
+        if self.tokenization == Tokenization::Segmented {
+            let (score, _, _) = tokenize::best_segmentation(&tokens_1, &tokens_2, |t1, t2| {
+                self.score_tokens(t1, t2)
+            });
+            return score;
+        }
+        self.score_tokens(&tokens_1, &tokens_2)
+    }
+
+    fn get_spec(&self) -> MatcherSpec {
+        let spec = MatcherSpec::new("Token-Sort")
+            .with_weight(self.get_weight())
+            .with_matcher(self.matcher.get_spec());
+        with_tokenization(spec, self.tokenization)
+    }
+}
+
+// TokenSetMatcher wraps any Matcher and compares the tokens two names have
+// in common against each name's remaining tokens. The best of the three
+// comparisons is returned, which makes extra name components harmless.
+// Eg:
+// "John Adam Doe" vs "Doe John"
+// -> t0 = "DOE JOHN", t1 = "DOE JOHN ADAM", t2 = "DOE JOHN"
+// -> max(score(t0, t1), score(t0, t2), score(t1, t2))
+pub struct TokenSetMatcher {
+    name: String,
+    matcher: Box<dyn Matcher>,
+    weight: RwLock<f64>,
+    tokenization: Tokenization,
+}
+
+impl TokenSetMatcher {
+    pub fn new(matcher: Box<dyn Matcher>, weight: Option<f64>) -> TokenSetMatcher {
+        let weight = weight.unwrap_or(1.0);
+        let locked_weight = RwLock::new(weight);
+        TokenSetMatcher {
+            name: format!("Token-Set({})", matcher.get_name()),
+            matcher,
+            weight: locked_weight,
+            tokenization: Tokenization::Whitespace,
+        }
+    }
+
+    fn score_tokens(&self, tokens_1: &[String], tokens_2: &[String]) -> f64 {
+        let (t0, t1, t2) =
+            compute::token_set_variants(&tokens_1.join(" ")[..], &tokens_2.join(" ")[..]);
+        let scores = [
+            self.matcher.get_score(&t0[..], &t1[..]),
+            self.matcher.get_score(&t0[..], &t2[..]),
+            self.matcher.get_score(&t1[..], &t2[..]),
+        ];
+        scores.iter().cloned().fold(0.0, f64::max)
+    }
+
+    // from_spec builds the matcher described by spec around the matcher
+    // wrapped by spec, tokenization is Whitespace unless given.
+    pub fn from_spec(
+        spec: &MatcherSpec,
+        matcher: Box<dyn Matcher>,
+    ) -> Result<TokenSetMatcher, SpecError> {
+        spec.check_params(&["tokenization"])?;
+        let mut wrapper = TokenSetMatcher::new(matcher, spec.weight);
+        if let Some(tokenization) = spec.tokenization_param("tokenization")? {
+            wrapper.set_tokenization(tokenization);
+        }
+        Ok(wrapper)
+    }
+}
+
+impl Named for TokenSetMatcher {
+    fn get_name(&self) -> &str {
+        &self.name[..]
+    }
+}
+
+impl Clean for TokenSetMatcher {}
+
+impl Tokenized for TokenSetMatcher {
+    fn get_tokenization(&self) -> Tokenization {
+        self.tokenization
+    }
+
+    fn set_tokenization(&mut self, tokenization: Tokenization) {
+        self.tokenization = tokenization;
+    }
+}
+
+impl Weighted for TokenSetMatcher {
+    fn get_weight(&self) -> f64 {
+        let weight = self.weight.read().unwrap();
+        *weight
+    }
+
+    fn set_weight(&mut self, weight: f64) {
+        let mut weight_ptr = self.weight.write().unwrap();
+        *weight_ptr = weight;
+    }
+}
+
+impl Matcher for TokenSetMatcher {
+    fn get_score(&self, s1: &str, s2: &str) -> f64 {
+        let tokens_1 = tokenize::tokenize(&self.clean(s1)[..]);
+        let tokens_2 = tokenize::tokenize(&self.clean(s2)[..]);
+        if self.tokenization == Tokenization::Segmented {
+            let (score, _, _) = tokenize::best_segmentation(&tokens_1, &tokens_2, |t1, t2| {
+                self.score_tokens(t1, t2)
+            });
+            return score;
+        }
+        self.score_tokens(&tokens_1, &tokens_2)
+    }
+
+    fn get_spec(&self) -> MatcherSpec {
+        let spec = MatcherSpec::new("Token-Set")
+            .with_weight(self.get_weight())
+            .with_matcher(self.matcher.get_spec());
+        with_tokenization(spec, self.tokenization)
+    }
+}
+
+// PartialMatcher wraps any Matcher and compares the shorter name against
+// every window of the longer name with the same number of characters.
+// The best scoring window is returned.
+// Eg:
+// "John Doe" vs "Mr John Doe Jr"
+// -> "JOHN DOE" vs ["MR JOHN ", "R JOHN D", ..., " DOE JR"]
+pub struct PartialMatcher {
+    name: String,
+    matcher: Box<dyn Matcher>,
+    weight: RwLock<f64>,
+}
+
+impl PartialMatcher {
+    pub fn new(matcher: Box<dyn Matcher>, weight: Option<f64>) -> PartialMatcher {
+        let weight = weight.unwrap_or(1.0);
+        let locked_weight = RwLock::new(weight);
+        PartialMatcher {
+            name: format!("Partial({})", matcher.get_name()),
+            matcher,
+            weight: locked_weight,
+        }
+    }
+
+    // from_spec builds the matcher described by spec around the matcher
+    // wrapped by spec.
+    pub fn from_spec(
+        spec: &MatcherSpec,
+        matcher: Box<dyn Matcher>,
+    ) -> Result<PartialMatcher, SpecError> {
+        spec.check_params(&[])?;
+        Ok(PartialMatcher::new(matcher, spec.weight))
+    }
+}
+
+impl Named for PartialMatcher {
+    fn get_name(&self) -> &str {
+        &self.name[..]
+    }
+}
+
+impl Clean for PartialMatcher {}
+
+impl Weighted for PartialMatcher {
+    fn get_weight(&self) -> f64 {
+        let weight = self.weight.read().unwrap();
+        *weight
+    }
+
+    fn set_weight(&mut self, weight: f64) {
+        let mut weight_ptr = self.weight.write().unwrap();
+        *weight_ptr = weight;
+    }
+}
+
+impl Matcher for PartialMatcher {
+    fn get_score(&self, s1: &str, s2: &str) -> f64 {
+        let cleaned_s1 = self.clean(s1);
+        let cleaned_s2 = self.clean(s2);
+        let (shorter, longer) = if cleaned_s1.chars().count() <= cleaned_s2.chars().count() {
+            (cleaned_s1, cleaned_s2)
+        } else {
+            (cleaned_s2, cleaned_s1)
+        };
+
+        let mut best = 0.0;
+        for window in compute::windows(&longer[..], shorter.chars().count()) {
+            let score = self.matcher.get_score(&shorter[..], &window[..]);
+            if score > best {
+                best = score;
+            }
+            if best >= 1.0 {
+                break;
+            }
+        }
+        best
+    }
+
+    fn get_spec(&self) -> MatcherSpec {
+        MatcherSpec::new("Partial")
+            .with_weight(self.get_weight())
+            .with_matcher(self.matcher.get_spec())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::super::jaccard::compare::JaccardMatcher;
+    use super::super::super::jaro::compare::JaroWinklerMatcher;
+    use super::super::super::prelude::*;
+    use super::{PartialMatcher, TokenSetMatcher, TokenSortMatcher};
+
+    #[test]
+    fn test_token_sort_transposed_name() {
+        let matcher = TokenSortMatcher::new(Box::new(JaroWinklerMatcher::default()), None);
+        let name1 = "Doe John";
+        let name2 = "john doe";
+        assert_eq!(matcher.get_score(name1, name2), 1.0);
+    }
+
+    #[test]
+    fn test_token_sort_name() {
+        let matcher = TokenSortMatcher::new(Box::new(JaroWinklerMatcher::default()), None);
+        assert_eq!(matcher.get_name(), "Token-Sort(Jaro-Winkler)");
+    }
+
+    #[test]
+    fn test_token_sort_half_weight() {
+        let matcher = TokenSortMatcher::new(Box::new(JaroWinklerMatcher::default()), Some(0.5));
+        let name1 = "JOHN DOE";
+        let name2 = "DOE JOHN";
+        assert_eq!(matcher.get_weighted_score(name1, name2), 0.5);
+    }
+
+    #[test]
+    fn test_token_sort_segmented() {
+        let mut matcher = TokenSortMatcher::new(Box::new(JaroWinklerMatcher::default()), None);
+        matcher.set_tokenization(Tokenization::Segmented);
+        let name1 = "Doe Mary Ann";
+        let name2 = "Maryann Doe";
+        assert_eq!(matcher.get_score(name1, name2), 1.0);
+    }
+
+    #[test]
+    fn test_token_set_extra_component() {
+        let matcher = TokenSetMatcher::new(Box::new(JaroWinklerMatcher::default()), None);
+        let name1 = "John Adam Doe";
+        let name2 = "Doe, John";
+        assert_eq!(matcher.get_score(name1, name2), 1.0);
+    }
+
+    #[test]
+    fn test_token_set_no_common_tokens() {
+        let matcher = TokenSetMatcher::new(Box::new(JaccardMatcher::default()), None);
+        let name1 = "John Doe";
+        let name2 = "Mary Smith";
+        assert_eq!(matcher.get_score(name1, name2), 0.0);
+    }
+
+    #[test]
+    fn test_token_set_name() {
+        let matcher = TokenSetMatcher::new(Box::new(JaccardMatcher::default()), None);
+        assert_eq!(matcher.get_name(), "Token-Set(Jaccard)");
+    }
+
+    #[test]
+    fn test_token_set_segmented() {
+        let mut matcher = TokenSetMatcher::new(Box::new(JaccardMatcher::default()), None);
+        matcher.set_tokenization(Tokenization::Segmented);
+        let name1 = "Ronald Mc Donald";
+        let name2 = "McDonald Ronald";
+        assert_eq!(matcher.get_score(name1, name2), 1.0);
+    }
+
+    #[test]
+    fn test_partial_embedded_name() {
+        let matcher = PartialMatcher::new(Box::new(JaroWinklerMatcher::default()), None);
+        let name1 = "John Doe";
+        let name2 = "Payment to John Doe ref";
+        assert_eq!(matcher.get_score(name1, name2), 1.0);
+    }
+
+    #[test]
+    fn test_partial_is_symmetric() {
+        let matcher = PartialMatcher::new(Box::new(JaroWinklerMatcher::default()), None);
+        let name1 = "Jon Doe";
+        let name2 = "Mr John Doe";
+        assert_eq!(
+            matcher.get_score(name1, name2),
+            matcher.get_score(name2, name1)
+        );
+    }
+
+    #[test]
+    fn test_partial_name() {
+        let matcher = PartialMatcher::new(Box::new(JaroWinklerMatcher::default()), None);
+        assert_eq!(matcher.get_name(), "Partial(Jaro-Winkler)");
+    }
+}
@@ -0,0 +1,87 @@
+use std::collections::BTreeSet;
+
+// sort_tokens splits a name by whitespace and joins its tokens
+// back in alphabetical order.
+// Eg:
+//   "DOE JOHN" -> "DOE JOHN"
+//   "JOHN DOE" -> "DOE JOHN"
+pub fn sort_tokens(name: &str) -> String {
+    let mut tokens: Vec<&str> = name.split_whitespace().collect();
+    tokens.sort_unstable();
+    tokens.join(" ")
+}
+
+// token_set_variants returns the three strings compared by the token set ratio:
+//   - t0: Sorted intersection of both token sets.
+//   - t1: t0 followed by the sorted tokens only found in s1.
+//   - t2: t0 followed by the sorted tokens only found in s2.
+// Eg:
+// "JOHN ADAM DOE" vs "DOE JOHN"
+// -> ("DOE JOHN", "DOE JOHN ADAM", "DOE JOHN")
+pub fn token_set_variants(s1: &str, s2: &str) -> (String, String, String) {
+    let set_1: BTreeSet<&str> = s1.split_whitespace().collect();
+    let set_2: BTreeSet<&str> = s2.split_whitespace().collect();
+
+    let intersection: Vec<&str> = set_1.intersection(&set_2).cloned().collect();
+    let diff_1: Vec<&str> = set_1.difference(&set_2).cloned().collect();
+    let diff_2: Vec<&str> = set_2.difference(&set_1).cloned().collect();
+
+    let t0 = intersection.join(" ");
+    let t1 = [&t0[..], &diff_1.join(" ")[..]].join(" ").trim().to_owned();
+    let t2 = [&t0[..], &diff_2.join(" ")[..]].join(" ").trim().to_owned();
+    (t0, t1, t2)
+}
+
+// windows returns every substring of name that is window_size
+// characters long, from left to right.
+// Eg:
+//   ("JOHN", 3) -> ["JOH", "OHN"]
+pub fn windows(name: &str, window_size: usize) -> Vec<String> {
+    let chars: Vec<char> = name.chars().collect();
+    if window_size == 0 || window_size >= chars.len() {
+        return vec![name.to_owned()];
+    }
+    chars
+        .windows(window_size)
+        .map(|window| window.iter().collect())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn test_sort_tokens() {
+        assert_eq!(super::sort_tokens("JOHN  DOE"), "DOE JOHN");
+    }
+
+    #[test]
+    fn test_sort_tokens_empty() {
+        assert_eq!(super::sort_tokens(""), "");
+    }
+
+    #[test]
+    fn test_token_set_variants() {
+        let (t0, t1, t2) = super::token_set_variants("JOHN ADAM DOE", "DOE JOHN");
+        assert_eq!(t0, "DOE JOHN");
+        assert_eq!(t1, "DOE JOHN ADAM");
+        assert_eq!(t2, "DOE JOHN");
+    }
+
+    #[test]
+    fn test_token_set_variants_no_intersection() {
+        let (t0, t1, t2) = super::token_set_variants("JOHN", "MARY");
+        assert_eq!(t0, "");
+        assert_eq!(t1, "JOHN");
+        assert_eq!(t2, "MARY");
+    }
+
+    #[test]
+    fn test_windows() {
+        assert_eq!(super::windows("JOHN", 3), vec!["JOH", "OHN"]);
+    }
+
+    #[test]
+    fn test_windows_larger_than_name() {
+        assert_eq!(super::windows("JOHN", 5), vec!["JOHN"]);
+    }
+}
@@ -0,0 +1,2 @@
+pub mod compare;
+mod compute;